@@ -0,0 +1,318 @@
+use anise::math::Vector3;
+
+use crate::{algo::{look::EARTH_ROTATION_RAD_S, sun::{beta_and_orbit_angle, orbit_normal, EARTH_RADIUS_KM}}, product::satellite::{Constellation, SatelliteId}};
+
+// Nominal attitude follows the IGS body frame convention: +Z towards the Earth center, +Y along the
+// solar panel axis and +X completing the frame towards the sunlit hemisphere. Yaw is measured from
+// the along-track direction to +X. Orbit angles are measured from orbit midnight, as in sun.rs.
+
+// step used to propagate rate limited turns
+const TURN_STEP_S:f64 = 1.0;
+// turns are assumed to be complete this long before the epoch, longer than any GNSS eclipse
+const TURN_HISTORY_S:f64 = 4_000.0;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub enum BlockType {
+    GpsIIR,
+    GpsIIRM,
+    GpsIIF,
+    GpsIII,
+    GlonassM,
+    GlonassK,
+    GalileoIov,
+    GalileoFoc,
+    BeiDou2Meo,
+    BeiDou2Igso,
+    BeiDou2Geo,
+    BeiDou3Meo,
+    BeiDou3Igso,
+    BeiDou3Geo,
+    Unknown,
+}
+
+impl BlockType {
+
+    // most common block of each constellation, used when no satellite metadata is available
+    pub fn from_satellite(satellite:SatelliteId) -> Self {
+        match satellite.constellation {
+            Constellation::Gps => BlockType::GpsIIR,
+            Constellation::Glonass => BlockType::GlonassM,
+            Constellation::Galileo => BlockType::GalileoFoc,
+            Constellation::BeiDou => BlockType::BeiDou3Meo,
+            _ => BlockType::Unknown,
+        }
+    }
+
+    // block names as used in the IGS satellite metadata SINEX
+    pub fn from_igs_block(block:&str) -> Self {
+        match block.to_uppercase().as_str() {
+            "GPS-IIR" | "GPS-IIR-A" | "GPS-IIR-B" => BlockType::GpsIIR,
+            "GPS-IIR-M" => BlockType::GpsIIRM,
+            "GPS-IIF" => BlockType::GpsIIF,
+            b if b.starts_with("GPS-III") => BlockType::GpsIII,
+            b if b.starts_with("GLO-M") => BlockType::GlonassM,
+            b if b.starts_with("GLO-K") => BlockType::GlonassK,
+            "GAL-1" => BlockType::GalileoIov,
+            "GAL-2" => BlockType::GalileoFoc,
+            "BDS-2M" => BlockType::BeiDou2Meo,
+            "BDS-2I" => BlockType::BeiDou2Igso,
+            "BDS-2G" => BlockType::BeiDou2Geo,
+            b if b.starts_with("BDS-3M") => BlockType::BeiDou3Meo,
+            b if b.starts_with("BDS-3I") => BlockType::BeiDou3Igso,
+            b if b.starts_with("BDS-3G") => BlockType::BeiDou3Geo,
+            _ => BlockType::Unknown,
+        }
+    }
+
+    // ionosphere-free antenna phase center offset from the center of mass in the body frame, meters.
+    // Block means of the IGS ANTEX and the MGEX conventional values where no estimate exists.
+    pub fn pco_m(&self) -> Vector3 {
+        match self {
+            BlockType::GpsIIR => Vector3::new(0.0, 0.0, 1.30),
+            BlockType::GpsIIRM => Vector3::new(0.0, 0.0, 0.95),
+            BlockType::GpsIIF => Vector3::new(0.394, 0.0, 1.10),
+            BlockType::GpsIII => Vector3::new(0.0, 0.0, 0.80),
+            BlockType::GlonassM => Vector3::new(-0.545, 0.0, 2.30),
+            BlockType::GlonassK => Vector3::new(0.0, 0.0, 1.80),
+            BlockType::GalileoIov => Vector3::new(-0.20, 0.0, 0.60),
+            BlockType::GalileoFoc => Vector3::new(0.15, -0.01, 0.80),
+            BlockType::BeiDou2Meo | BlockType::BeiDou2Igso | BlockType::BeiDou2Geo => Vector3::new(0.60, 0.0, 1.10),
+            BlockType::BeiDou3Meo | BlockType::BeiDou3Igso | BlockType::BeiDou3Geo => Vector3::new(0.0, 0.0, 1.00),
+            BlockType::Unknown => Vector3::zeros(),
+        }
+    }
+
+    fn yaw_model(&self) -> YawModel {
+        match self {
+            // Kouba (2009), noon and midnight turns at the hardware yaw rate limit
+            BlockType::GpsIIR | BlockType::GpsIIRM => YawModel::RateLimited { max_rate_deg_s: 0.2, shadow: ShadowYaw::RateLimited },
+            // Kouba (2009), noon turns at 0.11 deg/s, shadow crossings at a constant rate
+            BlockType::GpsIIF => YawModel::RateLimited { max_rate_deg_s: 0.11, shadow: ShadowYaw::ConstantRate },
+            // treated like the IIR until an operator model is published
+            BlockType::GpsIII => YawModel::RateLimited { max_rate_deg_s: 0.2, shadow: ShadowYaw::RateLimited },
+            // Dilssner et al. (2011), shadow crossings at the rate limit then held until shadow exit
+            BlockType::GlonassM | BlockType::GlonassK => YawModel::RateLimited { max_rate_deg_s: 0.25, shadow: ShadowYaw::RateLimitedThenHold },
+            // Galileo satellite metadata, cosine smoothed yaw steering near noon and midnight
+            BlockType::GalileoIov => YawModel::Smoothed { beta_threshold_deg: 2.0, window_deg: 15.0 },
+            BlockType::GalileoFoc => YawModel::Smoothed { beta_threshold_deg: 4.1, window_deg: 10.0 },
+            // orbit normal mode at low beta, GEOs always orbit normal
+            BlockType::BeiDou2Meo | BlockType::BeiDou2Igso => YawModel::OrbitNormal { beta_threshold_deg: 4.0 },
+            BlockType::BeiDou2Geo | BlockType::BeiDou3Geo => YawModel::OrbitNormal { beta_threshold_deg: 90.0 },
+            BlockType::BeiDou3Meo | BlockType::BeiDou3Igso => YawModel::RateLimited { max_rate_deg_s: 0.085, shadow: ShadowYaw::RateLimited },
+            BlockType::Unknown => YawModel::Nominal,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub enum AttitudeMode {
+    YawSteering,
+    NoonTurn,
+    MidnightTurn,
+    OrbitNormal,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Attitude {
+    pub block:BlockType,
+    pub mode:AttitudeMode,
+    pub yaw_deg:f64,
+    pub nominal_yaw_deg:f64,
+    pub yaw_rate_deg_s:f64,
+    // body axes as ECEF unit vectors
+    #[schema(value_type = Vec<f64>)]
+    pub x_axis:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub y_axis:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub z_axis:Vector3,
+}
+
+enum ShadowYaw {
+    RateLimited,
+    ConstantRate,
+    RateLimitedThenHold,
+}
+
+enum YawModel {
+    Nominal,
+    RateLimited { max_rate_deg_s:f64, shadow:ShadowYaw },
+    Smoothed { beta_threshold_deg:f64, window_deg:f64 },
+    OrbitNormal { beta_threshold_deg:f64 },
+}
+
+// Nominal yaw steering angle (Bar-Sever 1996) in degrees
+pub fn nominal_yaw_deg(beta_deg:f64, orbit_angle_deg:f64) -> f64 {
+    (-beta_deg.to_radians().tan()).atan2(orbit_angle_deg.to_radians().sin()).to_degrees()
+}
+
+// Nominal yaw rate in deg/s for an orbit angle rate in deg/s
+pub fn nominal_yaw_rate_deg_s(beta_deg:f64, orbit_angle_deg:f64, orbit_rate_deg_s:f64) -> f64 {
+    let tan_beta = beta_deg.to_radians().tan();
+    let (sin_mu, cos_mu) = orbit_angle_deg.to_radians().sin_cos();
+    let denominator = sin_mu * sin_mu + tan_beta * tan_beta;
+
+    // the nominal law is singular at noon and midnight when beta is exactly zero
+    if denominator == 0.0 {
+        return 0.0;
+    }
+
+    orbit_rate_deg_s * tan_beta * cos_mu / denominator
+}
+
+fn wrap_deg(angle:f64) -> f64 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+// signed distance of the orbit angle from orbit midnight, in (-180, 180]
+fn from_midnight_deg(orbit_angle_deg:f64) -> f64 {
+    wrap_deg(orbit_angle_deg)
+}
+
+fn from_noon_deg(orbit_angle_deg:f64) -> f64 {
+    wrap_deg(orbit_angle_deg - 180.0)
+}
+
+// half width in orbit angle of the cylindrical Earth shadow around orbit midnight, None without eclipses
+fn shadow_half_width_deg(beta_deg:f64, radius_km:f64) -> Option<f64> {
+    let cos_limit = (1.0 - (EARTH_RADIUS_KM / radius_km).powi(2)).sqrt() / beta_deg.to_radians().cos();
+    (cos_limit < 1.0).then(|| cos_limit.acos().to_degrees())
+}
+
+// Yaw angle and mode for a block type, given beta, the orbit angle and its rate (deg/s) and the orbit radius
+pub fn yaw(block:BlockType, beta_deg:f64, orbit_angle_deg:f64, orbit_rate_deg_s:f64, radius_km:f64) -> (f64, f64, AttitudeMode) {
+
+    let nominal = nominal_yaw_deg(beta_deg, orbit_angle_deg);
+    let nominal_rate = nominal_yaw_rate_deg_s(beta_deg, orbit_angle_deg, orbit_rate_deg_s);
+    let turn_mode = if from_noon_deg(orbit_angle_deg).abs() < 90.0 { AttitudeMode::NoonTurn } else { AttitudeMode::MidnightTurn };
+
+    match block.yaw_model() {
+        YawModel::Nominal => (nominal, nominal_rate, AttitudeMode::YawSteering),
+
+        YawModel::OrbitNormal { beta_threshold_deg } => {
+            if beta_deg.abs() < beta_threshold_deg {
+                (0.0, 0.0, AttitudeMode::OrbitNormal)
+            } else {
+                (nominal, nominal_rate, AttitudeMode::YawSteering)
+            }
+        }
+
+        YawModel::Smoothed { beta_threshold_deg, window_deg } => {
+            let offset = if turn_mode == AttitudeMode::NoonTurn { from_noon_deg(orbit_angle_deg) } else { from_midnight_deg(orbit_angle_deg) };
+
+            if beta_deg.abs() >= beta_threshold_deg || offset.abs() >= window_deg {
+                return (nominal, nominal_rate, AttitudeMode::YawSteering);
+            }
+
+            // yaw swings through -90 sign(beta) following a half cosine across the turn window
+            let start = nominal_yaw_deg(beta_deg, orbit_angle_deg - offset - window_deg);
+            let center = -90.0 * beta_deg.signum();
+            let phase = (offset + window_deg) / (2.0 * window_deg) * std::f64::consts::PI;
+            let smoothed = center + wrap_deg(start - center) * phase.cos();
+            let rate = -wrap_deg(start - center) * phase.sin() * std::f64::consts::PI / (2.0 * window_deg) * orbit_rate_deg_s;
+
+            (wrap_deg(smoothed), rate, turn_mode)
+        }
+
+        YawModel::RateLimited { max_rate_deg_s, shadow } => {
+            let shadow_half_width = shadow_half_width_deg(beta_deg, radius_km);
+            let in_shadow = shadow_half_width.is_some_and(|w| from_midnight_deg(orbit_angle_deg).abs() < w);
+
+            if in_shadow && matches!(shadow, ShadowYaw::ConstantRate) {
+                // constant yaw rate from the nominal yaw at shadow entry to the nominal yaw at exit
+                let half_width = shadow_half_width.unwrap();
+                let entry = nominal_yaw_deg(beta_deg, -half_width);
+                let exit = nominal_yaw_deg(beta_deg, half_width);
+                let mut change = wrap_deg(exit - entry);
+                if change.signum() != (beta_deg.signum() * orbit_rate_deg_s.signum()) {
+                    change -= 360.0 * change.signum();
+                }
+                let progress = (from_midnight_deg(orbit_angle_deg) + half_width) / (2.0 * half_width);
+                let rate = change / (2.0 * half_width) * orbit_rate_deg_s;
+                return (wrap_deg(entry + change * progress), rate, AttitudeMode::MidnightTurn);
+            }
+
+            // propagate the yaw from a point where it followed the nominal law, limiting its rate
+            let steps = (TURN_HISTORY_S / TURN_STEP_S) as usize;
+            let start_angle = orbit_angle_deg - orbit_rate_deg_s * TURN_HISTORY_S;
+            let mut yaw = nominal_yaw_deg(beta_deg, start_angle);
+            let mut rate = 0.0;
+
+            for step in 1..=steps {
+                let mu = start_angle + orbit_rate_deg_s * TURN_STEP_S * step as f64;
+                let max_step = max_rate_deg_s * TURN_STEP_S;
+
+                let mu_in_shadow = shadow_half_width.is_some_and(|w| from_midnight_deg(mu).abs() < w);
+
+                let target = match (&shadow, mu_in_shadow) {
+                    // turn towards the shadow exit attitude, then hold it
+                    (ShadowYaw::RateLimitedThenHold, true) => nominal_yaw_deg(beta_deg, shadow_half_width.unwrap()),
+                    _ => nominal_yaw_deg(beta_deg, mu),
+                };
+
+                let mut delta = wrap_deg(target - yaw);
+                if delta.abs() > max_step {
+                    // turn at the rate limit in the direction of the nominal yaw rate
+                    let direction = nominal_yaw_rate_deg_s(beta_deg, mu, orbit_rate_deg_s).signum();
+                    delta = max_step * if direction == 0.0 { delta.signum() } else { direction };
+                }
+
+                yaw = wrap_deg(yaw + delta);
+                rate = delta / TURN_STEP_S;
+            }
+
+            if wrap_deg(yaw - nominal).abs() < 1e-3 {
+                (nominal, nominal_rate, AttitudeMode::YawSteering)
+            } else {
+                (yaw, rate, turn_mode)
+            }
+        }
+    }
+}
+
+// Attitude from ECEF position (km) and velocity (km/s), the Sun geometry and the satellite block type
+pub fn attitude(block:BlockType, pos:&Vector3, vel:&Vector3, beta_deg:f64, orbit_angle_deg:f64) -> Attitude {
+
+    let normal = orbit_normal(pos, vel);
+    let radial = pos.normalize();
+    let along_track = normal.cross(&radial);
+
+    // orbit angle rate from the along-track component of the inertial velocity
+    let inertial_vel = vel + Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(pos);
+    let orbit_rate_deg_s = (along_track.dot(&inertial_vel) / pos.norm()).to_degrees();
+
+    let nominal_yaw = nominal_yaw_deg(beta_deg, orbit_angle_deg);
+    let (yaw_deg, yaw_rate_deg_s, mode) = yaw(block, beta_deg, orbit_angle_deg, orbit_rate_deg_s, pos.norm());
+
+    let (sin_yaw, cos_yaw) = yaw_deg.to_radians().sin_cos();
+
+    let z_axis = -radial;
+    let x_axis = cos_yaw * along_track - sin_yaw * normal;
+    let y_axis = z_axis.cross(&x_axis);
+
+    Attitude {
+        block,
+        mode,
+        yaw_deg,
+        nominal_yaw_deg: nominal_yaw,
+        yaw_rate_deg_s,
+        x_axis,
+        y_axis,
+        z_axis,
+    }
+}
+
+// Antenna phase center offset in ECEF km at a center of mass position and velocity, rotated by the
+// modelled attitude
+pub fn pco_ecef_km(block:BlockType, pos:&Vector3, vel:&Vector3, sun_pos:&Vector3) -> Vector3 {
+
+    let pco = block.pco_m();
+    if pco == Vector3::zeros() {
+        return pco;
+    }
+
+    let (beta_deg, orbit_angle_deg) = beta_and_orbit_angle(pos, vel, sun_pos);
+    let attitude = attitude(block, pos, vel, beta_deg, orbit_angle_deg);
+
+    (pco.x * attitude.x_axis + pco.y * attitude.y_axis + pco.z * attitude.z_axis) / 1_000.0
+}
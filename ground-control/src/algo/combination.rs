@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+
+use anise::math::Vector3;
+use anyhow::anyhow;
+
+use crate::{algo::util::invert_matrix, product::{satellite::SatelliteId, sv::{DataSource, Orbit, SVSource}}};
+
+pub const COMBINATION_ANALYSIS_CENTER:&str = "cmb";
+
+// coordinates are scaled before the Helmert estimation to keep the normal matrix well conditioned
+const HELMERT_COORDINATE_SCALE_KM:f64 = 10_000.0;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct CombinationConfig {
+    // an AC is rejected for a satellite when its RMS exceeds this multiple of the median AC RMS
+    pub outlier_factor:f64,
+    // RMS below which an AC is never rejected and floor for the weights, in km
+    pub min_rms_km:f64,
+    // satellites with fewer accepted ACs are left out of the combination
+    pub min_acs:usize,
+}
+
+impl Default for CombinationConfig {
+    fn default() -> Self {
+        CombinationConfig { outlier_factor: 5.0, min_rms_km: 0.00001, min_acs: 2 }
+    }
+}
+
+// 7-parameter similarity transformation, small angle approximation:
+// x' = x + T + scale * x + rotation x x
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub struct Helmert {
+    #[schema(value_type = Vec<f64>)]
+    pub translation_km:[f64; 3],
+    pub scale:f64,
+    #[schema(value_type = Vec<f64>)]
+    pub rotation_rad:[f64; 3],
+}
+
+impl Helmert {
+
+    pub fn identity() -> Self {
+        Helmert { translation_km: [0.0; 3], scale: 0.0, rotation_rad: [0.0; 3] }
+    }
+
+    pub fn apply(&self, pos:&Vector3) -> Vector3 {
+        let translation = Vector3::new(self.translation_km[0], self.translation_km[1], self.translation_km[2]);
+        let rotation = Vector3::new(self.rotation_rad[0], self.rotation_rad[1], self.rotation_rad[2]);
+        pos + translation + self.scale * pos + rotation.cross(pos)
+    }
+
+    // Least squares transformation taking the `from` positions onto the `to` positions,
+    // None when the geometry does not determine all seven parameters
+    pub fn estimate(pairs:&[(Vector3, Vector3)]) -> Option<Helmert> {
+
+        let mut normal = vec![vec![0.0; 7]; 7];
+        let mut rhs = [0.0; 7];
+
+        for (from, to) in pairs {
+            let x = from / HELMERT_COORDINATE_SCALE_KM;
+            let d = to - from;
+
+            // partials of the x, y and z differences by [tx, ty, tz, scale, rx, ry, rz]
+            let rows = [
+                [1.0, 0.0, 0.0, x.x, 0.0, x.z, -x.y],
+                [0.0, 1.0, 0.0, x.y, -x.z, 0.0, x.x],
+                [0.0, 0.0, 1.0, x.z, x.y, -x.x, 0.0],
+            ];
+
+            for (row, residual) in rows.iter().zip([d.x, d.y, d.z]) {
+                for i in 0..7 {
+                    rhs[i] += row[i] * residual;
+                    for j in 0..7 {
+                        normal[i][j] += row[i] * row[j];
+                    }
+                }
+            }
+        }
+
+        let inverse = invert_matrix(&normal)?;
+        let p:Vec<f64> = (0..7).map(|i| (0..7).map(|j| inverse[i][j] * rhs[j]).sum()).collect();
+
+        Some(Helmert {
+            translation_km: [p[0], p[1], p[2]],
+            scale: p[3] / HELMERT_COORDINATE_SCALE_KM,
+            rotation_rad: [p[4] / HELMERT_COORDINATE_SCALE_KM, p[5] / HELMERT_COORDINATE_SCALE_KM, p[6] / HELMERT_COORDINATE_SCALE_KM],
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct AcContribution {
+    pub satellite:SatelliteId,
+    pub data_source:DataSource,
+    // 3D RMS against the median of the aligned ACs
+    pub rms_km:f64,
+    pub rejected:bool,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct AcSummary {
+    pub data_source:DataSource,
+    pub helmert:Helmert,
+    pub rms_km:f64,
+    pub weight:f64,
+    pub satellites:usize,
+    pub rejected_satellites:Vec<SatelliteId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct CombinationReport {
+    pub data_source:DataSource,
+    pub analysis_centers:Vec<AcSummary>,
+    pub contributions:Vec<AcContribution>,
+    // satellites without enough accepted ACs
+    pub skipped_satellites:Vec<SatelliteId>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Combination {
+    pub orbits:Vec<Orbit>,
+    pub report:CombinationReport,
+}
+
+// one satellite sampled on the reference epochs, with the position and clock of each AC
+struct SatelliteSamples {
+    satellite:SatelliteId,
+    reference:usize,
+    epochs:Vec<f64>,
+    positions:Vec<Vec<(usize, Vector3)>>,
+    clocks:Vec<Vec<(usize, f64)>>,
+}
+
+// combined product named after the first input source, e.g. cddis_cod_fin -> cddis_cmb_fin
+pub fn combined_source(sources:&[DataSource]) -> Option<DataSource> {
+    sources.first().map(|source| DataSource {
+        source: source.source.clone(),
+        analysis_center: COMBINATION_ANALYSIS_CENTER.to_string(),
+        product_type: source.product_type.clone(),
+    })
+}
+
+fn epoch_key(epoch:f64) -> i64 {
+    (epoch * 1000.0).round() as i64
+}
+
+fn sample_index(orbit:&Orbit, epoch:f64) -> Option<usize> {
+    let index = ((epoch - orbit.valid_from) / orbit.sampling_resolution).round();
+    if index < 0.0 {
+        return None;
+    }
+    let index = index as usize;
+    (orbit.epochs.get(index) == Some(&epoch)).then_some(index)
+}
+
+// tabulated sample when the epoch is on the orbit grid, interpolated otherwise
+fn sample_at(orbit:&Orbit, epoch:f64) -> Option<Vector3> {
+    match sample_index(orbit, epoch) {
+        Some(index) => Some(orbit.pos_ecef_km[index]),
+        None => orbit.position_at(epoch).ok(),
+    }
+}
+
+fn clock_at(orbit:&Orbit, epoch:f64) -> Option<f64> {
+    let index = sample_index(orbit, epoch)?;
+    orbit.clock_usec.as_ref()?.get(index).copied()
+}
+
+fn median(values:&mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_position(positions:&[(usize, Vector3)]) -> Vector3 {
+    let component = |i:usize| median(&mut positions.iter().map(|(_, pos)| pos[i]).collect::<Vec<f64>>());
+    Vector3::new(component(0), component(1), component(2))
+}
+
+// Helmert estimate that repeatedly drops the satellite with the largest residual while it exceeds
+// `outlier_factor` times the median satellite residual, so a single bad orbit is not absorbed
+// into the transformation of its AC
+fn robust_helmert(pairs:&[(SatelliteId, Vector3, Vector3)], config:&CombinationConfig) -> Helmert {
+
+    let mut excluded:Vec<SatelliteId> = Vec::new();
+
+    loop {
+        let used:Vec<(Vector3, Vector3)> = pairs.iter()
+            .filter(|(satellite, _, _)| !excluded.contains(satellite))
+            .map(|(_, from, to)| (*from, *to))
+            .collect();
+
+        let Some(helmert) = Helmert::estimate(&used) else {
+            return Helmert::identity();
+        };
+
+        let mut residuals:HashMap<SatelliteId, (f64, usize)> = HashMap::new();
+        for (satellite, from, to) in pairs.iter().filter(|(satellite, _, _)| !excluded.contains(satellite)) {
+            let entry = residuals.entry(*satellite).or_insert((0.0, 0));
+            entry.0 += (helmert.apply(from) - to).norm_squared();
+            entry.1 += 1;
+        }
+
+        let rms:Vec<(SatelliteId, f64)> = residuals.into_iter()
+            .map(|(satellite, (sum_sq, count))| (satellite, (sum_sq / count as f64).sqrt()))
+            .collect();
+
+        let median_rms = median(&mut rms.iter().map(|(_, rms)| *rms).collect::<Vec<f64>>());
+        let Some((worst, worst_rms)) = rms.iter().max_by(|a, b| a.1.total_cmp(&b.1)) else {
+            return helmert;
+        };
+
+        // keep enough satellites to determine the rotations
+        if rms.len() <= 3 || *worst_rms <= (config.outlier_factor * median_rms).max(config.min_rms_km) {
+            return helmert;
+        }
+
+        excluded.push(*worst);
+    }
+}
+
+// Combines orbits of the same satellites from several analysis centers. Each AC is first aligned
+// to the median orbit with a Helmert transformation, ACs deviating from the median by more than
+// `outlier_factor` times the median AC RMS are rejected per satellite (which needs at least three
+// ACs), and the remaining ACs are averaged with weights from their overall RMS. Clocks, when
+// present, are aligned to the first AC by a per epoch median offset before averaging.
+// The combined orbits use the epochs of the first source providing each satellite.
+pub fn combine(orbits:&[Orbit], data_source:&DataSource, config:&CombinationConfig) -> Result<Combination, anyhow::Error> {
+
+    let mut acs:Vec<DataSource> = Vec::new();
+    let mut satellites:Vec<SatelliteId> = Vec::new();
+    for orbit in orbits {
+        if !acs.contains(&orbit.sv.data_source) {
+            acs.push(orbit.sv.data_source.clone());
+        }
+        if !satellites.contains(&orbit.sv.satellite) {
+            satellites.push(orbit.sv.satellite);
+        }
+    }
+
+    if acs.len() < 2 {
+        return Err(anyhow!("Combination needs at least two analysis centers"));
+    }
+
+    let find_orbit = |ac:usize, satellite:SatelliteId| orbits.iter()
+        .find(|orbit| orbit.sv.data_source == acs[ac] && orbit.sv.satellite == satellite);
+
+    // sample every AC on the reference epochs
+    let mut samples:Vec<SatelliteSamples> = Vec::new();
+    for satellite in &satellites {
+        let Some(reference) = (0..acs.len()).find(|ac| find_orbit(*ac, *satellite).is_some()) else {
+            continue;
+        };
+        let epochs = find_orbit(reference, *satellite).unwrap().epochs.clone();
+
+        let mut positions = vec![Vec::new(); epochs.len()];
+        let mut clocks = vec![Vec::new(); epochs.len()];
+        for ac in 0..acs.len() {
+            let Some(orbit) = find_orbit(ac, *satellite) else {
+                continue;
+            };
+            for (i, epoch) in epochs.iter().enumerate() {
+                if let Some(pos) = sample_at(orbit, *epoch) {
+                    positions[i].push((ac, pos));
+                }
+                if let Some(clock) = clock_at(orbit, *epoch) {
+                    clocks[i].push((ac, clock));
+                }
+            }
+        }
+
+        samples.push(SatelliteSamples { satellite: *satellite, reference, epochs, positions, clocks });
+    }
+
+    // align each AC to the preliminary median orbit
+    let mut pairs:Vec<Vec<(SatelliteId, Vector3, Vector3)>> = vec![Vec::new(); acs.len()];
+    for sat in &samples {
+        for positions in &sat.positions {
+            if positions.len() < 2 {
+                continue;
+            }
+            let median = median_position(positions);
+            for (ac, pos) in positions {
+                pairs[*ac].push((sat.satellite, *pos, median));
+            }
+        }
+    }
+
+    let helmerts:Vec<Helmert> = pairs.iter()
+        .map(|pairs| robust_helmert(pairs, config))
+        .collect();
+
+    for sat in samples.iter_mut() {
+        for positions in sat.positions.iter_mut() {
+            for (ac, pos) in positions.iter_mut() {
+                *pos = helmerts[*ac].apply(pos);
+            }
+        }
+    }
+
+    // per satellite RMS of each AC against the aligned median, then outlier rejection
+    let mut contributions:Vec<AcContribution> = Vec::new();
+    let mut accepted:HashMap<(SatelliteId, usize), f64> = HashMap::new();
+
+    for sat in &samples {
+        let mut sum_sq = vec![0.0; acs.len()];
+        let mut count = vec![0usize; acs.len()];
+
+        for positions in &sat.positions {
+            if positions.len() < 2 {
+                continue;
+            }
+            let median = median_position(positions);
+            for (ac, pos) in positions {
+                sum_sq[*ac] += (pos - median).norm_squared();
+                count[*ac] += 1;
+            }
+        }
+
+        let rms:Vec<(usize, f64)> = (0..acs.len())
+            .filter(|ac| count[*ac] > 0)
+            .map(|ac| (ac, (sum_sq[ac] / count[ac] as f64).sqrt()))
+            .collect();
+
+        if rms.is_empty() {
+            continue;
+        }
+
+        let median_rms = median(&mut rms.iter().map(|(_, rms)| *rms).collect::<Vec<f64>>());
+        let threshold = (config.outlier_factor * median_rms).max(config.min_rms_km);
+
+        for (ac, rms_km) in rms {
+            let rejected = rms_km > threshold;
+            if !rejected {
+                accepted.insert((sat.satellite, ac), rms_km);
+            }
+            contributions.push(AcContribution { satellite: sat.satellite, data_source: acs[ac].clone(), rms_km, rejected });
+        }
+    }
+
+    // AC weights from the mean square of the accepted satellites
+    let weights:Vec<f64> = (0..acs.len())
+        .map(|ac| {
+            let squares:Vec<f64> = accepted.iter()
+                .filter(|((_, a), _)| *a == ac)
+                .map(|(_, rms)| rms * rms)
+                .collect();
+            let mean_square = if squares.is_empty() { 0.0 } else { squares.iter().sum::<f64>() / squares.len() as f64 };
+            1.0 / mean_square.max(config.min_rms_km * config.min_rms_km)
+        })
+        .collect();
+
+    // clock datum of each AC relative to the first AC, per epoch
+    let mut clock_offsets:HashMap<(usize, i64), f64> = HashMap::new();
+    let mut differences:HashMap<(usize, i64), Vec<f64>> = HashMap::new();
+    for sat in &samples {
+        for (epoch, clocks) in sat.epochs.iter().zip(&sat.clocks) {
+            let Some((_, reference_clock)) = clocks.iter().find(|(ac, _)| *ac == 0) else {
+                continue;
+            };
+            for (ac, clock) in clocks {
+                differences.entry((*ac, epoch_key(*epoch))).or_default().push(clock - reference_clock);
+            }
+        }
+    }
+    for (key, mut values) in differences {
+        clock_offsets.insert(key, median(&mut values));
+    }
+
+    let mut combined:Vec<Orbit> = Vec::new();
+    let mut skipped_satellites:Vec<SatelliteId> = Vec::new();
+
+    for sat in &samples {
+
+        let accepted_acs:Vec<usize> = (0..acs.len()).filter(|ac| accepted.contains_key(&(sat.satellite, *ac))).collect();
+        if accepted_acs.len() < config.min_acs {
+            skipped_satellites.push(sat.satellite);
+            continue;
+        }
+
+        let mut pos_ecef_km:Vec<Vector3> = Vec::with_capacity(sat.epochs.len());
+        let mut clock_usec:Vec<f64> = Vec::with_capacity(sat.epochs.len());
+        let mut has_clocks = true;
+
+        for (i, epoch) in sat.epochs.iter().enumerate() {
+
+            // epochs without an accepted AC fall back to every AC so the grid stays uniform
+            let mut used:Vec<(usize, Vector3)> = sat.positions[i].iter().filter(|(ac, _)| accepted_acs.contains(ac)).copied().collect();
+            if used.is_empty() {
+                used = sat.positions[i].clone();
+            }
+
+            let weight_sum:f64 = used.iter().map(|(ac, _)| weights[*ac]).sum();
+            let pos = used.iter().fold(Vector3::zeros(), |sum, (ac, pos)| sum + weights[*ac] * pos) / weight_sum;
+            pos_ecef_km.push(pos);
+
+            let clocks:Vec<(f64, f64)> = sat.clocks[i].iter()
+                .filter(|(ac, _)| accepted_acs.contains(ac))
+                .filter_map(|(ac, clock)| clock_offsets.get(&(*ac, epoch_key(*epoch))).map(|offset| (weights[*ac], clock - offset)))
+                .collect();
+
+            if clocks.is_empty() {
+                has_clocks = false;
+            } else {
+                let clock_weight:f64 = clocks.iter().map(|(w, _)| w).sum();
+                clock_usec.push(clocks.iter().map(|(w, clock)| w * clock).sum::<f64>() / clock_weight);
+            }
+        }
+
+        let reference = find_orbit(sat.reference, sat.satellite).unwrap();
+        let product_run_id = (0..acs.len())
+            .filter_map(|ac| find_orbit(ac, sat.satellite))
+            .map(|orbit| orbit.product_run_id)
+            .max()
+            .unwrap_or(reference.product_run_id);
+
+        combined.push(Orbit {
+            sv: SVSource { satellite: sat.satellite, data_source: data_source.clone() },
+            product_run_id,
+            sampling_resolution: reference.sampling_resolution,
+            valid_from: reference.valid_from,
+            valid_to: reference.valid_to,
+            epochs: sat.epochs.clone(),
+            pos_ecef_km,
+            pos_latlonalt: None,
+            clock_usec: has_clocks.then_some(clock_usec),
+            metadata: None,
+            extrapolated: false,
+        });
+    }
+
+    let analysis_centers:Vec<AcSummary> = acs.iter().enumerate()
+        .map(|(ac, source)| {
+            let rms:Vec<f64> = accepted.iter().filter(|((_, a), _)| *a == ac).map(|(_, rms)| *rms).collect();
+            AcSummary {
+                data_source: source.clone(),
+                helmert: helmerts[ac],
+                rms_km: (rms.iter().map(|r| r * r).sum::<f64>() / rms.len().max(1) as f64).sqrt(),
+                weight: weights[ac],
+                satellites: rms.len(),
+                rejected_satellites: contributions.iter()
+                    .filter(|c| c.rejected && c.data_source == *source)
+                    .map(|c| c.satellite)
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(Combination {
+        orbits: combined,
+        report: CombinationReport { data_source: data_source.clone(), analysis_centers, contributions, skipped_satellites },
+    })
+}
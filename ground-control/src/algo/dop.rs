@@ -0,0 +1,95 @@
+use crate::{algo::{look::{look_angles, LookAngles, Observer}, pass::ElevationMask, util::invert_matrix}, product::{satellite::{Constellation, SatelliteId}, sv::Orbit}};
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Dop {
+    pub gdop:f64,
+    pub pdop:f64,
+    pub hdop:f64,
+    pub vdop:f64,
+    pub tdop:f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct DopEpoch {
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub satellites:Vec<SatelliteId>,
+    // None when fewer than four satellites are visible or the geometry is singular
+    pub dop:Option<Dop>,
+}
+
+// Computes DOP from the look angles of the visible satellites using a single receiver clock
+// (no inter-system bias terms), so mixed constellations are treated as one system
+pub fn dop(look_angles:&[LookAngles]) -> Option<Dop> {
+
+    if look_angles.len() < 4 {
+        return None;
+    }
+
+    // normal matrix G^T G with rows [-cos(el) sin(az), -cos(el) cos(az), -sin(el), 1] in ENU
+    let mut normal = vec![vec![0.0; 4]; 4];
+
+    for look in look_angles {
+        let (sin_el, cos_el) = look.elevation_deg.to_radians().sin_cos();
+        let (sin_az, cos_az) = look.azimuth_deg.to_radians().sin_cos();
+        let row = [-cos_el * sin_az, -cos_el * cos_az, -sin_el, 1.0];
+
+        for i in 0..4 {
+            for j in 0..4 {
+                normal[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    let cofactor = invert_matrix(&normal)?;
+
+    let (q_ee, q_nn, q_uu, q_tt) = (cofactor[0][0], cofactor[1][1], cofactor[2][2], cofactor[3][3]);
+
+    Some(Dop {
+        gdop: (q_ee + q_nn + q_uu + q_tt).sqrt(),
+        pdop: (q_ee + q_nn + q_uu).sqrt(),
+        hdop: (q_ee + q_nn).sqrt(),
+        vdop: q_uu.sqrt(),
+        tdop: q_tt.sqrt(),
+    })
+}
+
+// an empty selection includes every satellite
+pub fn constellation_selected(satellite:SatelliteId, constellations:&[Constellation]) -> bool {
+    constellations.is_empty() || constellations.contains(&satellite.constellation)
+}
+
+// Satellites above the mask for an observer at an epoch, restricted to the selected constellations
+pub fn visible_satellites(observer:&Observer, orbits:&[Orbit], epoch:f64, mask:&ElevationMask, constellations:&[Constellation]) -> Vec<LookAngles> {
+
+    orbits.iter()
+        .filter(|orbit| constellation_selected(orbit.sv.satellite, constellations))
+        .filter_map(|orbit| look_angles(observer, orbit, epoch).ok())
+        .filter(|look| look.elevation_deg >= mask.elevation_at(look.azimuth_deg))
+        .collect()
+}
+
+pub fn dop_at(observer:&Observer, orbits:&[Orbit], epoch:f64, mask:&ElevationMask, constellations:&[Constellation]) -> DopEpoch {
+
+    let visible = visible_satellites(observer, orbits, epoch, mask, constellations);
+
+    DopEpoch {
+        epoch,
+        satellites: visible.iter().map(|look| look.satellite).collect(),
+        dop: dop(&visible),
+    }
+}
+
+// DOP time series from start to end (inclusive) every `step` seconds
+pub fn dop_series(observer:&Observer, orbits:&[Orbit], start:f64, end:f64, step:f64, mask:&ElevationMask, constellations:&[Constellation]) -> Vec<DopEpoch> {
+
+    let mut series:Vec<DopEpoch> = Vec::new();
+
+    let mut epoch = start;
+    while epoch <= end {
+        series.push(dop_at(observer, orbits, epoch, mask, constellations));
+        epoch += step;
+    }
+
+    series
+}
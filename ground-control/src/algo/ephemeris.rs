@@ -0,0 +1,351 @@
+use anise::math::Vector3;
+use anyhow::anyhow;
+
+use crate::{algo::{look::EARTH_ROTATION_RAD_S, util::invert_matrix}, product::{satellite::{Constellation, SatelliteId}, sv::{lagrange_interpolate, Orbit}}};
+
+// IS-GPS-200 gravitational constant used by the broadcast orbit model, also used by QZSS
+pub const GPS_GM_M3_S2:f64 = 3.986005e14;
+// Galileo OS SIS ICD value
+pub const GALILEO_GM_M3_S2:f64 = 3.986004418e14;
+pub const SECONDS_PER_WEEK:f64 = 604_800.0;
+
+// LNAV broadcast ephemeris (IS-GPS-200 20.3.3.4) and clock polynomial, in ICD units: meters,
+// radians and seconds. Reference epochs are GPST seconds rather than seconds of week.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct BroadcastEphemeris {
+    pub satellite:SatelliteId,
+    pub iode:u16,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub toe:f64,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub toc:f64,
+    pub sqrt_a:f64,
+    pub e:f64,
+    pub i0:f64,
+    pub omega0:f64,
+    pub omega:f64,
+    pub m0:f64,
+    pub delta_n:f64,
+    pub idot:f64,
+    pub omega_dot:f64,
+    pub cuc:f64,
+    pub cus:f64,
+    pub crc:f64,
+    pub crs:f64,
+    pub cic:f64,
+    pub cis:f64,
+    pub af0:f64,
+    pub af1:f64,
+    pub af2:f64,
+    pub fit_interval_s:f64,
+}
+
+impl BroadcastEphemeris {
+
+    // gravitational constant of the constellation's broadcast orbit model
+    pub fn gm_m3_s2(&self) -> f64 {
+        match self.satellite.constellation {
+            Constellation::Galileo => GALILEO_GM_M3_S2,
+            _ => GPS_GM_M3_S2,
+        }
+    }
+
+    pub fn covers(&self, epoch:f64) -> bool {
+        (epoch - self.toe).abs() <= self.fit_interval_s / 2.0
+    }
+
+    // ECEF position in km (IS-GPS-200 Table 20-IV)
+    pub fn position_ecef_km(&self, epoch:f64) -> Vector3 {
+
+        let a = self.sqrt_a * self.sqrt_a;
+        let tk = epoch - self.toe;
+        let n = (self.gm_m3_s2() / (a * a * a)).sqrt() + self.delta_n;
+        let mk = self.m0 + n * tk;
+
+        let mut ek = mk;
+        for _ in 0..10 {
+            ek -= (ek - self.e * ek.sin() - mk) / (1.0 - self.e * ek.cos());
+        }
+
+        let vk = ((1.0 - self.e * self.e).sqrt() * ek.sin()).atan2(ek.cos() - self.e);
+        let phi = vk + self.omega;
+        let (sin_2phi, cos_2phi) = (2.0 * phi).sin_cos();
+
+        let uk = phi + self.cus * sin_2phi + self.cuc * cos_2phi;
+        let rk = a * (1.0 - self.e * ek.cos()) + self.crs * sin_2phi + self.crc * cos_2phi;
+        let ik = self.i0 + self.idot * tk + self.cis * sin_2phi + self.cic * cos_2phi;
+
+        let (x, y) = (rk * uk.cos(), rk * uk.sin());
+        let node = self.omega0 + (self.omega_dot - EARTH_ROTATION_RAD_S) * tk - EARTH_ROTATION_RAD_S * self.toe.rem_euclid(SECONDS_PER_WEEK);
+
+        Vector3::new(
+            x * node.cos() - y * ik.cos() * node.sin(),
+            x * node.sin() + y * ik.cos() * node.cos(),
+            y * ik.sin()) / 1_000.0
+    }
+
+    // ECEF velocity in km/s from a central difference of the broadcast positions
+    pub fn velocity_ecef_km_s(&self, epoch:f64) -> Vector3 {
+        let h = 0.5;
+        (self.position_ecef_km(epoch + h) - self.position_ecef_km(epoch - h)) / (2.0 * h)
+    }
+
+    // satellite clock offset in seconds, without the relativistic correction
+    pub fn clock_offset_s(&self, epoch:f64) -> f64 {
+        let dt = epoch - self.toc;
+        self.af0 + self.af1 * dt + self.af2 * dt * dt
+    }
+
+    // Fit parameters, with (e cos w, e sin w, w + M0) in place of (e, w, M0) which become degenerate
+    // for the near circular GNSS orbits
+    fn orbit_params(&self) -> [f64; 15] {
+        [self.sqrt_a, self.e * self.omega.cos(), self.e * self.omega.sin(), self.i0, self.omega0, self.omega + self.m0,
+            self.delta_n, self.idot, self.omega_dot, self.cuc, self.cus, self.crc, self.crs, self.cic, self.cis]
+    }
+
+    fn with_orbit_params(&self, params:&[f64; 15]) -> Self {
+        let omega = params[2].atan2(params[1]);
+        BroadcastEphemeris {
+            sqrt_a: params[0], e: params[1].hypot(params[2]), omega, i0: params[3], omega0: params[4], m0: params[5] - omega,
+            delta_n: params[6], idot: params[7], omega_dot: params[8],
+            cuc: params[9], cus: params[10], crc: params[11], crs: params[12], cic: params[13], cis: params[14],
+            ..self.clone()
+        }
+    }
+}
+
+// RTCM SSR orbit frame from ECEF position and velocity: along track, cross track and radial
+pub fn rac_basis(pos:&Vector3, vel:&Vector3) -> (Vector3, Vector3, Vector3) {
+    let along = vel.normalize();
+    let cross = pos.cross(vel).normalize();
+    let radial = along.cross(&cross);
+    (radial, along, cross)
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct EphemerisConfig {
+    // span of precise samples behind each ephemeris, centered on toe (LNAV uses 4 hours)
+    pub fit_interval_s:f64,
+    pub iterations:usize,
+}
+
+impl Default for EphemerisConfig {
+    fn default() -> Self {
+        EphemerisConfig { fit_interval_s: 14_400.0, iterations: 10 }
+    }
+}
+
+// broadcast minus precise at a sample epoch, orbit in the RTCM radial/along/cross frame
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct EphemerisResidual {
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub radial_m:f64,
+    pub along_m:f64,
+    pub cross_m:f64,
+    pub clock_ns:Option<f64>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct EphemerisFit {
+    pub ephemeris:BroadcastEphemeris,
+    pub residuals:Vec<EphemerisResidual>,
+    pub rms_m:f64,
+    pub max_m:f64,
+    pub clock_rms_ns:Option<f64>,
+}
+
+// Consecutive ephemerides over the orbit span, one per fit interval with enough samples
+pub fn fit_ephemerides(orbit:&Orbit, config:&EphemerisConfig) -> Result<Vec<EphemerisFit>, anyhow::Error> {
+
+    let mut fits = Vec::new();
+    let mut start = orbit.valid_from;
+
+    while start + config.fit_interval_s <= orbit.valid_to + orbit.sampling_resolution / 2.0 {
+        fits.push(fit_ephemeris(orbit, start, start + config.fit_interval_s, config)?);
+        start += config.fit_interval_s;
+    }
+
+    if fits.is_empty() {
+        return Err(anyhow!("Orbit {} is shorter than the fit interval", orbit.sv.get_key()));
+    }
+
+    Ok(fits)
+}
+
+// Ephemeris with toe at the middle of [from, to], Gauss-Newton on the 15 orbit parameters
+// starting from the osculating elements at toe, then a least squares clock polynomial.
+pub fn fit_ephemeris(orbit:&Orbit, from:f64, to:f64, config:&EphemerisConfig) -> Result<EphemerisFit, anyhow::Error> {
+
+    let samples:Vec<usize> = (0..orbit.epochs.len())
+        .filter(|i| orbit.epochs[*i] >= from && orbit.epochs[*i] <= to)
+        .collect();
+
+    if samples.len() < 16 {
+        return Err(anyhow!("Not enough samples to fit an ephemeris for {} between {} and {}", orbit.sv.satellite, from, to));
+    }
+
+    // LNAV transmits toe in units of 16 seconds
+    let toe = (((from + to) / 2.0) / 16.0).round() * 16.0;
+    let epochs:Vec<f64> = samples.iter().map(|i| orbit.epochs[*i]).collect();
+    let observed:Vec<Vector3> = samples.iter().map(|i| orbit.pos_ecef_km[*i] * 1_000.0).collect();
+
+    // initial state from the nine samples around toe, the full interpolation window may reach
+    // outside the orbit near its ends
+    let center = samples[samples.len() / 2];
+    let window = center - 4..center + 5;
+    let interpolate = |epoch:f64| lagrange_interpolate(&orbit.epochs[window.clone()], &orbit.pos_ecef_km[window.clone()], epoch);
+    let pos = interpolate(toe);
+    // central difference over one second
+    let vel = interpolate(toe + 0.5) - interpolate(toe - 0.5);
+
+    let mut ephemeris = osculating_ephemeris(orbit.sv.satellite, toe, &(pos * 1_000.0), &(vel * 1_000.0));
+    ephemeris.fit_interval_s = to - from;
+    ephemeris.iode = (toe.rem_euclid(SECONDS_PER_WEEK) / 16.0) as u16 % 256;
+
+    let deltas = [1e-4, 1e-9, 1e-9, 1e-9, 1e-9, 1e-9, 1e-13, 1e-13, 1e-13, 1e-9, 1e-9, 1e-3, 1e-3, 1e-9, 1e-9];
+    let mut params = ephemeris.orbit_params();
+
+    let model = |params:&[f64; 15]| -> Vec<Vector3> {
+        let ephemeris = ephemeris.with_orbit_params(params);
+        epochs.iter().map(|epoch| ephemeris.position_ecef_km(*epoch) * 1_000.0).collect()
+    };
+
+    for _ in 0..config.iterations {
+
+        let computed = model(&params);
+        let residuals:Vec<f64> = observed.iter().zip(&computed)
+            .flat_map(|(o, c)| { let d = o - c; [d.x, d.y, d.z] })
+            .collect();
+
+        let partials:Vec<Vec<f64>> = (0..15).map(|j| {
+            let mut perturbed = params;
+            perturbed[j] += deltas[j];
+            model(&perturbed).iter().zip(&computed)
+                .flat_map(|(p, c)| { let d = p - c; [d.x, d.y, d.z] })
+                .collect()
+        }).collect();
+
+        let mut normal = vec![vec![0.0; 15]; 15];
+        let mut rhs = [0.0; 15];
+        for j in 0..15 {
+            rhs[j] = partials[j].iter().zip(&residuals).map(|(a, b)| a * b).sum();
+            for k in 0..15 {
+                normal[j][k] = partials[j].iter().zip(&partials[k]).map(|(a, b)| a * b).sum();
+            }
+        }
+
+        let inverse = invert_matrix(&normal).ok_or_else(|| anyhow!("Singular ephemeris fit for {}", orbit.sv.satellite))?;
+
+        let mut largest_step:f64 = 0.0;
+        for j in 0..15 {
+            let step:f64 = (0..15).map(|k| inverse[j][k] * rhs[k]).sum();
+            params[j] += step * deltas[j];
+            largest_step = largest_step.max(step.abs());
+        }
+
+        // a tenth of the perturbation, below the finite difference noise of the partials
+        if largest_step < 0.1 {
+            break;
+        }
+    }
+
+    let mut ephemeris = ephemeris.with_orbit_params(&params);
+
+    let clocks:Option<Vec<f64>> = orbit.clock_usec.as_ref().map(|clock_usec| samples.iter().map(|i| clock_usec[*i] * 1e-6).collect());
+    if let Some(clocks) = &clocks {
+        let [af0, af1, af2] = clock_polynomial(&epochs, clocks, toe)?;
+        (ephemeris.af0, ephemeris.af1, ephemeris.af2) = (af0, af1, af2);
+    }
+
+    let residuals:Vec<EphemerisResidual> = epochs.iter().zip(&observed).enumerate().map(|(i, (epoch, observed))| {
+        let pos = ephemeris.position_ecef_km(*epoch);
+        let (radial, along, cross) = rac_basis(&pos, &ephemeris.velocity_ecef_km_s(*epoch));
+        let d = pos * 1_000.0 - observed;
+        EphemerisResidual {
+            epoch: *epoch,
+            radial_m: d.dot(&radial),
+            along_m: d.dot(&along),
+            cross_m: d.dot(&cross),
+            clock_ns: clocks.as_ref().map(|clocks| (ephemeris.clock_offset_s(*epoch) - clocks[i]) * 1e9),
+        }
+    }).collect();
+
+    let squares:Vec<f64> = residuals.iter().map(|r| r.radial_m.powi(2) + r.along_m.powi(2) + r.cross_m.powi(2)).collect();
+    let rms_m = (squares.iter().sum::<f64>() / squares.len() as f64).sqrt();
+    let max_m = squares.iter().fold(0.0f64, |max, square| max.max(square.sqrt()));
+    let clock_rms_ns = clocks.map(|_| (residuals.iter().filter_map(|r| r.clock_ns).map(|c| c * c).sum::<f64>() / residuals.len() as f64).sqrt());
+
+    Ok(EphemerisFit { ephemeris, residuals, rms_m, max_m, clock_rms_ns })
+}
+
+// Kepler elements of an ECEF state (m, m/s), with the node referred to the Greenwich meridian at
+// toe as the broadcast model expects
+fn osculating_ephemeris(satellite:SatelliteId, toe:f64, pos:&Vector3, vel:&Vector3) -> BroadcastEphemeris {
+
+    let inertial_vel = vel + Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(pos);
+    let r = pos.norm();
+    let h = pos.cross(&inertial_vel);
+
+    let a = 1.0 / (2.0 / r - inertial_vel.norm_squared() / GPS_GM_M3_S2);
+    let e_cos = 1.0 - r / a;
+    let e_sin = pos.dot(&inertial_vel) / (GPS_GM_M3_S2 * a).sqrt();
+    let e = (e_cos * e_cos + e_sin * e_sin).sqrt();
+    let eccentric_anomaly = e_sin.atan2(e_cos);
+
+    let inclination = (h.z / h.norm()).acos();
+    let node = h.x.atan2(-h.y);
+    let latitude_argument = (pos.z / inclination.sin()).atan2(pos.x * node.cos() + pos.y * node.sin());
+    let true_anomaly = ((1.0 - e * e).sqrt() * eccentric_anomaly.sin()).atan2(eccentric_anomaly.cos() - e);
+
+    BroadcastEphemeris {
+        satellite,
+        iode: 0,
+        toe,
+        toc: toe,
+        sqrt_a: a.sqrt(),
+        e,
+        i0: inclination,
+        omega0: node + EARTH_ROTATION_RAD_S * toe.rem_euclid(SECONDS_PER_WEEK),
+        omega: latitude_argument - true_anomaly,
+        m0: eccentric_anomaly - e * eccentric_anomaly.sin(),
+        delta_n: 0.0,
+        idot: 0.0,
+        omega_dot: 0.0,
+        cuc: 0.0,
+        cus: 0.0,
+        crc: 0.0,
+        crs: 0.0,
+        cic: 0.0,
+        cis: 0.0,
+        af0: 0.0,
+        af1: 0.0,
+        af2: 0.0,
+        fit_interval_s: 0.0,
+    }
+}
+
+// least squares af0, af1, af2 about toc
+fn clock_polynomial(epochs:&[f64], clocks:&[f64], toc:f64) -> Result<[f64; 3], anyhow::Error> {
+
+    // solved in kiloseconds to keep the normal matrix well conditioned
+    let scale = 1_000.0;
+    let mut normal = vec![vec![0.0; 3]; 3];
+    let mut rhs = [0.0; 3];
+
+    for (epoch, clock) in epochs.iter().zip(clocks) {
+        let dt = (epoch - toc) / scale;
+        let row = [1.0, dt, dt * dt];
+        for j in 0..3 {
+            rhs[j] += row[j] * clock;
+            for k in 0..3 {
+                normal[j][k] += row[j] * row[k];
+            }
+        }
+    }
+
+    let inverse = invert_matrix(&normal).ok_or_else(|| anyhow!("Singular clock fit"))?;
+
+    Ok([0, 1, 2].map(|j| (0..3).map(|k| inverse[j][k] * rhs[k]).sum::<f64>() / scale.powi(j as i32)))
+}
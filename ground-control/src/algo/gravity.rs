@@ -0,0 +1,227 @@
+use anise::math::Vector3;
+
+// EGM2008 gravitational constant and reference radius
+pub const EGM2008_GM_KM3_S2:f64 = 398600.4415;
+pub const EGM2008_RADIUS_KM:f64 = 6378.1363;
+
+// degree and order of the embedded field
+pub const EGM2008_DEGREE:usize = 12;
+
+// Fully normalized EGM2008 coefficients (tide free) to degree and order 12 as (n, m, C, S), the
+// degree one terms are zero in an Earth centered frame. Load an ICGEM model for a larger field.
+const EGM2008_COEFFICIENTS:[(usize, usize, f64, f64); 89] = [
+    (0, 0, 1.0, 0.0),
+    (2, 0, -4.84165143790815e-4, 0.0),
+    (2, 1, -2.06615509074176e-10, 1.38441389137979e-9),
+    (2, 2, 2.43938357328313e-6, -1.40027370385934e-6),
+    (3, 0, 9.57161207093473e-7, 0.0),
+    (3, 1, 2.03046201047864e-6, 2.48200415856872e-7),
+    (3, 2, 9.04787894809528e-7, -6.19005475177618e-7),
+    (3, 3, 7.21321757121568e-7, 1.41434926192941e-6),
+    (4, 0, 5.39965866638991e-7, 0.0),
+    (4, 1, -5.36157389388867e-7, -4.73567346518086e-7),
+    (4, 2, 3.50501623962649e-7, 6.62480026275829e-7),
+    (4, 3, 9.90856766672321e-7, -2.00956723567452e-7),
+    (4, 4, -1.88519633023033e-7, 3.08803882149194e-7),
+    (5, 0, 6.86702913736681e-8, 0.0),
+    (5, 1, -6.29211923042529e-8, -9.43698073395769e-8),
+    (5, 2, 6.52078043176164e-7, -3.23353192540522e-7),
+    (5, 3, -4.51847152328843e-7, -2.14955408306046e-7),
+    (5, 4, -2.95328761175629e-7, 4.98070550102351e-8),
+    (5, 5, 1.74811795496002e-7, -6.69379935180165e-7),
+    (6, 0, -1.49953927978527e-7, 0.0),
+    (6, 1, -7.59210081892527e-8, 2.65122593213897e-8),
+    (6, 2, 4.86488387706264e-8, -3.73789324523752e-7),
+    (6, 3, 5.72451611175653e-8, 8.95201130010730e-9),
+    (6, 4, -8.60237937191611e-8, -4.71425573429758e-7),
+    (6, 5, -2.67166423703038e-7, -5.36493151500206e-7),
+    (6, 6, 9.47068749756882e-9, -2.37382353351005e-7),
+    (7, 0, 9.05120844521618e-8, 0.0),
+    (7, 1, 2.80887555771238e-7, 9.51259362239784e-8),
+    (7, 2, 3.30407993096024e-7, 9.29969537260919e-8),
+    (7, 3, 2.50458455243634e-7, -2.17118510726353e-7),
+    (7, 4, -2.74993908483559e-7, -1.24058571442700e-7),
+    (7, 5, 1.64773935123604e-9, 1.79281782751438e-8),
+    (7, 6, -3.58802482061200e-7, 1.51798257443526e-7),
+    (7, 7, 1.50795911022542e-9, 2.41068494932722e-8),
+    (8, 0, 4.94756003005199e-8, 0.0),
+    (8, 1, 2.31607991248329e-8, 5.89321652041430e-8),
+    (8, 2, 8.00143565483e-8, 6.5262298991727e-8),
+    (8, 3, -1.9364384377083e-8, -8.598664727743e-8),
+    (8, 4, -2.44600106058e-7, 6.982081083667e-8),
+    (8, 5, -2.5702161844073e-8, 8.92115400820e-8),
+    (8, 6, -6.596174455709e-8, 3.089254579252e-7),
+    (8, 7, 6.726348062115e-8, 7.48740836360e-8),
+    (8, 8, -1.24021393520e-7, 1.20441706490e-7),
+    (9, 0, 2.80287554141e-8, 0.0),
+    (9, 1, 1.42336791434e-7, 2.14010596497e-8),
+    (9, 2, 2.14210255188e-8, -3.17412813055e-8),
+    (9, 3, -1.60686186065e-7, -1.51638727218e-7),
+    (9, 4, -9.36832387209e-9, 1.99946935339e-8),
+    (9, 5, -1.63157918986e-8, -5.40432616010e-8),
+    (9, 6, 6.27802106093e-8, 2.22916465974e-7),
+    (9, 7, -1.17975590938e-7, -9.69059101897e-8),
+    (9, 8, 1.88131678380e-7, -3.00409163186e-9),
+    (9, 9, -4.75573617081e-8, 9.68819811947e-8),
+    (10, 0, 5.33304819127e-8, 0.0),
+    (10, 1, 8.37631712253e-8, -1.31128238810e-7),
+    (10, 2, -9.40432301791e-8, -5.12651303876e-8),
+    (10, 3, -7.00994021143e-9, -1.54384734119e-7),
+    (10, 4, -8.44498355167e-8, -7.90169310279e-8),
+    (10, 5, -4.92791210983e-8, -5.05958146914e-8),
+    (10, 6, -3.75884775066e-8, -7.97292149097e-8),
+    (10, 7, 8.26019051010e-9, -3.04045458001e-9),
+    (10, 8, 4.05668211911e-8, -9.17739357573e-8),
+    (10, 9, 1.25403599808e-7, -3.79286286067e-8),
+    (10, 10, 1.00414984112e-7, -2.38173612279e-8),
+    (11, 0, -5.07613239398e-8, 0.0),
+    (11, 1, 1.56206043211e-8, -2.68461032434e-8),
+    (11, 2, 1.93568935016e-8, -9.90052718498e-8),
+    (11, 3, -3.60536036884e-8, -1.48738059428e-7),
+    (11, 4, -3.13955845240e-9, -6.31070393449e-8),
+    (11, 5, 3.76549006767e-8, 4.96149802093e-8),
+    (11, 6, -5.45913050905e-10, 3.40922562728e-8),
+    (11, 7, -1.46926717807e-8, -8.71102286852e-8),
+    (11, 8, -4.61130013720e-10, 6.86985398224e-8),
+    (11, 9, -3.08723498340e-9, 2.06722406863e-8),
+    (11, 10, -3.38497010843e-8, -5.69002698102e-8),
+    (11, 11, -5.40003466e-8, -8.51036617e-9),
+    (12, 0, 3.64361957e-8, 0.0),
+    (12, 1, -5.37107393e-8, -3.97813553e-8),
+    (12, 2, 1.42161082e-8, 3.56216826e-8),
+    (12, 3, 4.10567306e-8, 7.92143618e-9),
+    (12, 4, -2.52802622e-8, 2.89143479e-8),
+    (12, 5, 2.63209012e-8, 6.13542773e-9),
+    (12, 6, -4.55213316e-8, 1.59839431e-8),
+    (12, 7, -2.83106766e-8, 1.80913225e-8),
+    (12, 8, -1.85447282e-8, 3.61766581e-8),
+    (12, 9, 4.20355958e-9, -3.49810457e-8),
+    (12, 10, -1.03426007e-8, -1.21419393e-8),
+    (12, 11, -3.56036618e-8, -6.82474802e-9),
+    (12, 12, -6.93022101e-9, 6.63011946e-9),
+];
+
+// Spherical harmonic Earth gravity field with fully normalized coefficients indexed [n][m]
+#[derive(Debug, PartialEq, Clone)]
+pub struct GravityModel {
+    pub gm_km3_s2:f64,
+    pub radius_km:f64,
+    pub degree:usize,
+    pub c:Vec<Vec<f64>>,
+    pub s:Vec<Vec<f64>>,
+}
+
+impl GravityModel {
+
+    // zero coefficients up to degree and order `degree`
+    pub fn new(gm_km3_s2:f64, radius_km:f64, degree:usize) -> Self {
+        GravityModel {
+            gm_km3_s2,
+            radius_km,
+            degree,
+            c: (0..=degree).map(|n| vec![0.0; n + 1]).collect(),
+            s: (0..=degree).map(|n| vec![0.0; n + 1]).collect(),
+        }
+    }
+
+    // embedded EGM2008 field to degree and order 12
+    pub fn egm2008() -> Self {
+
+        let mut model = GravityModel::new(EGM2008_GM_KM3_S2, EGM2008_RADIUS_KM, EGM2008_DEGREE);
+        for (n, m, c, s) in EGM2008_COEFFICIENTS {
+            model.c[n][m] = c;
+            model.s[n][m] = s;
+        }
+
+        model
+    }
+
+    pub fn truncate(&self, degree:usize) -> Self {
+
+        let degree = degree.min(self.degree);
+
+        GravityModel {
+            gm_km3_s2: self.gm_km3_s2,
+            radius_km: self.radius_km,
+            degree,
+            c: self.c[..=degree].to_vec(),
+            s: self.s[..=degree].to_vec(),
+        }
+    }
+
+    // Body-fixed acceleration in km/s^2 at a body-fixed position, including the central term.
+    // Cunningham's recursion on unnormalized coefficients (Montenbruck & Gill 3.2.4).
+    pub fn acceleration(&self, pos:&Vector3) -> Vector3 {
+
+        let n_max = self.degree;
+        let r2 = pos.norm_squared();
+        let rho = self.radius_km * self.radius_km / r2;
+        let (x0, y0, z0) = (self.radius_km * pos.x / r2, self.radius_km * pos.y / r2, self.radius_km * pos.z / r2);
+
+        // V and W to degree n_max + 1, the acceleration needs one degree above the field
+        let size = n_max + 2;
+        let mut v = vec![vec![0.0; size + 1]; size + 1];
+        let mut w = vec![vec![0.0; size + 1]; size + 1];
+
+        v[0][0] = self.radius_km / r2.sqrt();
+
+        for m in 0..=size {
+
+            if m > 0 {
+                let k = (2 * m - 1) as f64;
+                v[m][m] = k * (x0 * v[m - 1][m - 1] - y0 * w[m - 1][m - 1]);
+                w[m][m] = k * (x0 * w[m - 1][m - 1] + y0 * v[m - 1][m - 1]);
+            }
+
+            if m < size {
+                let k = (2 * m + 1) as f64;
+                v[m + 1][m] = k * z0 * v[m][m];
+                w[m + 1][m] = k * z0 * w[m][m];
+            }
+
+            for n in (m + 2)..=size {
+                let a = (2 * n - 1) as f64 / (n - m) as f64;
+                let b = (n + m - 1) as f64 / (n - m) as f64;
+                v[n][m] = a * z0 * v[n - 1][m] - b * rho * v[n - 2][m];
+                w[n][m] = a * z0 * w[n - 1][m] - b * rho * w[n - 2][m];
+            }
+        }
+
+        let (mut ax, mut ay, mut az) = (0.0, 0.0, 0.0);
+
+        for n in 0..=n_max {
+            for m in 0..=n {
+
+                let scale = normalization(n, m);
+                let c = self.c[n][m] * scale;
+                let s = self.s[n][m] * scale;
+
+                if m == 0 {
+                    ax -= c * v[n + 1][1];
+                    ay -= c * w[n + 1][1];
+                }
+                else {
+                    // (n - m + 2)! / (n - m)!
+                    let f = ((n - m + 1) * (n - m + 2)) as f64;
+                    ax += 0.5 * (-c * v[n + 1][m + 1] - s * w[n + 1][m + 1] + f * (c * v[n + 1][m - 1] + s * w[n + 1][m - 1]));
+                    ay += 0.5 * (-c * w[n + 1][m + 1] + s * v[n + 1][m + 1] + f * (-c * w[n + 1][m - 1] + s * v[n + 1][m - 1]));
+                }
+
+                az += (n - m + 1) as f64 * (-c * v[n + 1][m] - s * w[n + 1][m]);
+            }
+        }
+
+        let scale = self.gm_km3_s2 / (self.radius_km * self.radius_km);
+        Vector3::new(ax, ay, az) * scale
+    }
+}
+
+// factor from fully normalized to unnormalized coefficients
+fn normalization(n:usize, m:usize) -> f64 {
+
+    let delta = if m == 0 { 1.0 } else { 2.0 };
+    // (n - m)! / (n + m)!
+    let ratio:f64 = ((n - m + 1)..=(n + m)).map(|k| 1.0 / k as f64).product();
+
+    (delta * (2 * n + 1) as f64 * ratio).sqrt()
+}
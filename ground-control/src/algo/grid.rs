@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use anise::math::Vector3;
+use arrow_array::{Float64Array, RecordBatch, UInt32Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use serde_json::{json, Value};
+
+use crate::{algo::{dop::{constellation_selected, dop}, look::{LookAngles, SPEED_OF_LIGHT_KM_S}, pass::ElevationMask, util::{ecef_to_enu, latlon_to_ecef}}, product::{satellite::{Constellation, SatelliteId}, sv::Orbit}};
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum GridField {
+    Visible,
+    Pdop,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct VisibilityGrid {
+    pub epoch:f64,
+    pub step_deg:f64,
+    // -90..=90 and -180..=180, the 180 column repeats -180 so contours close across the antimeridian
+    pub lats_deg:Vec<f64>,
+    pub lons_deg:Vec<f64>,
+    // row-major by latitude then longitude
+    pub visible:Vec<u32>,
+    pub pdop:Vec<Option<f64>>,
+}
+
+// Counts visible satellites and computes PDOP on a global lat/lon grid at the ellipsoid surface.
+// Satellite positions are evaluated once at the epoch: at grid resolution the per-observer
+// light-time correction (a few hundred meters along track) does not change visibility.
+pub fn visibility_grid(orbits:&[Orbit], epoch:f64, step_deg:f64, mask:&ElevationMask, constellations:&[Constellation]) -> VisibilityGrid {
+
+    let satellites:Vec<(SatelliteId, Vector3)> = orbits.iter()
+        .filter(|orbit| constellation_selected(orbit.sv.satellite, constellations))
+        .filter_map(|orbit| orbit.position_at(epoch).ok().map(|pos| (orbit.sv.satellite, pos)))
+        .collect();
+
+    let lats_deg = grid_axis(-90.0, 90.0, step_deg);
+    let lons_deg = grid_axis(-180.0, 180.0, step_deg);
+
+    let mut visible:Vec<u32> = Vec::with_capacity(lats_deg.len() * lons_deg.len());
+    let mut pdop:Vec<Option<f64>> = Vec::with_capacity(lats_deg.len() * lons_deg.len());
+
+    for lat_deg in &lats_deg {
+        for lon_deg in &lons_deg {
+
+            let (lat, lon) = (lat_deg.to_radians(), lon_deg.to_radians());
+            let (x, y, z) = latlon_to_ecef(lat, lon, 0.0);
+            let rx_pos = Vector3::new(x / 1000.0, y / 1000.0, z / 1000.0);
+
+            let look_angles:Vec<LookAngles> = satellites.iter()
+                .filter_map(|(satellite, sv_pos)| {
+                    let los = sv_pos - rx_pos;
+                    let range = los.norm();
+                    let (e, n, u) = ecef_to_enu(los.x, los.y, los.z, lat, lon);
+
+                    let azimuth_deg = e.atan2(n).to_degrees().rem_euclid(360.0);
+                    let elevation_deg = (u / range).asin().to_degrees();
+
+                    if elevation_deg < mask.elevation_at(azimuth_deg) {
+                        return None;
+                    }
+
+                    Some(LookAngles {
+                        satellite: *satellite,
+                        epoch,
+                        azimuth_deg,
+                        elevation_deg,
+                        range_km: range,
+                        range_rate_km_s: 0.0,
+                        light_time_s: range / SPEED_OF_LIGHT_KM_S,
+                    })
+                })
+                .collect();
+
+            visible.push(look_angles.len() as u32);
+            pdop.push(dop(&look_angles).map(|d| d.pdop));
+        }
+    }
+
+    VisibilityGrid { epoch, step_deg, lats_deg, lons_deg, visible, pdop }
+}
+
+fn grid_axis(from:f64, to:f64, step:f64) -> Vec<f64> {
+    let count = ((to - from) / step).floor() as usize;
+    let mut axis:Vec<f64> = (0..=count).map(|i| from + i as f64 * step).collect();
+    if axis[count] < to {
+        axis.push(to);
+    }
+    axis
+}
+
+impl VisibilityGrid {
+
+    fn value(&self, field:GridField, lat_index:usize, lon_index:usize) -> Option<f64> {
+        let index = lat_index * self.lons_deg.len() + lon_index;
+        match field {
+            GridField::Visible => Some(self.visible[index] as f64),
+            GridField::Pdop => self.pdop[index],
+        }
+    }
+
+    pub fn arrow_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("lat_deg", DataType::Float64, false),
+            Field::new("lon_deg", DataType::Float64, false),
+            Field::new("visible", DataType::UInt32, false),
+            Field::new("pdop", DataType::Float64, true),
+        ]))
+    }
+
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+
+        let mut lat_vec:Vec<f64> = Vec::with_capacity(self.visible.len());
+        let mut lon_vec:Vec<f64> = Vec::with_capacity(self.visible.len());
+
+        for lat in &self.lats_deg {
+            for lon in &self.lons_deg {
+                lat_vec.push(*lat);
+                lon_vec.push(*lon);
+            }
+        }
+
+        RecordBatch::try_new(
+            Self::arrow_schema(),
+            vec![
+                Arc::new(Float64Array::from(lat_vec)),
+                Arc::new(Float64Array::from(lon_vec)),
+                Arc::new(UInt32Array::from(self.visible.clone())),
+                Arc::new(Float64Array::from(self.pdop.clone())),
+            ])
+    }
+
+    // Marching squares isolines as a GeoJSON FeatureCollection with one MultiLineString per level.
+    // Segments are not joined into rings; cells touching an undefined PDOP value are skipped.
+    pub fn contours(&self, field:GridField, levels:&[f64]) -> Value {
+
+        let mut features:Vec<Value> = Vec::new();
+
+        for level in levels {
+
+            let mut segments:Vec<[[f64; 2]; 2]> = Vec::new();
+
+            for i in 0..self.lats_deg.len() - 1 {
+                for j in 0..self.lons_deg.len() - 1 {
+                    let corners = (
+                        self.value(field, i, j),
+                        self.value(field, i, j + 1),
+                        self.value(field, i + 1, j + 1),
+                        self.value(field, i + 1, j));
+
+                    if let (Some(v00), Some(v01), Some(v11), Some(v10)) = corners {
+                        self.cell_segments(i, j, [v00, v01, v11, v10], *level, &mut segments);
+                    }
+                }
+            }
+
+            let lines:Vec<Value> = segments.iter()
+                .map(|s| json!([s[0], s[1]]))
+                .collect();
+
+            features.push(json!({
+                "type": "Feature",
+                "properties": { "field": field, "level": level, "epoch": self.epoch },
+                "geometry": { "type": "MultiLineString", "coordinates": lines }
+            }));
+        }
+
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    // corner values ordered (lat i, lon j), (i, j+1), (i+1, j+1), (i+1, j)
+    fn cell_segments(&self, i:usize, j:usize, v:[f64; 4], level:f64, segments:&mut Vec<[[f64; 2]; 2]>) {
+
+        let (lat0, lat1) = (self.lats_deg[i], self.lats_deg[i + 1]);
+        let (lon0, lon1) = (self.lons_deg[j], self.lons_deg[j + 1]);
+        let above:Vec<bool> = v.iter().map(|value| *value >= level).collect();
+
+        let fraction = |a:f64, b:f64| (level - a) / (b - a);
+
+        // crossing points on the bottom, right, top and left edges as [lon, lat]
+        let bottom = (above[0] != above[1]).then(|| [lon0 + fraction(v[0], v[1]) * (lon1 - lon0), lat0]);
+        let right = (above[1] != above[2]).then(|| [lon1, lat0 + fraction(v[1], v[2]) * (lat1 - lat0)]);
+        let top = (above[3] != above[2]).then(|| [lon0 + fraction(v[3], v[2]) * (lon1 - lon0), lat1]);
+        let left = (above[0] != above[3]).then(|| [lon0, lat0 + fraction(v[0], v[3]) * (lat1 - lat0)]);
+
+        match (bottom, right, top, left) {
+            (Some(b), Some(r), Some(t), Some(l)) => {
+                // saddle, resolved with the cell center value
+                let center_above = (v[0] + v[1] + v[2] + v[3]) / 4.0 >= level;
+                if center_above == above[0] {
+                    segments.push([b, r]);
+                    segments.push([t, l]);
+                } else {
+                    segments.push([b, l]);
+                    segments.push([r, t]);
+                }
+            }
+            (bottom, right, top, left) => {
+                let points:Vec<[f64; 2]> = [bottom, right, top, left].into_iter().flatten().collect();
+                if points.len() == 2 {
+                    segments.push([points[0], points[1]]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anise::math::Vector3;
+use anyhow::anyhow;
+
+use crate::{algo::util::{ecef_to_enu, ecef_to_latlon, latlon_to_ecef}, product::{satellite::SatelliteId, sv::Orbit}};
+
+pub const SPEED_OF_LIGHT_KM_S:f64 = 299_792.458;
+pub const EARTH_ROTATION_RAD_S:f64 = 7.2921151467e-5; // WGS-84 / IS-GPS-200
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub enum Observer {
+    Geodetic { lat_deg:f64, lon_deg:f64, alt_m:f64 },
+    Ecef { x_m:f64, y_m:f64, z_m:f64 },
+}
+
+impl Observer {
+
+    pub fn ecef_km(&self) -> Vector3 {
+        match self {
+            Observer::Geodetic { lat_deg, lon_deg, alt_m } => {
+                let (x, y, z) = latlon_to_ecef(lat_deg.to_radians(), lon_deg.to_radians(), *alt_m);
+                Vector3::new(x / 1000.0, y / 1000.0, z / 1000.0)
+            }
+            Observer::Ecef { x_m, y_m, z_m } => Vector3::new(x_m / 1000.0, y_m / 1000.0, z_m / 1000.0),
+        }
+    }
+
+    // geodetic latitude and longitude in radians
+    pub fn latlon_rad(&self) -> (f64, f64) {
+        match self {
+            Observer::Geodetic { lat_deg, lon_deg, .. } => (lat_deg.to_radians(), lon_deg.to_radians()),
+            Observer::Ecef { x_m, y_m, z_m } => {
+                let (lat, lon, _) = ecef_to_latlon(*x_m, *y_m, *z_m);
+                (lat, lon)
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct LookAngles {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub azimuth_deg:f64,
+    pub elevation_deg:f64,
+    pub range_km:f64,
+    pub range_rate_km_s:f64,
+    pub light_time_s:f64,
+}
+
+// Rotates an ECEF vector about the z axis by the earth rotation during the signal travel time
+// (Sagnac correction), bringing the transmit-time position into the receive-time frame
+pub fn earth_rotation_correction(pos:Vector3, travel_time_s:f64) -> Vector3 {
+    let (sin_theta, cos_theta) = (EARTH_ROTATION_RAD_S * travel_time_s).sin_cos();
+    Vector3::new(
+        cos_theta * pos.x + sin_theta * pos.y,
+        -sin_theta * pos.x + cos_theta * pos.y,
+        pos.z)
+}
+
+// Computes azimuth, elevation, slant range and range rate of a satellite from an observer.
+// `epoch` is the signal reception time in GPST seconds; the satellite position is evaluated
+// at transmit time (light-time iteration) and rotated into the reception frame (Sagnac).
+pub fn look_angles(observer:&Observer, orbit:&Orbit, epoch:f64) -> Result<LookAngles, anyhow::Error> {
+
+    let rx_pos = observer.ecef_km();
+    let (lat, lon) = observer.latlon_rad();
+
+    let mut light_time = 0.0;
+    let mut sv_pos = Vector3::zeros();
+
+    // converges to well below a nanosecond within three iterations for GNSS altitudes
+    for _ in 0..5 {
+        let tx_pos = orbit.position_at(epoch - light_time)?;
+        sv_pos = earth_rotation_correction(tx_pos, light_time);
+
+        let next_light_time = (sv_pos - rx_pos).norm() / SPEED_OF_LIGHT_KM_S;
+        let converged = (next_light_time - light_time).abs() < 1e-12;
+        light_time = next_light_time;
+
+        if converged {
+            break;
+        }
+    }
+
+    let sv_vel = earth_rotation_correction(orbit.velocity_at(epoch - light_time)?, light_time);
+
+    let los = sv_pos - rx_pos;
+    let range = los.norm();
+
+    if range == 0.0 {
+        return Err(anyhow!("Observer coincides with satellite {}", orbit.sv.satellite));
+    }
+
+    let (e, n, u) = ecef_to_enu(los.x, los.y, los.z, lat, lon);
+
+    let azimuth = e.atan2(n).to_degrees().rem_euclid(360.0);
+    let elevation = (u / range).asin().to_degrees();
+
+    // observer is fixed in ECEF so only the satellite velocity contributes
+    let range_rate = sv_vel.dot(&los) / range;
+
+    Ok(LookAngles {
+        satellite: orbit.sv.satellite,
+        epoch,
+        azimuth_deg: azimuth,
+        elevation_deg: elevation,
+        range_km: range,
+        range_rate_km_s: range_rate,
+        light_time_s: light_time,
+    })
+}
+
+// Look angles for every orbit that covers the epoch, satellites without coverage are skipped
+pub fn look_angles_all(observer:&Observer, orbits:&[Orbit], epoch:f64) -> Vec<LookAngles> {
+
+    orbits.iter()
+        .filter_map(|orbit| look_angles(observer, orbit, epoch).ok())
+        .collect()
+}
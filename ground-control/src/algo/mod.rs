@@ -1 +1,15 @@
 pub mod util;
+pub mod look;
+pub mod pass;
+pub mod dop;
+pub mod grid;
+pub mod sun;
+pub mod attitude;
+pub mod combination;
+pub mod gravity;
+pub mod propagate;
+pub mod ephemeris;
+pub mod ssr;
+pub mod sisre;
+pub mod tle;
+pub mod track;
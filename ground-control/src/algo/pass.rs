@@ -0,0 +1,242 @@
+use anyhow::anyhow;
+
+use crate::{algo::look::{look_angles, LookAngles, Observer}, product::{satellite::SatelliteId, sv::Orbit}};
+
+// root finding and peak search stop once the bracket is narrower than this (seconds)
+const TIME_TOLERANCE_S:f64 = 0.01;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct ElevationMask {
+    pub min_elevation_deg:f64,
+    // (azimuth_deg, elevation_deg) horizon profile, linearly interpolated in azimuth
+    pub horizon:Vec<(f64, f64)>,
+}
+
+impl ElevationMask {
+
+    pub fn new(min_elevation_deg:f64) -> Self {
+        ElevationMask { min_elevation_deg, horizon: Vec::new() }
+    }
+
+    pub fn with_horizon(min_elevation_deg:f64, mut horizon:Vec<(f64, f64)>) -> Self {
+        for point in horizon.iter_mut() {
+            point.0 = point.0.rem_euclid(360.0);
+        }
+        horizon.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ElevationMask { min_elevation_deg, horizon }
+    }
+
+    // parses a horizon profile of the form "az:el,az:el,..." in degrees
+    pub fn parse_horizon(horizon:&str) -> Result<Vec<(f64, f64)>, anyhow::Error> {
+
+        let mut points = Vec::new();
+
+        for point in horizon.split(',').filter(|p| !p.trim().is_empty()) {
+            let (az, el) = point.split_once(':')
+                .ok_or_else(|| anyhow!("Invalid horizon point {}", point))?;
+            points.push((az.trim().parse::<f64>()?, el.trim().parse::<f64>()?));
+        }
+
+        Ok(points)
+    }
+
+    // effective mask elevation at an azimuth, never below the global minimum
+    pub fn elevation_at(&self, azimuth_deg:f64) -> f64 {
+
+        if self.horizon.is_empty() {
+            return self.min_elevation_deg;
+        }
+
+        let azimuth = azimuth_deg.rem_euclid(360.0);
+        let n = self.horizon.len();
+
+        // bracketing points, wrapping around north
+        let upper = self.horizon.iter().position(|p| p.0 >= azimuth).unwrap_or(n);
+        let (az0, el0) = if upper == 0 { (self.horizon[n - 1].0 - 360.0, self.horizon[n - 1].1) } else { self.horizon[upper - 1] };
+        let (az1, el1) = if upper == n { (self.horizon[0].0 + 360.0, self.horizon[0].1) } else { self.horizon[upper] };
+
+        let horizon_elevation = if az1 == az0 {
+            el0.max(el1)
+        } else {
+            el0 + (el1 - el0) * (azimuth - az0) / (az1 - az0)
+        };
+
+        horizon_elevation.max(self.min_elevation_deg)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Pass {
+    pub satellite:SatelliteId,
+    // None when the satellite is already above the mask at the start of the window or data
+    #[serde(serialize_with = "crate::gpst::serialize_optional_epoch")]
+    pub rise:Option<f64>,
+    pub rise_azimuth_deg:Option<f64>,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub culmination:f64,
+    pub peak_elevation_deg:f64,
+    pub peak_azimuth_deg:f64,
+    // None when the satellite is still above the mask at the end of the window or data
+    #[serde(serialize_with = "crate::gpst::serialize_optional_epoch")]
+    pub set:Option<f64>,
+    pub set_azimuth_deg:Option<f64>,
+}
+
+struct OpenPass {
+    rise:Option<f64>,
+    rise_azimuth_deg:Option<f64>,
+    peak:LookAngles,
+}
+
+// Predicts rise, culmination and set times for one satellite between start and end (GPST seconds).
+// Elevation above the mask is sampled every `step` seconds, crossings are refined by bisection
+// and the culmination by golden-section search, both to TIME_TOLERANCE_S.
+pub fn predict_passes(observer:&Observer, orbit:&Orbit, mask:&ElevationMask, start:f64, end:f64, step:f64) -> Vec<Pass> {
+
+    let visibility = |t:f64| -> Option<(f64, LookAngles)> {
+        look_angles(observer, orbit, t).ok()
+            .map(|look| (look.elevation_deg - mask.elevation_at(look.azimuth_deg), look))
+    };
+
+    let mut passes:Vec<Pass> = Vec::new();
+    let mut open_pass:Option<OpenPass> = None;
+    let mut previous:Option<(f64, f64)> = None;
+
+    let mut t = start;
+    loop {
+        let t_now = t.min(end);
+
+        match visibility(t_now) {
+            None => {
+                // gap in orbit coverage closes any open pass
+                if let Some(pass) = open_pass.take() {
+                    let last_covered = previous.map(|(t_previous, _)| t_previous).unwrap_or(t_now);
+                    passes.push(close_pass(observer, orbit, pass, None, (start, last_covered), step));
+                }
+                previous = None;
+            }
+            Some((above_mask, look)) => {
+                match previous {
+                    None => {
+                        if above_mask >= 0.0 {
+                            open_pass = Some(OpenPass { rise: None, rise_azimuth_deg: None, peak: look.clone() });
+                        }
+                    }
+                    Some((t_previous, previous_above_mask)) => {
+                        if previous_above_mask < 0.0 && above_mask >= 0.0 {
+                            let rise = bisect_crossing(&visibility, t_previous, t_now);
+                            let rise_azimuth_deg = visibility(rise).map(|(_, l)| l.azimuth_deg);
+                            open_pass = Some(OpenPass { rise: Some(rise), rise_azimuth_deg, peak: look.clone() });
+                        }
+                        else if previous_above_mask >= 0.0 && above_mask < 0.0 {
+                            let set = bisect_crossing(&visibility, t_previous, t_now);
+                            if let Some(pass) = open_pass.take() {
+                                passes.push(close_pass(observer, orbit, pass, Some(set), (start, end), step));
+                            }
+                        }
+                    }
+                }
+
+                match open_pass.as_mut() {
+                    Some(pass) if above_mask >= 0.0 && look.elevation_deg > pass.peak.elevation_deg => pass.peak = look,
+                    _ => {}
+                }
+
+                previous = Some((t_now, above_mask));
+            }
+        }
+
+        if t_now >= end {
+            break;
+        }
+        t += step;
+    }
+
+    if let Some(pass) = open_pass.take() {
+        passes.push(close_pass(observer, orbit, pass, None, (start, end), step));
+    }
+
+    passes
+}
+
+pub fn predict_passes_all(observer:&Observer, orbits:&[Orbit], mask:&ElevationMask, start:f64, end:f64, step:f64) -> Vec<Pass> {
+
+    let mut passes:Vec<Pass> = orbits.iter()
+        .flat_map(|orbit| predict_passes(observer, orbit, mask, start, end, step))
+        .collect();
+
+    passes.sort_by(|a, b| a.culmination.total_cmp(&b.culmination));
+
+    passes
+}
+
+fn close_pass(observer:&Observer, orbit:&Orbit, pass:OpenPass, set:Option<f64>, window:(f64, f64), step:f64) -> Pass {
+
+    let lower = pass.rise.unwrap_or(window.0).max(pass.peak.epoch - step);
+    let upper = set.unwrap_or(window.1).min(pass.peak.epoch + step);
+
+    let peak = refine_peak(observer, orbit, lower, upper).unwrap_or(pass.peak.clone());
+    let peak = if peak.elevation_deg >= pass.peak.elevation_deg { peak } else { pass.peak };
+
+    let set_azimuth_deg = set.and_then(|t| look_angles(observer, orbit, t).ok()).map(|l| l.azimuth_deg);
+
+    Pass {
+        satellite: orbit.sv.satellite,
+        rise: pass.rise,
+        rise_azimuth_deg: pass.rise_azimuth_deg,
+        culmination: peak.epoch,
+        peak_elevation_deg: peak.elevation_deg,
+        peak_azimuth_deg: peak.azimuth_deg,
+        set,
+        set_azimuth_deg,
+    }
+}
+
+// bisection on the sign of (elevation - mask) between a sample below and above the mask (either order)
+fn bisect_crossing<F>(visibility:&F, mut lower:f64, mut upper:f64) -> f64
+where F: Fn(f64) -> Option<(f64, LookAngles)> {
+
+    let lower_visible = visibility(lower).map(|(v, _)| v >= 0.0).unwrap_or(false);
+
+    while upper - lower > TIME_TOLERANCE_S {
+        let mid = 0.5 * (lower + upper);
+        let mid_visible = visibility(mid).map(|(v, _)| v >= 0.0).unwrap_or(false);
+
+        if mid_visible == lower_visible {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+
+    0.5 * (lower + upper)
+}
+
+// golden-section search for maximum elevation
+fn refine_peak(observer:&Observer, orbit:&Orbit, mut lower:f64, mut upper:f64) -> Option<LookAngles> {
+
+    let inv_phi = (5.0_f64.sqrt() - 1.0) / 2.0;
+
+    let mut c = upper - inv_phi * (upper - lower);
+    let mut d = lower + inv_phi * (upper - lower);
+    let mut look_c = look_angles(observer, orbit, c).ok()?;
+    let mut look_d = look_angles(observer, orbit, d).ok()?;
+
+    while upper - lower > TIME_TOLERANCE_S {
+        if look_c.elevation_deg > look_d.elevation_deg {
+            upper = d;
+            d = c;
+            look_d = look_c;
+            c = upper - inv_phi * (upper - lower);
+            look_c = look_angles(observer, orbit, c).ok()?;
+        } else {
+            lower = c;
+            c = d;
+            look_c = look_d;
+            d = lower + inv_phi * (upper - lower);
+            look_d = look_angles(observer, orbit, d).ok()?;
+        }
+    }
+
+    look_angles(observer, orbit, 0.5 * (lower + upper)).ok()
+}
@@ -0,0 +1,285 @@
+use anise::math::Vector3;
+use anyhow::anyhow;
+
+use crate::{error::ApiError, algo::{gravity::GravityModel, look::EARTH_ROTATION_RAD_S, sun::{eclipse_status, sun_moon_analytic_ecef_km}, util::invert_matrix}, product::sv::Orbit};
+
+pub const SUN_GM_KM3_S2:f64 = 1.32712440018e11;
+pub const MOON_GM_KM3_S2:f64 = 4902.800066;
+pub const AU_KM:f64 = 149_597_870.7;
+
+// degree and order an ICGEM gravity field is truncated to for propagation, the size of the
+// embedded EGM2008 field
+pub const PROPAGATION_DEGREE:usize = 12;
+
+// Reduced ECOM solar radiation pressure: constant accelerations in km/s^2 at 1 AU along the
+// Sun direction (D), the solar panel axis (Y) and their cross product (B)
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub struct Ecom {
+    pub d0:f64,
+    pub y0:f64,
+    pub b0:f64,
+}
+
+impl Ecom {
+    // typical GNSS area to mass ratio (~0.02 m^2/kg), refined by the orbit fit
+    pub fn a_priori() -> Self {
+        Ecom { d0: -1e-10, y0: 0.0, b0: 0.0 }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct PropagatorConfig {
+    // integration step
+    pub step_s:f64,
+    // trailing span of precise samples the initial state is fitted to
+    pub fit_arc_s:f64,
+    // how far past the product span positions are extrapolated
+    pub max_extrapolation_s:f64,
+    pub fit_iterations:usize,
+    // estimate the ECOM parameters with the initial state, otherwise keep the a priori values
+    pub estimate_srp:bool,
+}
+
+impl Default for PropagatorConfig {
+    fn default() -> Self {
+        PropagatorConfig { step_s: 60.0, fit_arc_s: 21_600.0, max_extrapolation_s: 21_600.0, fit_iterations: 6, estimate_srp: true }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub struct State {
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    #[schema(value_type = Vec<f64>)]
+    pub pos_ecef_km:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub vel_ecef_km_s:Vector3,
+}
+
+// Forces on a GNSS satellite, integrated in the rotating ECEF frame. Earth orientation is modeled
+// as a uniform rotation, precession, nutation and polar motion are negligible over a few hours.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForceModel {
+    pub gravity:GravityModel,
+    pub srp:Ecom,
+}
+
+impl ForceModel {
+
+    pub fn new(gravity:&GravityModel, srp:Ecom) -> Self {
+        ForceModel { gravity: gravity.truncate(PROPAGATION_DEGREE), srp }
+    }
+
+    // ECEF acceleration in km/s^2, including the Coriolis and centrifugal terms
+    pub fn acceleration(&self, epoch:f64, pos:&Vector3, vel:&Vector3) -> Vector3 {
+
+        let (sun, moon) = sun_moon_analytic_ecef_km(epoch);
+
+        let third_body = third_body_acceleration(pos, &sun, SUN_GM_KM3_S2)
+            + third_body_acceleration(pos, &moon, MOON_GM_KM3_S2);
+
+        let omega = Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S);
+        let rotation = -2.0 * omega.cross(vel) - omega.cross(&omega.cross(pos));
+
+        self.gravity.acceleration(pos) + third_body + self.srp_acceleration(pos, &sun, &moon) + rotation
+    }
+
+    fn srp_acceleration(&self, pos:&Vector3, sun:&Vector3, moon:&Vector3) -> Vector3 {
+
+        let (_, sunlit_fraction) = eclipse_status(pos, sun, moon);
+        if sunlit_fraction <= 0.0 {
+            return Vector3::zeros();
+        }
+
+        let to_sun = sun - pos;
+        let distance_au = to_sun.norm() / AU_KM;
+
+        let e_d = to_sun.normalize();
+        let e_y = e_d.cross(&pos.normalize()).normalize();
+        let e_b = e_d.cross(&e_y);
+
+        (self.srp.d0 * e_d + self.srp.y0 * e_y + self.srp.b0 * e_b) * sunlit_fraction / (distance_au * distance_au)
+    }
+
+    // classic fourth order Runge-Kutta step
+    pub fn step(&self, state:&State, h:f64) -> State {
+
+        let (t, r, v) = (state.epoch, state.pos_ecef_km, state.vel_ecef_km_s);
+
+        let a1 = self.acceleration(t, &r, &v);
+        let (r2, v2) = (r + v * (h / 2.0), v + a1 * (h / 2.0));
+        let a2 = self.acceleration(t + h / 2.0, &r2, &v2);
+        let (r3, v3) = (r + v2 * (h / 2.0), v + a2 * (h / 2.0));
+        let a3 = self.acceleration(t + h / 2.0, &r3, &v3);
+        let (r4, v4) = (r + v3 * h, v + a3 * h);
+        let a4 = self.acceleration(t + h, &r4, &v4);
+
+        State {
+            epoch: t + h,
+            pos_ecef_km: r + (v + 2.0 * v2 + 2.0 * v3 + v4) * (h / 6.0),
+            vel_ecef_km_s: v + (a1 + 2.0 * a2 + 2.0 * a3 + a4) * (h / 6.0),
+        }
+    }
+
+    // states at ascending epochs from `state` onwards, steps are shortened to land on each epoch
+    pub fn propagate(&self, state:&State, epochs:&[f64], step_s:f64) -> Vec<State> {
+
+        let mut current = *state;
+        let mut states = Vec::with_capacity(epochs.len());
+
+        for epoch in epochs {
+            while current.epoch < *epoch {
+                current = self.step(&current, step_s.min(epoch - current.epoch));
+            }
+            states.push(current);
+        }
+
+        states
+    }
+}
+
+pub fn third_body_acceleration(pos:&Vector3, body:&Vector3, gm_km3_s2:f64) -> Vector3 {
+    let to_body = body - pos;
+    gm_km3_s2 * (to_body / to_body.norm().powi(3) - body / body.norm().powi(3))
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct OrbitFit {
+    pub state:State,
+    pub srp:Ecom,
+    // post-fit position residual over the fit arc
+    pub rms_km:f64,
+    pub samples:usize,
+}
+
+// Least squares fit of the initial state (and ECOM parameters) to the trailing precise samples,
+// Gauss-Newton with finite difference partials.
+pub fn fit_orbit(orbit:&Orbit, gravity:&GravityModel, config:&PropagatorConfig) -> Result<OrbitFit, anyhow::Error> {
+
+    let samples:Vec<usize> = (0..orbit.epochs.len())
+        .filter(|i| orbit.epochs[*i] >= orbit.valid_to - config.fit_arc_s && orbit.epochs[*i] <= orbit.valid_to)
+        .collect();
+
+    if samples.len() < 8 {
+        return Err(anyhow!("Not enough samples to fit {}", orbit.sv.satellite));
+    }
+
+    let first = samples[0];
+    let epochs:Vec<f64> = samples.iter().map(|i| orbit.epochs[*i]).collect();
+    let observed:Vec<Vector3> = samples.iter().map(|i| orbit.pos_ecef_km[*i]).collect();
+
+    let velocity = orbit.velocity_at(epochs[0]).or_else(|_| {
+        let after = first + 1;
+        let before = first.saturating_sub(1);
+        Ok::<Vector3, anyhow::Error>((orbit.pos_ecef_km[after] - orbit.pos_ecef_km[before]) / (orbit.epochs[after] - orbit.epochs[before]))
+    })?;
+
+    let a_priori = Ecom::a_priori();
+    let mut params = [
+        observed[0].x, observed[0].y, observed[0].z,
+        velocity.x, velocity.y, velocity.z,
+        a_priori.d0, a_priori.y0, a_priori.b0,
+    ];
+    // parameter perturbations, the fit solves in units of these
+    let deltas = [1e-3, 1e-3, 1e-3, 1e-6, 1e-6, 1e-6, 1e-11, 1e-11, 1e-11];
+    let unknowns = if config.estimate_srp { 9 } else { 6 };
+
+    let model = |params:&[f64; 9]| -> Vec<Vector3> {
+        let (state, srp) = state_from_params(epochs[0], params);
+        ForceModel::new(gravity, srp).propagate(&state, &epochs, config.step_s)
+            .iter()
+            .map(|state| state.pos_ecef_km)
+            .collect()
+    };
+
+    for _ in 0..config.fit_iterations {
+
+        let computed = model(&params);
+        let residuals:Vec<f64> = observed.iter().zip(&computed)
+            .flat_map(|(o, c)| { let d = o - c; [d.x, d.y, d.z] })
+            .collect();
+
+        let partials:Vec<Vec<f64>> = (0..unknowns).map(|j| {
+            let mut perturbed = params;
+            perturbed[j] += deltas[j];
+            model(&perturbed).iter().zip(&computed)
+                .flat_map(|(p, c)| { let d = p - c; [d.x, d.y, d.z] })
+                .collect()
+        }).collect();
+
+        let mut normal = vec![vec![0.0; unknowns]; unknowns];
+        let mut rhs = vec![0.0; unknowns];
+        for j in 0..unknowns {
+            rhs[j] = partials[j].iter().zip(&residuals).map(|(a, b)| a * b).sum();
+            for k in 0..unknowns {
+                normal[j][k] = partials[j].iter().zip(&partials[k]).map(|(a, b)| a * b).sum();
+            }
+        }
+
+        let inverse = invert_matrix(&normal).ok_or_else(|| anyhow!("Singular orbit fit for {}", orbit.sv.satellite))?;
+
+        let mut largest_step:f64 = 0.0;
+        for j in 0..unknowns {
+            let step:f64 = (0..unknowns).map(|k| inverse[j][k] * rhs[k]).sum();
+            params[j] += step * deltas[j];
+            largest_step = largest_step.max(step.abs());
+        }
+
+        if largest_step < 1e-3 {
+            break;
+        }
+    }
+
+    let computed = model(&params);
+    let rms_km = (observed.iter().zip(&computed).map(|(o, c)| (o - c).norm_squared()).sum::<f64>() / observed.len() as f64).sqrt();
+    let (state, srp) = state_from_params(epochs[0], &params);
+
+    Ok(OrbitFit { state, srp, rms_km, samples: samples.len() })
+}
+
+fn state_from_params(epoch:f64, params:&[f64; 9]) -> (State, Ecom) {
+    (
+        State { epoch, pos_ecef_km: Vector3::new(params[0], params[1], params[2]), vel_ecef_km_s: Vector3::new(params[3], params[4], params[5]) },
+        Ecom { d0: params[6], y0: params[7], b0: params[8] },
+    )
+}
+
+// Checks an epoch past the interpolation window against the propagation arc before fitting: no
+// further than max_extrapolation_s past the product end and not before the first fitted sample
+pub fn check_extrapolation(orbit:&Orbit, epoch:f64, config:&PropagatorConfig) -> Result<(), anyhow::Error> {
+
+    if epoch > orbit.valid_to + config.max_extrapolation_s {
+        return Err(ApiError::OutsideCoverage(format!("Epoch {} is more than {} s past the end of the {} orbit", epoch, config.max_extrapolation_s, orbit.sv.get_key())).into());
+    }
+
+    let arc_start = orbit.epochs.iter().copied().find(|sample| *sample >= orbit.valid_to - config.fit_arc_s);
+
+    if arc_start.is_none_or(|arc_start| epoch < arc_start) {
+        return Err(ApiError::OutsideCoverage(format!("Epoch {} is before the propagation arc of {}", epoch, orbit.sv.get_key())).into());
+    }
+
+    Ok(())
+}
+
+// Position at an epoch past the interpolation window, propagated from a fit to the last precise
+// samples. The result holds the single propagated sample and is flagged as extrapolated.
+pub fn extrapolate(orbit:&Orbit, epoch:f64, gravity:&GravityModel, config:&PropagatorConfig) -> Result<Orbit, anyhow::Error> {
+    check_extrapolation(orbit, epoch, config)?;
+    let fit = fit_orbit(orbit, gravity, config)?;
+    Ok(extrapolate_fit(orbit, &fit, epoch, gravity, config))
+}
+
+// extrapolated position from an existing fit of the orbit, the epoch checked by check_extrapolation
+pub fn extrapolate_fit(orbit:&Orbit, fit:&OrbitFit, epoch:f64, gravity:&GravityModel, config:&PropagatorConfig) -> Orbit {
+
+    let state = ForceModel::new(gravity, fit.srp).propagate(&fit.state, &[epoch], config.step_s)[0];
+
+    let mut extrapolated = orbit.clone();
+    extrapolated.epochs = vec![epoch];
+    extrapolated.pos_ecef_km = vec![state.pos_ecef_km];
+    extrapolated.pos_latlonalt = None;
+    extrapolated.clock_usec = None;
+    extrapolated.extrapolated = true;
+
+    extrapolated
+}
@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use arrow_array::{Float64Array, RecordBatch, StringArray, UInt16Array, UInt64Array};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+
+use crate::{algo::{attitude::BlockType, ephemeris::BroadcastEphemeris, ssr::{clock_at, ephemeris_at, rac_difference, SPEED_OF_LIGHT_M_S}}, product::{satellite::{Constellation, SatelliteId}, sv::Orbit}};
+
+// Global average weights of the radial error and of the squared along/cross errors in the
+// SISRE (Montenbruck et al. 2015), they depend on the orbit altitude. None for LEOs, which
+// have no broadcast navigation message.
+pub fn sisre_weights(constellation:Constellation) -> Option<(f64, f64)> {
+    match constellation {
+        Constellation::Gps => Some((0.98, 1.0 / 49.0)),
+        Constellation::Glonass => Some((0.98, 1.0 / 45.0)),
+        Constellation::Galileo => Some((0.98, 1.0 / 61.0)),
+        Constellation::BeiDou => Some((0.98, 1.0 / 54.0)),
+        // geosynchronous orbits
+        Constellation::Qzss | Constellation::NavIC | Constellation::Sbas => Some((0.99, 1.0 / 126.0)),
+        Constellation::Leo => None,
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct SisreConfig {
+    // spacing of the SISRE samples
+    pub interval_s:f64,
+    // SISRE above which a satellite is reported, the full SISRE when clocks are available
+    pub threshold_m:f64,
+}
+
+impl Default for SisreConfig {
+    fn default() -> Self {
+        SisreConfig { interval_s: 300.0, threshold_m: 3.0 }
+    }
+}
+
+// Broadcast minus precise errors at one epoch. The clock error and full SISRE are None when the
+// precise orbit has no clocks.
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct SisreSample {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub iode:u16,
+    pub radial_m:f64,
+    pub along_m:f64,
+    pub cross_m:f64,
+    pub clock_m:Option<f64>,
+    pub orbit_sisre_m:f64,
+    pub sisre_m:Option<f64>,
+}
+
+impl SisreSample {
+
+    // value compared against the alert threshold
+    pub fn monitored_m(&self) -> f64 {
+        self.sisre_m.unwrap_or(self.orbit_sisre_m)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct ConstellationStatistics {
+    pub constellation:Constellation,
+    pub satellites:usize,
+    pub samples:usize,
+    pub orbit_rms_m:f64,
+    pub orbit_p95_m:f64,
+    // over the samples with clocks
+    pub rms_m:Option<f64>,
+    pub p95_m:Option<f64>,
+    pub max_m:f64,
+}
+
+// consecutive samples of one satellite above the threshold
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct SisreAlert {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub from:f64,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub to:f64,
+    pub samples:usize,
+    pub max_m:f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Default)]
+pub struct SisreMonitoring {
+    pub samples:Vec<SisreSample>,
+    pub statistics:Vec<ConstellationStatistics>,
+    pub alerts:Vec<SisreAlert>,
+}
+
+// SISRE samples of one satellite over [from, to]. Epochs without a covering ephemeris or precise
+// sample are skipped.
+pub fn sisre_series(orbit:&Orbit, ephemerides:&[BroadcastEphemeris], block:BlockType, from:f64, to:f64, config:&SisreConfig) -> Result<Vec<SisreSample>, anyhow::Error> {
+
+    if config.interval_s <= 0.0 {
+        return Err(anyhow!("SISRE interval must be positive"));
+    }
+
+    let satellite = orbit.sv.satellite;
+    let Some((radial_weight, along_cross_weight)) = sisre_weights(satellite.constellation) else {
+        return Err(anyhow!("No SISRE weights for {}", satellite));
+    };
+
+    let mut samples = Vec::new();
+
+    let mut epoch = from;
+    while epoch <= to {
+
+        if let Some(ephemeris) = ephemeris_at(ephemerides, epoch)
+            && let Ok(error) = rac_difference(ephemeris, orbit, block, epoch) {
+
+            let along_cross = along_cross_weight * (error.y.powi(2) + error.z.powi(2));
+            let clock_m = clock_at(orbit, epoch).map(|precise| (ephemeris.clock_offset_s(epoch) - precise) * SPEED_OF_LIGHT_M_S);
+
+            samples.push(SisreSample {
+                satellite,
+                epoch,
+                iode: ephemeris.iode,
+                radial_m: error.x,
+                along_m: error.y,
+                cross_m: error.z,
+                clock_m,
+                orbit_sisre_m: ((radial_weight * error.x).powi(2) + along_cross).sqrt(),
+                sisre_m: clock_m.map(|clock_m| ((radial_weight * error.x - clock_m).powi(2) + along_cross).sqrt()),
+            });
+        }
+
+        epoch += config.interval_s;
+    }
+
+    Ok(samples)
+}
+
+fn rms(values:&[f64]) -> f64 {
+    (values.iter().map(|value| value * value).sum::<f64>() / values.len() as f64).sqrt()
+}
+
+fn p95(values:&mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[((values.len() as f64 * 0.95).ceil() as usize).max(1) - 1]
+}
+
+pub fn constellation_statistics(samples:&[SisreSample]) -> Vec<ConstellationStatistics> {
+
+    let mut constellations:Vec<Constellation> = samples.iter().map(|sample| sample.satellite.constellation).collect();
+    constellations.sort();
+    constellations.dedup();
+
+    constellations.into_iter().map(|constellation| {
+
+        let members:Vec<&SisreSample> = samples.iter().filter(|sample| sample.satellite.constellation == constellation).collect();
+
+        let mut satellites:Vec<SatelliteId> = members.iter().map(|sample| sample.satellite).collect();
+        satellites.sort();
+        satellites.dedup();
+
+        let mut orbit:Vec<f64> = members.iter().map(|sample| sample.orbit_sisre_m).collect();
+        let mut full:Vec<f64> = members.iter().filter_map(|sample| sample.sisre_m).collect();
+
+        ConstellationStatistics {
+            constellation,
+            satellites: satellites.len(),
+            samples: members.len(),
+            orbit_rms_m: rms(&orbit),
+            orbit_p95_m: p95(&mut orbit),
+            rms_m: (!full.is_empty()).then(|| rms(&full)),
+            p95_m: (!full.is_empty()).then(|| p95(&mut full)),
+            max_m: members.iter().map(|sample| sample.monitored_m()).fold(0.0, f64::max),
+        }
+    }).collect()
+}
+
+// Alerts for runs of consecutive samples above the threshold, expects the samples of each
+// satellite in epoch order
+pub fn sisre_alerts(samples:&[SisreSample], threshold_m:f64) -> Vec<SisreAlert> {
+
+    let mut alerts:Vec<SisreAlert> = Vec::new();
+    let mut previous:Option<&SisreSample> = None;
+
+    for sample in samples {
+
+        let exceeds = sample.monitored_m() > threshold_m;
+        let continues = previous.is_some_and(|previous| previous.satellite == sample.satellite && previous.monitored_m() > threshold_m);
+
+        if exceeds {
+            match alerts.last_mut() {
+                Some(alert) if continues => {
+                    alert.to = sample.epoch;
+                    alert.samples += 1;
+                    alert.max_m = alert.max_m.max(sample.monitored_m());
+                }
+                _ => alerts.push(SisreAlert {
+                    satellite: sample.satellite,
+                    from: sample.epoch,
+                    to: sample.epoch,
+                    samples: 1,
+                    max_m: sample.monitored_m(),
+                }),
+            }
+        }
+
+        previous = Some(sample);
+    }
+
+    alerts
+}
+
+impl SisreMonitoring {
+
+    pub fn new(samples:Vec<SisreSample>, config:&SisreConfig) -> Self {
+        SisreMonitoring {
+            statistics: constellation_statistics(&samples),
+            alerts: sisre_alerts(&samples, config.threshold_m),
+            samples,
+        }
+    }
+
+    pub fn samples_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("satellite", DataType::Utf8, false),
+            Field::new("gpst_seconds", DataType::Float64, false),
+            Field::new("iode", DataType::UInt16, false),
+            Field::new("radial_m", DataType::Float64, false),
+            Field::new("along_m", DataType::Float64, false),
+            Field::new("cross_m", DataType::Float64, false),
+            Field::new("clock_m", DataType::Float64, true),
+            Field::new("orbit_sisre_m", DataType::Float64, false),
+            Field::new("sisre_m", DataType::Float64, true),
+        ]))
+    }
+
+    pub fn samples_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+
+        let column = |value:fn(&SisreSample) -> f64| Arc::new(Float64Array::from(self.samples.iter().map(value).collect::<Vec<f64>>()));
+
+        RecordBatch::try_new(
+            Self::samples_schema(),
+            vec![
+                Arc::new(StringArray::from(self.samples.iter().map(|sample| sample.satellite.to_string()).collect::<Vec<String>>())),
+                column(|sample| sample.epoch),
+                Arc::new(UInt16Array::from(self.samples.iter().map(|sample| sample.iode).collect::<Vec<u16>>())),
+                column(|sample| sample.radial_m),
+                column(|sample| sample.along_m),
+                column(|sample| sample.cross_m),
+                Arc::new(Float64Array::from(self.samples.iter().map(|sample| sample.clock_m).collect::<Vec<Option<f64>>>())),
+                column(|sample| sample.orbit_sisre_m),
+                Arc::new(Float64Array::from(self.samples.iter().map(|sample| sample.sisre_m).collect::<Vec<Option<f64>>>())),
+            ])
+    }
+
+    pub fn statistics_schema() -> Arc<Schema> {
+        Arc::new(Schema::new(vec![
+            Field::new("constellation", DataType::Utf8, false),
+            Field::new("satellites", DataType::UInt64, false),
+            Field::new("samples", DataType::UInt64, false),
+            Field::new("orbit_rms_m", DataType::Float64, false),
+            Field::new("orbit_p95_m", DataType::Float64, false),
+            Field::new("rms_m", DataType::Float64, true),
+            Field::new("p95_m", DataType::Float64, true),
+            Field::new("max_m", DataType::Float64, false),
+        ]))
+    }
+
+    pub fn statistics_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+
+        let statistics = &self.statistics;
+
+        RecordBatch::try_new(
+            Self::statistics_schema(),
+            vec![
+                Arc::new(StringArray::from(statistics.iter().map(|s| s.constellation.letter().to_string()).collect::<Vec<String>>())),
+                Arc::new(UInt64Array::from(statistics.iter().map(|s| s.satellites as u64).collect::<Vec<u64>>())),
+                Arc::new(UInt64Array::from(statistics.iter().map(|s| s.samples as u64).collect::<Vec<u64>>())),
+                Arc::new(Float64Array::from(statistics.iter().map(|s| s.orbit_rms_m).collect::<Vec<f64>>())),
+                Arc::new(Float64Array::from(statistics.iter().map(|s| s.orbit_p95_m).collect::<Vec<f64>>())),
+                Arc::new(Float64Array::from(statistics.iter().map(|s| s.rms_m).collect::<Vec<Option<f64>>>())),
+                Arc::new(Float64Array::from(statistics.iter().map(|s| s.p95_m).collect::<Vec<Option<f64>>>())),
+                Arc::new(Float64Array::from(statistics.iter().map(|s| s.max_m).collect::<Vec<f64>>())),
+            ])
+    }
+}
@@ -0,0 +1,166 @@
+use anise::math::Vector3;
+use anyhow::anyhow;
+
+use crate::{algo::{attitude::{pco_ecef_km, BlockType}, ephemeris::{rac_basis, BroadcastEphemeris}, sun::sun_moon_analytic_ecef_km}, error::ApiError, product::{satellite::SatelliteId, sv::Orbit}};
+
+pub const SPEED_OF_LIGHT_M_S:f64 = 299_792_458.0;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct SsrConfig {
+    // spacing of orbit and clock correction epochs, RTCM SSR update intervals are 1 s to 3 h
+    pub orbit_interval_s:f64,
+    pub clock_interval_s:f64,
+}
+
+impl Default for SsrConfig {
+    fn default() -> Self {
+        SsrConfig { orbit_interval_s: 60.0, clock_interval_s: 5.0 }
+    }
+}
+
+// RTCM SSR orbit correction: broadcast minus precise position in the radial/along/cross frame
+// of the broadcast orbit, valid for the broadcast ephemeris with the same IODE
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct OrbitCorrection {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub iode:u16,
+    pub radial_m:f64,
+    pub along_m:f64,
+    pub cross_m:f64,
+    pub radial_rate_m_s:f64,
+    pub along_rate_m_s:f64,
+    pub cross_rate_m_s:f64,
+}
+
+// RTCM SSR clock correction polynomial about `epoch` in meters, the precise satellite clock is
+// the broadcast clock minus (C0 + C1 dt + C2 dt^2) / c
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct ClockCorrection {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub iode:u16,
+    pub c0_m:f64,
+    pub c1_m_s:f64,
+    pub c2_m_s2:f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Default)]
+pub struct SsrCorrections {
+    pub orbit:Vec<OrbitCorrection>,
+    pub clock:Vec<ClockCorrection>,
+}
+
+// broadcast ephemeris in use at an epoch, the one with the closest toe that covers it
+pub fn ephemeris_at(ephemerides:&[BroadcastEphemeris], epoch:f64) -> Option<&BroadcastEphemeris> {
+    ephemerides.iter()
+        .filter(|ephemeris| ephemeris.covers(epoch))
+        .min_by(|a, b| (a.toe - epoch).abs().total_cmp(&(b.toe - epoch).abs()))
+}
+
+// broadcast minus precise position in meters, radial/along/cross of the broadcast orbit. Broadcast
+// orbits are for the antenna phase center and precise orbits for the center of mass, so the offset
+// of the block is added to the precise position.
+pub fn rac_difference(ephemeris:&BroadcastEphemeris, orbit:&Orbit, block:BlockType, epoch:f64) -> Result<Vector3, anyhow::Error> {
+
+    let broadcast = ephemeris.position_ecef_km(epoch);
+    let velocity = ephemeris.velocity_ecef_km_s(epoch);
+    let (radial, along, cross) = rac_basis(&broadcast, &velocity);
+
+    let center_of_mass = orbit.position_at(epoch)?;
+    let (sun_pos, _) = sun_moon_analytic_ecef_km(epoch);
+    let phase_center = center_of_mass + pco_ecef_km(block, &center_of_mass, &velocity, &sun_pos);
+
+    let difference = (broadcast - phase_center) * 1_000.0;
+
+    Ok(Vector3::new(difference.dot(&radial), difference.dot(&along), difference.dot(&cross)))
+}
+
+// precise clock offset in seconds, linear between samples
+pub fn clock_at(orbit:&Orbit, epoch:f64) -> Option<f64> {
+
+    let clock_usec = orbit.clock_usec.as_ref()?;
+    let after = orbit.epochs.iter().position(|sample| *sample >= epoch)?;
+
+    if orbit.epochs[after] == epoch {
+        return Some(clock_usec[after] * 1e-6);
+    }
+
+    let before = after.checked_sub(1)?;
+    let fraction = (epoch - orbit.epochs[before]) / (orbit.epochs[after] - orbit.epochs[before]);
+
+    Some((clock_usec[before] + fraction * (clock_usec[after] - clock_usec[before])) * 1e-6)
+}
+
+// Orbit and clock corrections for [from, to] against the broadcast ephemerides of the same
+// satellite. Epochs without a covering ephemeris or precise sample are skipped.
+pub fn ssr_corrections(orbit:&Orbit, ephemerides:&[BroadcastEphemeris], block:BlockType, from:f64, to:f64, config:&SsrConfig) -> Result<SsrCorrections, anyhow::Error> {
+
+    if config.orbit_interval_s <= 0.0 || config.clock_interval_s <= 0.0 {
+        return Err(anyhow!("SSR update intervals must be positive"));
+    }
+
+    // orbit corrections alone would be applied with the broadcast clock, which does not match them
+    if orbit.clock_usec.is_none() {
+        return Err(ApiError::Unprocessable(format!("No precise clocks in the orbit of {}, SSR needs a product with clocks", orbit.sv.get_key())).into());
+    }
+
+    let mut corrections = SsrCorrections::default();
+
+    let mut epoch = from;
+    while epoch <= to {
+
+        if let Some(ephemeris) = ephemeris_at(ephemerides, epoch) {
+
+            // rates by central difference, which includes the rotation of the frame
+            let h = 0.5;
+            if let (Ok(before), Ok(correction), Ok(after)) = (rac_difference(ephemeris, orbit, block, epoch - h), rac_difference(ephemeris, orbit, block, epoch), rac_difference(ephemeris, orbit, block, epoch + h)) {
+                let rate = (after - before) / (2.0 * h);
+                corrections.orbit.push(OrbitCorrection {
+                    satellite: orbit.sv.satellite,
+                    epoch,
+                    iode: ephemeris.iode,
+                    radial_m: correction.x,
+                    along_m: correction.y,
+                    cross_m: correction.z,
+                    radial_rate_m_s: rate.x,
+                    along_rate_m_s: rate.y,
+                    cross_rate_m_s: rate.z,
+                });
+            }
+        }
+
+        epoch += config.orbit_interval_s;
+    }
+
+    let mut epoch = from;
+    while epoch <= to {
+
+        // quadratic through the broadcast minus precise clock at the start, middle and end of the
+        // update interval
+        let h = config.clock_interval_s / 2.0;
+        let difference = |ephemeris:&BroadcastEphemeris, dt:f64| clock_at(orbit, epoch + dt)
+            .map(|precise| (ephemeris.clock_offset_s(epoch + dt) - precise) * SPEED_OF_LIGHT_M_S);
+
+        if let Some(ephemeris) = ephemeris_at(ephemerides, epoch)
+            && let (Some(c0), Some(c_mid), Some(c_end)) = (difference(ephemeris, 0.0), difference(ephemeris, h), difference(ephemeris, 2.0 * h)) {
+
+            let c2 = (c0 - 2.0 * c_mid + c_end) / (2.0 * h * h);
+            corrections.clock.push(ClockCorrection {
+                satellite: orbit.sv.satellite,
+                epoch,
+                iode: ephemeris.iode,
+                c0_m: c0,
+                c1_m_s: (c_mid - c0) / h - c2 * h,
+                c2_m_s2: c2,
+            });
+        }
+
+        epoch += config.clock_interval_s;
+    }
+
+    Ok(corrections)
+}
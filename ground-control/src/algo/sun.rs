@@ -0,0 +1,218 @@
+use std::f64::consts::PI;
+
+use anise::{constants::frames::{IAU_EARTH_FRAME, MOON_J2000, SUN_J2000}, math::Vector3, prelude::Almanac};
+use anyhow::anyhow;
+use hifitime::Epoch;
+
+use crate::{algo::{attitude::{attitude, Attitude, BlockType}, look::EARTH_ROTATION_RAD_S}, product::{satellite::SatelliteId, sv::Orbit}};
+
+pub const SUN_RADIUS_KM:f64 = 696_000.0;
+pub const EARTH_RADIUS_KM:f64 = 6_378.137;
+pub const MOON_RADIUS_KM:f64 = 1_737.4;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub enum EclipseStatus {
+    Sunlit,
+    Penumbra,
+    Umbra,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct SatelliteState {
+    pub satellite:SatelliteId,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    #[schema(value_type = Vec<f64>)]
+    pub pos_ecef_km:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub vel_ecef_km_s:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub sun_ecef_km:Vector3,
+    #[schema(value_type = Vec<f64>)]
+    pub moon_ecef_km:Vector3,
+    pub eclipse:EclipseStatus,
+    // fraction of the solar disk visible from the satellite, 1.0 when sunlit
+    pub sunlit_fraction:f64,
+    // elevation of the Sun above the orbital plane
+    pub beta_angle_deg:f64,
+    // angle from orbit midnight in the direction of motion, 180 is orbit noon
+    pub orbit_angle_deg:f64,
+    pub attitude:Attitude,
+}
+
+// Sun and Moon positions in the Earth body-fixed frame (IAU_EARTH) at a GPST epoch.
+// The almanac must hold planetary ephemerides (e.g. DE440) and the Earth orientation PCK.
+pub fn sun_moon_ecef_km(almanac:&Almanac, epoch:f64) -> Result<(Vector3, Vector3), anyhow::Error> {
+
+    let epoch = Epoch::from_gpst_seconds(epoch);
+
+    let sun = almanac.transform(SUN_J2000, IAU_EARTH_FRAME, epoch, None)
+        .map_err(|err| anyhow!("Sun ephemeris error: {}", err))?;
+    let moon = almanac.transform(MOON_J2000, IAU_EARTH_FRAME, epoch, None)
+        .map_err(|err| anyhow!("Moon ephemeris error: {}", err))?;
+
+    Ok((sun.radius_km, moon.radius_km))
+}
+
+// Low precision Sun and Moon positions in ECEF km (Montenbruck & Gill 3.3.2), rotated from the mean
+// equator and equinox of date by GMST. Good to ~0.1% in distance and a few arcminutes in direction,
+// enough for third-body and SRP forces, and needs no ephemeris files.
+pub fn sun_moon_analytic_ecef_km(epoch:f64) -> (Vector3, Vector3) {
+
+    let epoch = Epoch::from_gpst_seconds(epoch);
+    let t = (epoch.to_jde_tt_days() - 2_451_545.0) / 36_525.0;
+    let arcsec = |value:f64| (value / 3_600.0).to_radians();
+
+    // Sun, mean anomaly and ecliptic longitude of date
+    let m = (357.5256 + 35999.049 * t).to_radians();
+    let sun_longitude = (282.9400 + 1.3972 * t).to_radians() + m + arcsec(6_892.0 * m.sin() + 72.0 * (2.0 * m).sin());
+    let sun_distance = (149.619 - 2.499 * m.cos() - 0.021 * (2.0 * m).cos()) * 1e6;
+    let sun = ecliptic_to_ecef(sun_distance, sun_longitude, 0.0, t, &epoch);
+
+    // Moon, mean longitude and the fundamental arguments l, l', F and D
+    let l0 = (218.31617 + 481267.88088 * t).to_radians();
+    let l = (134.96292 + 477198.86753 * t).to_radians();
+    let lp = (357.52543 + 35999.04944 * t).to_radians();
+    let f = (93.27283 + 483202.01873 * t).to_radians();
+    let d = (297.85027 + 445267.11135 * t).to_radians();
+
+    let moon_longitude = l0 + arcsec(22_640.0 * l.sin() + 769.0 * (2.0 * l).sin()
+        - 4_586.0 * (l - 2.0 * d).sin() + 2_370.0 * (2.0 * d).sin()
+        - 668.0 * lp.sin() - 412.0 * (2.0 * f).sin()
+        - 212.0 * (2.0 * l - 2.0 * d).sin() - 206.0 * (l + lp - 2.0 * d).sin()
+        + 192.0 * (l + 2.0 * d).sin() - 165.0 * (lp - 2.0 * d).sin()
+        + 148.0 * (l - lp).sin() - 125.0 * d.sin()
+        - 110.0 * (l + lp).sin() - 55.0 * (2.0 * f - 2.0 * d).sin());
+
+    let moon_latitude = arcsec(18_520.0 * (f + moon_longitude - l0 + arcsec(412.0 * (2.0 * f).sin() + 541.0 * lp.sin())).sin()
+        - 526.0 * (f - 2.0 * d).sin() + 44.0 * (l + f - 2.0 * d).sin()
+        - 31.0 * (-l + f - 2.0 * d).sin() - 25.0 * (-2.0 * l + f).sin()
+        - 23.0 * (lp + f - 2.0 * d).sin() + 21.0 * (-l + f).sin()
+        + 11.0 * (-lp + f - 2.0 * d).sin());
+
+    let moon_distance = 385_000.0 - 20_905.0 * l.cos() - 3_699.0 * (2.0 * d - l).cos()
+        - 2_956.0 * (2.0 * d).cos() - 570.0 * (2.0 * l).cos() + 246.0 * (2.0 * l - 2.0 * d).cos()
+        - 205.0 * (lp - 2.0 * d).cos() - 171.0 * (l + 2.0 * d).cos() - 152.0 * (l + lp - 2.0 * d).cos();
+
+    let moon = ecliptic_to_ecef(moon_distance, moon_longitude, moon_latitude, t, &epoch);
+
+    (sun, moon)
+}
+
+fn ecliptic_to_ecef(distance:f64, longitude:f64, latitude:f64, t:f64, epoch:&Epoch) -> Vector3 {
+
+    let obliquity = (23.43929111 - 0.0130042 * t).to_radians();
+    let (x, y, z) = (distance * longitude.cos() * latitude.cos(), distance * longitude.sin() * latitude.cos(), distance * latitude.sin());
+    let (y, z) = (y * obliquity.cos() - z * obliquity.sin(), y * obliquity.sin() + z * obliquity.cos());
+
+    let gmst = gmst_rad(epoch);
+
+    Vector3::new(x * gmst.cos() + y * gmst.sin(), -x * gmst.sin() + y * gmst.cos(), z)
+}
+
+// Greenwich mean sidereal time (IAU 1982), UT1 taken as UTC
+pub fn gmst_rad(epoch:&Epoch) -> f64 {
+    let days = epoch.to_jde_utc_days() - 2_451_545.0;
+    let t = days / 36_525.0;
+    (280.46061837 + 360.98564736629 * days + 0.000387933 * t * t).rem_euclid(360.0).to_radians()
+}
+
+// Fraction of the solar disk visible from `sv_pos` when a spherical body occults it (conical
+// shadow model, Montenbruck & Gill 3.4.2). All positions in the same frame.
+pub fn occultation_fraction(sv_pos:&Vector3, sun_pos:&Vector3, body_pos:&Vector3, body_radius_km:f64) -> f64 {
+
+    let to_sun = sun_pos - sv_pos;
+    let to_body = body_pos - sv_pos;
+
+    // apparent radii of the Sun and the occulting body and their apparent separation
+    let a = (SUN_RADIUS_KM / to_sun.norm()).asin();
+    let b = (body_radius_km / to_body.norm()).min(1.0).asin();
+    let c = (to_sun.dot(&to_body) / (to_sun.norm() * to_body.norm())).clamp(-1.0, 1.0).acos();
+
+    if c >= a + b {
+        return 1.0;
+    }
+
+    if c <= b - a {
+        return 0.0;
+    }
+
+    if c <= a - b {
+        // annular, body entirely within the solar disk
+        return 1.0 - (b * b) / (a * a);
+    }
+
+    let x = (c * c + a * a - b * b) / (2.0 * c);
+    let y = (a * a - x * x).max(0.0).sqrt();
+    let overlap = a * a * (x / a).clamp(-1.0, 1.0).acos() + b * b * ((c - x) / b).clamp(-1.0, 1.0).acos() - c * y;
+
+    1.0 - overlap / (PI * a * a)
+}
+
+// Eclipse status from Earth and Moon occultation, positions in ECEF km
+pub fn eclipse_status(sv_pos:&Vector3, sun_pos:&Vector3, moon_pos:&Vector3) -> (EclipseStatus, f64) {
+
+    let earth = occultation_fraction(sv_pos, sun_pos, &Vector3::zeros(), EARTH_RADIUS_KM);
+    let moon = occultation_fraction(sv_pos, sun_pos, moon_pos, MOON_RADIUS_KM);
+
+    let fraction = earth * moon;
+
+    let status = if fraction >= 1.0 {
+        EclipseStatus::Sunlit
+    } else if fraction <= 0.0 {
+        EclipseStatus::Umbra
+    } else {
+        EclipseStatus::Penumbra
+    };
+
+    (status, fraction)
+}
+
+// Orbit normal from ECEF position and velocity, using the inertial velocity (v + w x r)
+pub fn orbit_normal(pos:&Vector3, vel:&Vector3) -> Vector3 {
+    let earth_rotation = Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S);
+    let inertial_vel = vel + earth_rotation.cross(pos);
+    pos.cross(&inertial_vel).normalize()
+}
+
+// Sun elevation above the orbital plane (beta) and the orbit angle from orbit midnight, in degrees
+pub fn beta_and_orbit_angle(pos:&Vector3, vel:&Vector3, sun_pos:&Vector3) -> (f64, f64) {
+
+    let normal = orbit_normal(pos, vel);
+    let sun_dir = sun_pos.normalize();
+
+    let beta = sun_dir.dot(&normal).clamp(-1.0, 1.0).asin();
+
+    // projection of the Sun direction into the orbital plane points at orbit noon
+    let noon = (sun_dir - sun_dir.dot(&normal) * normal).normalize();
+    let midnight = -noon;
+    let sv_dir = pos.normalize();
+
+    let orbit_angle = midnight.cross(&sv_dir).dot(&normal).atan2(midnight.dot(&sv_dir));
+
+    (beta.to_degrees(), orbit_angle.to_degrees().rem_euclid(360.0))
+}
+
+pub fn satellite_state(orbit:&Orbit, epoch:f64, sun_pos:&Vector3, moon_pos:&Vector3, block:BlockType) -> Result<SatelliteState, anyhow::Error> {
+
+    let pos = orbit.position_at(epoch)?;
+    let vel = orbit.velocity_at(epoch)?;
+
+    let (eclipse, sunlit_fraction) = eclipse_status(&pos, sun_pos, moon_pos);
+    let (beta_angle_deg, orbit_angle_deg) = beta_and_orbit_angle(&pos, &vel, sun_pos);
+    let attitude = attitude(block, &pos, &vel, beta_angle_deg, orbit_angle_deg);
+
+    Ok(SatelliteState {
+        satellite: orbit.sv.satellite,
+        epoch,
+        pos_ecef_km: pos,
+        vel_ecef_km_s: vel,
+        sun_ecef_km: *sun_pos,
+        moon_ecef_km: *moon_pos,
+        eclipse,
+        sunlit_fraction,
+        beta_angle_deg,
+        orbit_angle_deg,
+        attitude,
+    })
+}
@@ -0,0 +1,475 @@
+use std::f64::consts::PI;
+
+use anise::math::Vector3;
+use anyhow::anyhow;
+use hifitime::Epoch;
+
+use crate::{algo::{ephemeris::rac_basis, look::EARTH_ROTATION_RAD_S, sun::gmst_rad, util::invert_matrix}, gpst::iso_utc, product::{satellite::SatelliteId, sv::{lagrange_interpolate, Orbit}}};
+
+// gravitational parameter of the SGP4 WGS84 constants
+pub const SGP4_GM_KM3_S2:f64 = 398_600.5;
+
+const MINUTES_PER_DAY:f64 = 1_440.0;
+const J2000_JDE_UTC:f64 = 2_451_545.0;
+const DAYS_PER_JULIAN_YEAR:f64 = 365.25;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct TleConfig {
+    // trailing arc of the precise orbit fitted, the elements epoch is its last sample
+    pub fit_arc_s:f64,
+    // spacing of the fitted samples
+    pub step_s:f64,
+    pub iterations:usize,
+}
+
+impl Default for TleConfig {
+    fn default() -> Self {
+        TleConfig { fit_arc_s: 86_400.0, step_s: 900.0, iterations: 20 }
+    }
+}
+
+// catalog identifiers written to TLE and OMM output
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct TleIdentity {
+    pub name:String,
+    pub norad_id:u32,
+    // YYYY-NNNP
+    pub cospar_id:String,
+}
+
+impl TleIdentity {
+
+    // placeholder identity when the satellite is not in the catalog
+    pub fn from_satellite(satellite:SatelliteId) -> Self {
+        TleIdentity { name: satellite.to_string(), norad_id: 0, cospar_id: String::new() }
+    }
+
+    // international designator as written in TLE line 1, 2011-036A -> 11036A
+    fn designator(&self) -> String {
+        match self.cospar_id.split_once('-') {
+            Some((year, piece)) if year.len() == 4 => format!("{}{}", &year[2..], piece),
+            _ => String::new(),
+        }
+    }
+}
+
+// SGP4 mean elements (Kozai mean motion, TEME), angles in degrees
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct MeanElements {
+    pub satellite:SatelliteId,
+    // GPST seconds
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub inclination_deg:f64,
+    pub raan_deg:f64,
+    pub eccentricity:f64,
+    pub arg_perigee_deg:f64,
+    pub mean_anomaly_deg:f64,
+    pub mean_motion_rev_day:f64,
+    pub bstar:f64,
+}
+
+// SGP4 minus precise position in the radial/along/cross frame of the SGP4 orbit
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Sgp4Residual {
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub radial_km:f64,
+    pub along_km:f64,
+    pub cross_km:f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Sgp4Statistics {
+    pub samples:usize,
+    pub rms_radial_km:f64,
+    pub rms_along_km:f64,
+    pub rms_cross_km:f64,
+    pub rms_km:f64,
+    pub max_km:f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct TleFit {
+    pub identity:TleIdentity,
+    pub elements:MeanElements,
+    pub residuals:Vec<Sgp4Residual>,
+    pub statistics:Sgp4Statistics,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct TwoLineElements {
+    #[serde(default)]
+    pub name:Option<String>,
+    pub line1:String,
+    pub line2:String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Sgp4Comparison {
+    pub satellite:SatelliteId,
+    pub norad_id:u64,
+    // TLE epoch, GPST seconds
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub tle_epoch:f64,
+    pub residuals:Vec<Sgp4Residual>,
+    pub statistics:Sgp4Statistics,
+}
+
+// SGP4 epoch, Julian years since 2000-01-01 12:00 UTC
+fn sgp4_epoch(epoch:f64) -> f64 {
+    (Epoch::from_gpst_seconds(epoch).to_jde_utc_days() - J2000_JDE_UTC) / DAYS_PER_JULIAN_YEAR
+}
+
+// TEME and ECEF differ by the rotation about z by GMST, polar motion is neglected
+fn rotate_z(v:&Vector3, angle:f64) -> Vector3 {
+    Vector3::new(v.x * angle.cos() + v.y * angle.sin(), -v.x * angle.sin() + v.y * angle.cos(), v.z)
+}
+
+pub fn teme_to_ecef(pos:&Vector3, vel:&Vector3, epoch:f64) -> (Vector3, Vector3) {
+    let gmst = gmst_rad(&Epoch::from_gpst_seconds(epoch));
+    let pos_ecef = rotate_z(pos, gmst);
+    let vel_ecef = rotate_z(vel, gmst) - Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(&pos_ecef);
+    (pos_ecef, vel_ecef)
+}
+
+pub fn ecef_to_teme(pos:&Vector3, vel:&Vector3, epoch:f64) -> (Vector3, Vector3) {
+    let gmst = gmst_rad(&Epoch::from_gpst_seconds(epoch));
+    let inertial_vel = vel + Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(pos);
+    (rotate_z(pos, -gmst), rotate_z(&inertial_vel, -gmst))
+}
+
+// ECEF position and velocity at `epoch` (GPST seconds) from SGP4 constants with epoch `epoch_0`
+fn predict(constants:&sgp4::Constants, epoch_0:f64, epoch:f64) -> Result<(Vector3, Vector3), anyhow::Error> {
+    let prediction = constants.propagate(sgp4::MinutesSinceEpoch((epoch - epoch_0) / 60.0))
+        .map_err(|err| anyhow!("SGP4 propagation failed: {}", err))?;
+    let [x, y, z] = prediction.position;
+    let [vx, vy, vz] = prediction.velocity;
+    Ok(teme_to_ecef(&Vector3::new(x, y, z), &Vector3::new(vx, vy, vz), epoch))
+}
+
+impl MeanElements {
+
+    fn constants(&self) -> Result<sgp4::Constants, anyhow::Error> {
+
+        let orbit = sgp4::Orbit::from_kozai_elements(&sgp4::WGS84,
+            self.inclination_deg.to_radians(),
+            self.raan_deg.to_radians(),
+            self.eccentricity,
+            self.arg_perigee_deg.to_radians(),
+            self.mean_anomaly_deg.to_radians(),
+            self.mean_motion_rev_day * 2.0 * PI / MINUTES_PER_DAY)
+            .map_err(|err| anyhow!("Invalid mean elements for {}: {}", self.satellite, err))?;
+
+        sgp4::Constants::new(sgp4::WGS84, sgp4::iau_epoch_to_sidereal_time, sgp4_epoch(self.epoch), self.bstar, orbit)
+            .map_err(|err| anyhow!("Invalid mean elements for {}: {}", self.satellite, err))
+    }
+
+    pub fn position_ecef_km(&self, epoch:f64) -> Result<Vector3, anyhow::Error> {
+        Ok(predict(&self.constants()?, self.epoch, epoch)?.0)
+    }
+
+    // [n (rad/min), e cos(w), e sin(w), i, raan, w + M], the mean longitude from the node stays
+    // well determined for near circular orbits where w and M are not
+    fn fit_params(&self) -> [f64; 6] {
+        let (e, w) = (self.eccentricity, self.arg_perigee_deg.to_radians());
+        [
+            self.mean_motion_rev_day * 2.0 * PI / MINUTES_PER_DAY,
+            e * w.cos(),
+            e * w.sin(),
+            self.inclination_deg.to_radians(),
+            self.raan_deg.to_radians(),
+            w + self.mean_anomaly_deg.to_radians(),
+        ]
+    }
+
+    fn with_fit_params(&self, params:&[f64; 6]) -> Self {
+        let w = params[2].atan2(params[1]);
+        MeanElements {
+            inclination_deg: params[3].to_degrees(),
+            raan_deg: params[4].to_degrees().rem_euclid(360.0),
+            eccentricity: params[1].hypot(params[2]),
+            arg_perigee_deg: w.to_degrees().rem_euclid(360.0),
+            mean_anomaly_deg: (params[5] - w).to_degrees().rem_euclid(360.0),
+            mean_motion_rev_day: params[0] * MINUTES_PER_DAY / (2.0 * PI),
+            ..self.clone()
+        }
+    }
+
+    // NORAD two-line element set, classification U, no drag terms beyond B*
+    pub fn tle(&self, identity:&TleIdentity) -> Result<[String; 2], anyhow::Error> {
+
+        let catalog_number = alpha5(identity.norad_id)?;
+
+        let epoch = Epoch::from_gpst_seconds(self.epoch);
+        let (year, ..) = epoch.to_gregorian_utc();
+        let day_of_year = 1.0 + epoch.to_jde_utc_days() - Epoch::from_gregorian_utc_at_midnight(year, 1, 1).to_jde_utc_days();
+
+        let line1 = format!("1 {}U {:<8} {:02}{:012.8}  .00000000  00000-0 {} 0  999",
+            catalog_number, identity.designator(), year.rem_euclid(100), day_of_year, tle_exponent(self.bstar));
+
+        let line2 = format!("2 {} {:8.4} {:8.4} {:07} {:8.4} {:8.4} {:11.8}{:5}",
+            catalog_number,
+            self.inclination_deg,
+            self.raan_deg,
+            (self.eccentricity * 1e7).round() as u64,
+            self.arg_perigee_deg,
+            self.mean_anomaly_deg,
+            self.mean_motion_rev_day,
+            0);
+
+        Ok([with_checksum(line1), with_checksum(line2)])
+    }
+
+    // CCSDS Orbit Mean-Elements Message (502.0-B-3) in KVN
+    pub fn omm(&self, identity:&TleIdentity, creation:&Epoch) -> String {
+
+        let lines = [
+            "CCSDS_OMM_VERS = 3.0".to_string(),
+            format!("CREATION_DATE = {}", iso_utc(creation)),
+            "ORIGINATOR = GROUND-CONTROL".to_string(),
+            String::new(),
+            format!("OBJECT_NAME = {}", identity.name),
+            format!("OBJECT_ID = {}", identity.cospar_id),
+            "CENTER_NAME = EARTH".to_string(),
+            "REF_FRAME = TEME".to_string(),
+            "TIME_SYSTEM = UTC".to_string(),
+            "MEAN_ELEMENT_THEORY = SGP4".to_string(),
+            String::new(),
+            format!("EPOCH = {}", iso_utc(&Epoch::from_gpst_seconds(self.epoch))),
+            format!("MEAN_MOTION = {:.8}", self.mean_motion_rev_day),
+            format!("ECCENTRICITY = {:.7}", self.eccentricity),
+            format!("INCLINATION = {:.4}", self.inclination_deg),
+            format!("RA_OF_ASC_NODE = {:.4}", self.raan_deg),
+            format!("ARG_OF_PERICENTER = {:.4}", self.arg_perigee_deg),
+            format!("MEAN_ANOMALY = {:.4}", self.mean_anomaly_deg),
+            String::new(),
+            "EPHEMERIS_TYPE = 0".to_string(),
+            "CLASSIFICATION_TYPE = U".to_string(),
+            format!("NORAD_CAT_ID = {}", identity.norad_id),
+            "ELEMENT_SET_NO = 999".to_string(),
+            "REV_AT_EPOCH = 0".to_string(),
+            format!("BSTAR = {:.8}", self.bstar),
+            "MEAN_MOTION_DOT = 0.00000000".to_string(),
+            "MEAN_MOTION_DDOT = 0.0".to_string(),
+        ];
+
+        lines.join("\n") + "\n"
+    }
+}
+
+// Five character catalog number, Alpha-5 above 99999: a letter for the ten thousands (A = 10,
+// I and O skipped) followed by four digits, up to Z9999 = 339999
+pub fn alpha5(norad_id:u32) -> Result<String, anyhow::Error> {
+
+    if norad_id < 100_000 {
+        return Ok(format!("{:05}", norad_id));
+    }
+
+    let letter = "ABCDEFGHJKLMNPQRSTUVWXYZ".chars().nth((norad_id / 10_000 - 10) as usize)
+        .ok_or_else(|| anyhow!("NORAD ID {} does not fit a TLE", norad_id))?;
+
+    Ok(format!("{}{:04}", letter, norad_id % 10_000))
+}
+
+// TLE assumed decimal point notation, 0.00012345 -> " 12345-3"
+fn tle_exponent(value:f64) -> String {
+
+    if value == 0.0 {
+        return " 00000-0".to_string();
+    }
+
+    let mut exponent = value.abs().log10().floor() as i32 + 1;
+    let mut mantissa = (value.abs() / 10f64.powi(exponent) * 1e5).round() as i64;
+    if mantissa >= 100_000 {
+        mantissa /= 10;
+        exponent += 1;
+    }
+
+    format!("{}{:05}{}{}", if value < 0.0 { '-' } else { ' ' }, mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+}
+
+// modulo 10 sum of the digits with minus signs counting one
+pub fn tle_checksum(line:&str) -> u32 {
+    line.chars().take(68).map(|c| match c {
+        '-' => 1,
+        c => c.to_digit(10).unwrap_or(0),
+    }).sum::<u32>() % 10
+}
+
+fn with_checksum(line:String) -> String {
+    let checksum = tle_checksum(&line);
+    format!("{}{}", line, checksum)
+}
+
+fn residual(epoch:f64, pos:&Vector3, vel:&Vector3, precise:&Vector3) -> Sgp4Residual {
+    let (radial, along, cross) = rac_basis(pos, vel);
+    let d = pos - precise;
+    Sgp4Residual { epoch, radial_km: d.dot(&radial), along_km: d.dot(&along), cross_km: d.dot(&cross) }
+}
+
+fn statistics(residuals:&[Sgp4Residual]) -> Sgp4Statistics {
+    let n = residuals.len() as f64;
+    let rms = |value:fn(&Sgp4Residual) -> f64| (residuals.iter().map(|r| value(r).powi(2)).sum::<f64>() / n).sqrt();
+    let (rms_radial_km, rms_along_km, rms_cross_km) = (rms(|r| r.radial_km), rms(|r| r.along_km), rms(|r| r.cross_km));
+    Sgp4Statistics {
+        samples: residuals.len(),
+        rms_radial_km,
+        rms_along_km,
+        rms_cross_km,
+        rms_km: (rms_radial_km.powi(2) + rms_along_km.powi(2) + rms_cross_km.powi(2)).sqrt(),
+        max_km: residuals.iter().map(|r| (r.radial_km.powi(2) + r.along_km.powi(2) + r.cross_km.powi(2)).sqrt()).fold(0.0, f64::max),
+    }
+}
+
+// Osculating Kepler elements of a TEME state taken as mean elements, the starting point of the fit
+fn osculating_elements(satellite:SatelliteId, epoch:f64, pos:&Vector3, vel:&Vector3) -> MeanElements {
+
+    let r = pos.norm();
+    let h = pos.cross(vel);
+
+    let a = 1.0 / (2.0 / r - vel.norm_squared() / SGP4_GM_KM3_S2);
+    let e_cos = 1.0 - r / a;
+    let e_sin = pos.dot(vel) / (SGP4_GM_KM3_S2 * a).sqrt();
+    let e = (e_cos * e_cos + e_sin * e_sin).sqrt();
+    let eccentric_anomaly = e_sin.atan2(e_cos);
+
+    let inclination = (h.z / h.norm()).acos();
+    let node = h.x.atan2(-h.y);
+    let latitude_argument = (pos.z / inclination.sin()).atan2(pos.x * node.cos() + pos.y * node.sin());
+    let true_anomaly = ((1.0 - e * e).sqrt() * eccentric_anomaly.sin()).atan2(eccentric_anomaly.cos() - e);
+
+    MeanElements {
+        satellite,
+        epoch,
+        inclination_deg: inclination.to_degrees(),
+        raan_deg: node.to_degrees().rem_euclid(360.0),
+        eccentricity: e,
+        arg_perigee_deg: (latitude_argument - true_anomaly).to_degrees().rem_euclid(360.0),
+        mean_anomaly_deg: (eccentric_anomaly - e * eccentric_anomaly.sin()).to_degrees().rem_euclid(360.0),
+        mean_motion_rev_day: (SGP4_GM_KM3_S2 / a.powi(3)).sqrt() * 86_400.0 / (2.0 * PI),
+        bstar: 0.0,
+    }
+}
+
+// SGP4 mean elements fitted to the trailing arc of a precise orbit, epoch at its last sample.
+// Gauss-Newton from the osculating elements; drag is not estimated (B* = 0).
+pub fn fit_mean_elements(orbit:&Orbit, identity:TleIdentity, config:&TleConfig) -> Result<TleFit, anyhow::Error> {
+
+    let Some(&epoch) = orbit.epochs.last() else {
+        return Err(anyhow!("Orbit {} has no samples", orbit.sv.get_key()));
+    };
+
+    // every stride-th sample counted back from the elements epoch
+    let stride = ((config.step_s / orbit.sampling_resolution).round() as usize).max(1);
+    let last = orbit.epochs.len() - 1;
+    let samples:Vec<usize> = (0..=last).rev()
+        .step_by(stride)
+        .take_while(|i| orbit.epochs[*i] >= epoch - config.fit_arc_s)
+        .collect();
+
+    if samples.len() < 12 {
+        return Err(anyhow!("Not enough samples to fit mean elements for {}", orbit.sv.satellite));
+    }
+
+    let epochs:Vec<f64> = samples.iter().map(|i| orbit.epochs[*i]).collect();
+    let observed:Vec<Vector3> = samples.iter().map(|i| orbit.pos_ecef_km[*i]).collect();
+
+    // initial state from the last nine samples
+    let window = last - 8..last + 1;
+    let interpolate = |epoch:f64| lagrange_interpolate(&orbit.epochs[window.clone()], &orbit.pos_ecef_km[window.clone()], epoch);
+    let (pos, vel) = ecef_to_teme(&interpolate(epoch), &(interpolate(epoch + 0.5) - interpolate(epoch - 0.5)), epoch);
+
+    let elements = osculating_elements(orbit.sv.satellite, epoch, &pos, &vel);
+
+    let deltas = [1e-10, 1e-7, 1e-7, 1e-7, 1e-7, 1e-7];
+    let mut params = elements.fit_params();
+
+    let model = |params:&[f64; 6]| -> Result<Vec<Vector3>, anyhow::Error> {
+        let constants = elements.with_fit_params(params).constants()?;
+        epochs.iter().map(|t| Ok(predict(&constants, epoch, *t)?.0)).collect()
+    };
+
+    for _ in 0..config.iterations {
+
+        let computed = model(&params)?;
+        let residuals:Vec<f64> = observed.iter().zip(&computed)
+            .flat_map(|(o, c)| { let d = o - c; [d.x, d.y, d.z] })
+            .collect();
+
+        let mut partials:Vec<Vec<f64>> = Vec::with_capacity(6);
+        for j in 0..6 {
+            let mut perturbed = params;
+            perturbed[j] += deltas[j];
+            partials.push(model(&perturbed)?.iter().zip(&computed)
+                .flat_map(|(p, c)| { let d = p - c; [d.x, d.y, d.z] })
+                .collect());
+        }
+
+        let mut normal = vec![vec![0.0; 6]; 6];
+        let mut rhs = [0.0; 6];
+        for j in 0..6 {
+            rhs[j] = partials[j].iter().zip(&residuals).map(|(a, b)| a * b).sum();
+            for k in 0..6 {
+                normal[j][k] = partials[j].iter().zip(&partials[k]).map(|(a, b)| a * b).sum();
+            }
+        }
+
+        let inverse = invert_matrix(&normal).ok_or_else(|| anyhow!("Singular mean element fit for {}", orbit.sv.satellite))?;
+
+        let mut largest_step:f64 = 0.0;
+        for j in 0..6 {
+            let step:f64 = (0..6).map(|k| inverse[j][k] * rhs[k]).sum();
+            params[j] += step * deltas[j];
+            largest_step = largest_step.max(step.abs());
+        }
+
+        if largest_step < 0.1 {
+            break;
+        }
+    }
+
+    let elements = elements.with_fit_params(&params);
+    let constants = elements.constants()?;
+
+    let residuals = epochs.iter().zip(&observed)
+        .map(|(t, precise)| {
+            let (pos, vel) = predict(&constants, epoch, *t)?;
+            Ok(residual(*t, &pos, &vel, precise))
+        })
+        .collect::<Result<Vec<Sgp4Residual>, anyhow::Error>>()?;
+
+    Ok(TleFit { identity, statistics: statistics(&residuals), elements, residuals })
+}
+
+// Propagates a TLE with SGP4 over the precise samples in [from, to] and compares positions
+pub fn compare_tle(tle:&TwoLineElements, orbit:&Orbit, from:f64, to:f64) -> Result<Sgp4Comparison, anyhow::Error> {
+
+    let elements = sgp4::Elements::from_tle(tle.name.clone(), tle.line1.as_bytes(), tle.line2.as_bytes())
+        .map_err(|err| anyhow!("Invalid TLE: {}", err))?;
+    let constants = sgp4::Constants::from_elements(&elements)
+        .map_err(|err| anyhow!("Invalid TLE: {}", err))?;
+
+    let tle_epoch = Epoch::from_jde_utc(J2000_JDE_UTC + elements.epoch() * DAYS_PER_JULIAN_YEAR).to_gpst_seconds();
+
+    let residuals = orbit.epochs.iter().zip(&orbit.pos_ecef_km)
+        .filter(|(t, _)| **t >= from && **t <= to)
+        .map(|(t, precise)| {
+            let (pos, vel) = predict(&constants, tle_epoch, *t)?;
+            Ok(residual(*t, &pos, &vel, precise))
+        })
+        .collect::<Result<Vec<Sgp4Residual>, anyhow::Error>>()?;
+
+    if residuals.is_empty() {
+        return Err(anyhow!("No precise samples of {} between {} and {}", orbit.sv.satellite, from, to));
+    }
+
+    Ok(Sgp4Comparison {
+        satellite: orbit.sv.satellite,
+        norad_id: elements.norad_id,
+        tle_epoch,
+        statistics: statistics(&residuals),
+        residuals,
+    })
+}
@@ -0,0 +1,131 @@
+use anise::math::Vector3;
+
+use crate::{algo::{propagate::State, util::ecef_to_latlon}, product::{satellite::SatelliteId, sv::Orbit}};
+
+// samples further apart than this multiple of the sampling step start a new arc
+const GAP_FACTOR:f64 = 1.5;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+pub struct SubSatellitePoint {
+    pub epoch:f64,
+    // geodetic WGS84
+    pub lat_deg:f64,
+    pub lon_deg:f64,
+    pub alt_km:f64,
+}
+
+impl SubSatellitePoint {
+
+    pub fn from_ecef(epoch:f64, pos_ecef_km:&Vector3) -> Self {
+        let (lat, lon, alt) = ecef_to_latlon(pos_ecef_km.x * 1_000.0, pos_ecef_km.y * 1_000.0, pos_ecef_km.z * 1_000.0);
+        SubSatellitePoint { epoch, lat_deg: lat.to_degrees(), lon_deg: lon.to_degrees(), alt_km: alt / 1_000.0 }
+    }
+}
+
+// sub-satellite point of an orbit position response, with the source that answered
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct SatellitePosition {
+    pub satellite:SatelliteId,
+    pub source:String,
+    pub extrapolated:bool,
+    pub point:SubSatellitePoint,
+}
+
+impl SatellitePosition {
+
+    // the position response window contains the requested epoch, otherwise the nearest sample is used
+    pub fn from_orbit(orbit:&Orbit, epoch:f64) -> Option<Self> {
+
+        let index = orbit.epochs.iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - epoch).abs().total_cmp(&(*b - epoch).abs()))
+            .map(|(index, _)| index)?;
+
+        Some(SatellitePosition {
+            satellite: orbit.sv.satellite,
+            source: orbit.sv.data_source.get_key(),
+            extrapolated: orbit.extrapolated,
+            point: SubSatellitePoint::from_ecef(orbit.epochs[index], &orbit.pos_ecef_km[index]),
+        })
+    }
+}
+
+// sampling step of a state series, the smallest spacing between its states
+fn sampling_step(states:&[State]) -> Option<f64> {
+    states.windows(2)
+        .map(|pair| pair[1].epoch - pair[0].epoch)
+        .filter(|step| *step > 0.0)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+// runs of states without gaps in the sampling
+pub fn continuous_arcs(states:&[State]) -> Vec<&[State]> {
+
+    if states.is_empty() {
+        return Vec::new();
+    }
+
+    let Some(step) = sampling_step(states) else {
+        return vec![states];
+    };
+
+    let mut arcs = Vec::new();
+    let mut arc_start = 0;
+
+    for (i, pair) in states.windows(2).enumerate() {
+        if pair[1].epoch - pair[0].epoch > GAP_FACTOR * step {
+            arcs.push(&states[arc_start..=i]);
+            arc_start = i + 1;
+        }
+    }
+    arcs.push(&states[arc_start..]);
+
+    arcs
+}
+
+// point where the track between two points reaches longitude +-180, with the longitude
+// of the second point unwrapped to the same side
+fn antimeridian_crossing(from:&SubSatellitePoint, to:&SubSatellitePoint) -> SubSatellitePoint {
+
+    let boundary = 180.0f64.copysign(from.lon_deg);
+    let to_lon = to.lon_deg + 360.0f64.copysign(from.lon_deg);
+    let fraction = (boundary - from.lon_deg) / (to_lon - from.lon_deg);
+
+    SubSatellitePoint {
+        epoch: from.epoch + fraction * (to.epoch - from.epoch),
+        lat_deg: from.lat_deg + fraction * (to.lat_deg - from.lat_deg),
+        lon_deg: boundary,
+        alt_km: from.alt_km + fraction * (to.alt_km - from.alt_km),
+    }
+}
+
+// Ground track as line parts split at gaps in the states and at the antimeridian. Each crossing
+// ends one part at longitude +-180 and starts the next at the opposite sign, so no segment of a
+// line spans more than 180 degrees of longitude.
+pub fn ground_track(states:&[State]) -> Vec<Vec<SubSatellitePoint>> {
+
+    let mut parts = Vec::new();
+
+    for arc in continuous_arcs(states) {
+
+        let mut part:Vec<SubSatellitePoint> = Vec::new();
+
+        for state in arc {
+
+            let point = SubSatellitePoint::from_ecef(state.epoch, &state.pos_ecef_km);
+
+            if let Some(last) = part.last() && (point.lon_deg - last.lon_deg).abs() > 180.0 {
+                let crossing = antimeridian_crossing(last, &point);
+                part.push(crossing);
+                parts.push(std::mem::take(&mut part));
+                part.push(SubSatellitePoint { lon_deg: -crossing.lon_deg, ..crossing });
+            }
+
+            part.push(point);
+        }
+
+        parts.push(part);
+    }
+
+    parts
+}
@@ -73,3 +73,96 @@ pub fn ecef_to_latlon(x: f64, y: f64, z: f64) -> (f64, f64, f64) {
 
     (lat, lon, ht)
 }
+
+// Converts geodetic coordinates (latitude, longitude, height) to ECEF coordinates
+// on the WGS-84 ellipsoid, inverse of ecef_to_latlon
+
+// # Arguments
+// * `lat` - latitude in radians
+// * `lon` - longitude in radians
+// * `h` - ellipsoidal height in meters
+
+// # Returns
+// A tuple of (x, y, z) in meters
+
+pub fn latlon_to_ecef(lat: f64, lon: f64, h: f64) -> (f64, f64, f64) {
+    let a = 6378137.0; // semi-major axis
+    let e2 = 6.6943799901377997e-3; // first eccentricity squared
+
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_lon, cos_lon) = lon.sin_cos();
+
+    // prime vertical radius of curvature
+    let n = a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+
+    let x = (n + h) * cos_lat * cos_lon;
+    let y = (n + h) * cos_lat * sin_lon;
+    let z = (n * (1.0 - e2) + h) * sin_lat;
+
+    (x, y, z)
+}
+
+// Rotates an ECEF difference vector into the local east/north/up frame
+// at the given geodetic latitude and longitude (radians)
+
+pub fn ecef_to_enu(dx: f64, dy: f64, dz: f64, lat: f64, lon: f64) -> (f64, f64, f64) {
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_lon, cos_lon) = lon.sin_cos();
+
+    let e = -sin_lon * dx + cos_lon * dy;
+    let n = -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz;
+    let u = cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz;
+
+    (e, n, u)
+}
+
+// Inverts a square matrix using Gauss-Jordan elimination with partial pivoting,
+// returns None for singular (or numerically singular) matrices
+
+pub fn invert_matrix(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut a: Vec<Vec<f64>> = matrix.to_vec();
+    let mut inverse: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+
+    let scale = matrix
+        .iter()
+        .flat_map(|row| row.iter())
+        .fold(0.0_f64, |max, v| max.max(v.abs()));
+
+    if scale == 0.0 {
+        return None;
+    }
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+
+        if a[pivot][col].abs() < scale * 1e-14 {
+            return None;
+        }
+
+        a.swap(col, pivot);
+        inverse.swap(col, pivot);
+
+        let pivot_value = a[col][col];
+        for j in 0..n {
+            a[col][j] /= pivot_value;
+            inverse[col][j] /= pivot_value;
+        }
+
+        for row in 0..n {
+            if row != col {
+                let factor = a[row][col];
+                if factor != 0.0 {
+                    for j in 0..n {
+                        a[row][j] -= factor * a[col][j];
+                        inverse[row][j] -= factor * inverse[col][j];
+                    }
+                }
+            }
+        }
+    }
+
+    Some(inverse)
+}
@@ -0,0 +1,101 @@
+use hifitime::Epoch;
+use serde_json::{json, Value};
+
+use crate::{algo::track::continuous_arcs, data::oem::{EphemerisSegment, INTERPOLATION_DEGREE}, gpst::iso_utc, product::satellite::Constellation};
+
+// same palette as the map legend in ui/satellite-map.html
+pub fn rgba(constellation:Constellation) -> [u8; 4] {
+    match constellation {
+        Constellation::Gps => [0x1f, 0x77, 0xb4, 0xff],
+        Constellation::Glonass => [0xff, 0x7f, 0x0e, 0xff],
+        Constellation::Galileo => [0x2c, 0xa0, 0x2c, 0xff],
+        Constellation::BeiDou => [0xd6, 0x27, 0x28, 0xff],
+        Constellation::Qzss => [0x94, 0x67, 0xbd, 0xff],
+        Constellation::NavIC => [0x8c, 0x56, 0x4b, 0xff],
+        Constellation::Sbas => [0xe3, 0x77, 0xc2, 0xff],
+        Constellation::Leo => [0x99, 0x99, 0x99, 0xff],
+    }
+}
+
+fn utc(epoch:f64) -> String {
+    format!("{}Z", iso_utc(&Epoch::from_gpst_seconds(epoch)))
+}
+
+fn interval(start:f64, stop:f64) -> String {
+    format!("{}/{}", utc(start), utc(stop))
+}
+
+// GPST intervals with continuous samples, split where the orbit has gaps
+pub fn availability(segment:&EphemerisSegment) -> Vec<(f64, f64)> {
+    continuous_arcs(&segment.states).into_iter()
+        .map(|arc| (arc[0].epoch, arc[arc.len() - 1].epoch))
+        .collect()
+}
+
+// CZML document with one packet per satellite, positions in meters in the earth fixed frame
+pub fn czml(segments:&[EphemerisSegment]) -> Value {
+
+    let segments:Vec<&EphemerisSegment> = segments.iter().filter(|segment| !segment.states.is_empty()).collect();
+
+    let start = segments.iter().filter_map(|segment| segment.start()).min_by(|a, b| a.total_cmp(b));
+    let stop = segments.iter().filter_map(|segment| segment.stop()).max_by(|a, b| a.total_cmp(b));
+
+    let mut document = json!({ "id": "document", "name": "ground-control", "version": "1.0" });
+    if let (Some(start), Some(stop)) = (start, stop) {
+        document["clock"] = json!({
+            "interval": interval(start, stop),
+            "currentTime": utc(start),
+            "multiplier": 60,
+            "range": "LOOP_STOP",
+            "step": "SYSTEM_CLOCK_MULTIPLIER",
+        });
+    }
+
+    let mut packets = vec![document];
+
+    for segment in segments {
+
+        let epoch = segment.states[0].epoch;
+        let color = json!({ "rgba": rgba(segment.satellite.constellation) });
+
+        let cartesian:Vec<f64> = segment.states.iter()
+            .flat_map(|state| {
+                let pos = state.pos_ecef_km * 1_000.0;
+                [state.epoch - epoch, pos.x, pos.y, pos.z]
+            })
+            .collect();
+
+        let availability:Vec<String> = availability(segment).into_iter()
+            .map(|(start, stop)| interval(start, stop))
+            .collect();
+
+        packets.push(json!({
+            "id": segment.satellite.to_string(),
+            "name": segment.satellite.to_string(),
+            "availability": availability,
+            "label": {
+                "text": segment.satellite.to_string(),
+                "font": "11pt sans-serif",
+                "horizontalOrigin": "LEFT",
+                "pixelOffset": { "cartesian2": [8, 0] },
+                "fillColor": color,
+            },
+            "point": { "pixelSize": 6, "color": color },
+            "path": {
+                "width": 1,
+                "leadTime": 0,
+                "trailTime": 3_600,
+                "material": { "solidColor": { "color": color } },
+            },
+            "position": {
+                "epoch": utc(epoch),
+                "referenceFrame": "FIXED",
+                "interpolationAlgorithm": "LAGRANGE",
+                "interpolationDegree": INTERPOLATION_DEGREE,
+                "cartesian": cartesian,
+            },
+        }));
+    }
+
+    Value::Array(packets)
+}
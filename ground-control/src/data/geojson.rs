@@ -0,0 +1,66 @@
+use hifitime::Epoch;
+use serde_json::{json, Value};
+
+use crate::{algo::track::{ground_track, SatellitePosition}, data::{czml::rgba, oem::EphemerisSegment}, gpst::iso_utc, product::satellite::Constellation};
+
+fn utc(epoch:f64) -> String {
+    format!("{}Z", iso_utc(&Epoch::from_gpst_seconds(epoch)))
+}
+
+// simplestyle color for marker-color and stroke
+fn hex_color(constellation:Constellation) -> String {
+    let [r, g, b, _] = rgba(constellation);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// FeatureCollection with a sub-satellite Point per satellite
+pub fn positions_geojson(positions:&[SatellitePosition]) -> Value {
+
+    let features:Vec<Value> = positions.iter()
+        .map(|position| json!({
+            "type": "Feature",
+            "id": position.satellite.to_string(),
+            "geometry": { "type": "Point", "coordinates": [position.point.lon_deg, position.point.lat_deg] },
+            "properties": {
+                "satellite": position.satellite.to_string(),
+                "constellation": position.satellite.constellation,
+                "epoch": position.point.epoch,
+                "utc": utc(position.point.epoch),
+                "alt_km": position.point.alt_km,
+                "source": position.source,
+                "extrapolated": position.extrapolated,
+                "marker-color": hex_color(position.satellite.constellation),
+            },
+        }))
+        .collect();
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+// FeatureCollection with a ground track per satellite, split at gaps and at the antimeridian
+pub fn ground_tracks_geojson(segments:&[EphemerisSegment]) -> Value {
+
+    let features:Vec<Value> = segments.iter()
+        .filter_map(|segment| {
+            let (start, stop) = (segment.start()?, segment.stop()?);
+            let lines:Vec<Vec<[f64; 2]>> = ground_track(&segment.states).iter()
+                .map(|part| part.iter().map(|point| [point.lon_deg, point.lat_deg]).collect())
+                .collect();
+
+            Some(json!({
+                "type": "Feature",
+                "id": segment.satellite.to_string(),
+                "geometry": { "type": "MultiLineString", "coordinates": lines },
+                "properties": {
+                    "satellite": segment.satellite.to_string(),
+                    "constellation": segment.satellite.constellation,
+                    "start": utc(start),
+                    "stop": utc(stop),
+                    "stroke": hex_color(segment.satellite.constellation),
+                },
+            }))
+        })
+        .collect();
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
@@ -0,0 +1,67 @@
+use std::io::BufRead;
+
+use anyhow::anyhow;
+
+use crate::algo::gravity::GravityModel;
+
+// ICGEM gravity field (.gfc) truncated to `max_degree`. The header must provide
+// earth_gravity_constant (m^3/s^2) and radius (m), coefficients follow end_of_head as
+// `gfc n m C S [sigma_C sigma_S]` records.
+pub fn parse_icgem<R:BufRead>(reader:R, max_degree:usize) -> Result<GravityModel, anyhow::Error> {
+
+    let mut gm_m3_s2:Option<f64> = None;
+    let mut radius_m:Option<f64> = None;
+    let mut model_degree:Option<usize> = None;
+    let mut model:Option<GravityModel> = None;
+
+    for line in reader.lines() {
+
+        let line = line?;
+        let fields:Vec<&str> = line.split_whitespace().collect();
+
+        let Some(keyword) = fields.first() else {
+            continue;
+        };
+
+        match (*keyword, &mut model) {
+            ("earth_gravity_constant", None) => gm_m3_s2 = Some(parse_value(&fields)?),
+            ("radius", None) => radius_m = Some(parse_value(&fields)?),
+            ("max_degree", None) => model_degree = Some(parse_value(&fields)? as usize),
+            ("end_of_head", None) => {
+                let gm = gm_m3_s2.ok_or_else(|| anyhow!("ICGEM header missing earth_gravity_constant"))?;
+                let radius = radius_m.ok_or_else(|| anyhow!("ICGEM header missing radius"))?;
+                let degree = model_degree.unwrap_or(max_degree).min(max_degree);
+                model = Some(GravityModel::new(gm * 1e-9, radius * 1e-3, degree));
+            }
+            ("gfc", Some(model)) => {
+                if fields.len() < 5 {
+                    return Err(anyhow!("Incomplete ICGEM record {}", line));
+                }
+
+                let n = fields[1].parse::<usize>()?;
+                let m = fields[2].parse::<usize>()?;
+                if n > model.degree || m > n {
+                    continue;
+                }
+
+                model.c[n][m] = parse_float(fields[3])?;
+                model.s[n][m] = parse_float(fields[4])?;
+            }
+            _ => {}
+        }
+    }
+
+    model.ok_or_else(|| anyhow!("ICGEM file has no end_of_head"))
+}
+
+fn parse_value(fields:&[&str]) -> Result<f64, anyhow::Error> {
+    match fields.get(1) {
+        Some(value) => parse_float(value),
+        None => Err(anyhow!("ICGEM header {} has no value", fields[0])),
+    }
+}
+
+// ICGEM files may use Fortran style exponents (1.0D-06)
+fn parse_float(value:&str) -> Result<f64, anyhow::Error> {
+    value.replace(['D', 'd'], "E").parse::<f64>().map_err(|err| anyhow!("Invalid ICGEM value {}: {}", value, err))
+}
@@ -0,0 +1,104 @@
+use std::fmt::Write;
+
+use hifitime::Epoch;
+
+use crate::{algo::track::{ground_track, SatellitePosition}, data::{czml::rgba, oem::EphemerisSegment}, gpst::iso_utc, product::satellite::{Constellation, SatelliteId}};
+
+fn utc(epoch:f64) -> String {
+    format!("{}Z", iso_utc(&Epoch::from_gpst_seconds(epoch)))
+}
+
+// one style per constellation in the document, KML colors are aabbggrr
+fn styles(kml:&mut String, satellites:impl Iterator<Item = SatelliteId>) {
+
+    let mut constellations:Vec<Constellation> = Vec::new();
+    for satellite in satellites {
+        if !constellations.contains(&satellite.constellation) {
+            constellations.push(satellite.constellation);
+        }
+    }
+
+    for constellation in constellations {
+        let [r, g, b, a] = rgba(constellation);
+        let color = format!("{:02x}{:02x}{:02x}{:02x}", a, b, g, r);
+        writeln!(kml, "    <Style id=\"{}\">", constellation.letter()).unwrap();
+        writeln!(kml, "      <IconStyle><color>{}</color></IconStyle>", color).unwrap();
+        writeln!(kml, "      <LineStyle><color>{}</color><width>1.5</width></LineStyle>", color).unwrap();
+        writeln!(kml, "    </Style>").unwrap();
+    }
+}
+
+fn document_start(kml:&mut String) {
+    writeln!(kml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(kml, "<kml xmlns=\"http://www.opengis.net/kml/2.2\">").unwrap();
+    writeln!(kml, "  <Document>").unwrap();
+    writeln!(kml, "    <name>ground-control</name>").unwrap();
+}
+
+fn document_end(kml:&mut String) {
+    writeln!(kml, "  </Document>").unwrap();
+    writeln!(kml, "</kml>").unwrap();
+}
+
+// Placemark with a sub-satellite Point per satellite
+pub fn positions_kml(positions:&[SatellitePosition]) -> String {
+
+    let mut kml = String::new();
+
+    document_start(&mut kml);
+    styles(&mut kml, positions.iter().map(|position| position.satellite));
+
+    for position in positions {
+        writeln!(kml, "    <Placemark>").unwrap();
+        writeln!(kml, "      <name>{}</name>", position.satellite).unwrap();
+        writeln!(kml, "      <styleUrl>#{}</styleUrl>", position.satellite.constellation.letter()).unwrap();
+        writeln!(kml, "      <TimeStamp><when>{}</when></TimeStamp>", utc(position.point.epoch)).unwrap();
+        writeln!(kml, "      <ExtendedData>").unwrap();
+        writeln!(kml, "        <Data name=\"alt_km\"><value>{:.3}</value></Data>", position.point.alt_km).unwrap();
+        writeln!(kml, "        <Data name=\"source\"><value>{}</value></Data>", position.source).unwrap();
+        writeln!(kml, "        <Data name=\"extrapolated\"><value>{}</value></Data>", position.extrapolated).unwrap();
+        writeln!(kml, "      </ExtendedData>").unwrap();
+        writeln!(kml, "      <Point><coordinates>{:.6},{:.6},0</coordinates></Point>", position.point.lon_deg, position.point.lat_deg).unwrap();
+        writeln!(kml, "    </Placemark>").unwrap();
+    }
+
+    document_end(&mut kml);
+
+    kml
+}
+
+// Placemark with the ground track LineStrings per satellite, split at gaps and at the antimeridian
+pub fn ground_tracks_kml(segments:&[EphemerisSegment]) -> String {
+
+    let mut kml = String::new();
+
+    document_start(&mut kml);
+    styles(&mut kml, segments.iter().map(|segment| segment.satellite));
+
+    for segment in segments {
+
+        let (Some(start), Some(stop)) = (segment.start(), segment.stop()) else {
+            continue;
+        };
+
+        writeln!(kml, "    <Placemark>").unwrap();
+        writeln!(kml, "      <name>{}</name>", segment.satellite).unwrap();
+        writeln!(kml, "      <styleUrl>#{}</styleUrl>", segment.satellite.constellation.letter()).unwrap();
+        writeln!(kml, "      <TimeSpan><begin>{}</begin><end>{}</end></TimeSpan>", utc(start), utc(stop)).unwrap();
+        writeln!(kml, "      <MultiGeometry>").unwrap();
+
+        for part in ground_track(&segment.states) {
+            let coordinates:Vec<String> = part.iter()
+                .map(|point| format!("{:.6},{:.6},0", point.lon_deg, point.lat_deg))
+                .collect();
+            writeln!(kml, "        <LineString><tessellate>1</tessellate><coordinates>{}</coordinates></LineString>", coordinates.join(" ")).unwrap();
+        }
+
+        writeln!(kml, "      </MultiGeometry>").unwrap();
+        writeln!(kml, "    </Placemark>").unwrap();
+    }
+
+    document_end(&mut kml);
+
+    kml
+}
@@ -1 +1,9 @@
 pub mod sp3;
+pub mod sinex;
+pub mod icgem;
+pub mod rtcm;
+pub mod oem;
+pub mod czml;
+pub mod geojson;
+pub mod kml;
+pub mod rinex;
@@ -0,0 +1,133 @@
+use std::fmt::Write;
+
+use hifitime::Epoch;
+
+use crate::{algo::propagate::State, gpst::iso_utc, product::{satellite::SatelliteId, sv::Orbit}};
+
+const CCSDS_OEM_VERS:&str = "2.0";
+const ORIGINATOR:&str = "GROUND-CONTROL";
+// Lagrange degree declared for the exported states, in OEM and CZML alike
+pub const INTERPOLATION_DEGREE:usize = 9;
+
+// Orbit states of one satellite over a time range, one OEM segment or CZML packet each
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct EphemerisSegment {
+    pub satellite:SatelliteId,
+    pub states:Vec<State>,
+}
+
+impl EphemerisSegment {
+
+    // orbit samples in [from, to], or resampled every `step_s` seconds
+    pub fn from_orbit(orbit:&Orbit, from:f64, to:f64, step_s:Option<f64>) -> Result<Self, anyhow::Error> {
+        Ok(EphemerisSegment { satellite: orbit.sv.satellite, states: orbit.states(from, to, step_s)? })
+    }
+
+    pub fn start(&self) -> Option<f64> {
+        self.states.first().map(|state| state.epoch)
+    }
+
+    pub fn stop(&self) -> Option<f64> {
+        self.states.last().map(|state| state.epoch)
+    }
+}
+
+fn utc(epoch:f64) -> String {
+    iso_utc(&Epoch::from_gpst_seconds(epoch))
+}
+
+// metadata keywords of a segment, states are earth fixed ITRF with UTC epochs. None for a segment
+// without states, which has no start and stop time and is left out of the message.
+fn metadata(segment:&EphemerisSegment) -> Option<Vec<(&'static str, String)>> {
+    Some(vec![
+        ("OBJECT_NAME", segment.satellite.to_string()),
+        ("OBJECT_ID", segment.satellite.to_string()),
+        ("CENTER_NAME", "EARTH".to_string()),
+        ("REF_FRAME", "ITRF".to_string()),
+        ("TIME_SYSTEM", "UTC".to_string()),
+        ("START_TIME", utc(segment.start()?)),
+        ("STOP_TIME", utc(segment.stop()?)),
+        ("INTERPOLATION", "LAGRANGE".to_string()),
+        ("INTERPOLATION_DEGREE", INTERPOLATION_DEGREE.to_string()),
+    ])
+}
+
+// CCSDS Orbit Ephemeris Message in keyword = value notation, positions in km and velocities in km/s
+pub fn oem_kvn(segments:&[EphemerisSegment], creation:&Epoch) -> String {
+
+    let mut kvn = String::new();
+
+    writeln!(kvn, "CCSDS_OEM_VERS = {}", CCSDS_OEM_VERS).unwrap();
+    writeln!(kvn, "CREATION_DATE = {}", iso_utc(creation)).unwrap();
+    writeln!(kvn, "ORIGINATOR = {}", ORIGINATOR).unwrap();
+
+    for segment in segments {
+
+        let Some(metadata) = metadata(segment) else {
+            continue;
+        };
+
+        writeln!(kvn, "\nMETA_START").unwrap();
+        for (keyword, value) in metadata {
+            writeln!(kvn, "{} = {}", keyword, value).unwrap();
+        }
+        writeln!(kvn, "META_STOP\n").unwrap();
+
+        for state in &segment.states {
+            let (pos, vel) = (state.pos_ecef_km, state.vel_ecef_km_s);
+            writeln!(kvn, "{} {:.6} {:.6} {:.6} {:.9} {:.9} {:.9}", utc(state.epoch), pos.x, pos.y, pos.z, vel.x, vel.y, vel.z).unwrap();
+        }
+    }
+
+    kvn
+}
+
+// the same message in the CCSDS NDM/XML schema
+pub fn oem_xml(segments:&[EphemerisSegment], creation:&Epoch) -> String {
+
+    let mut xml = String::new();
+
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+    writeln!(xml, "<oem id=\"CCSDS_OEM_VERS\" version=\"{}\">", CCSDS_OEM_VERS).unwrap();
+    writeln!(xml, "  <header>").unwrap();
+    writeln!(xml, "    <CREATION_DATE>{}</CREATION_DATE>", iso_utc(creation)).unwrap();
+    writeln!(xml, "    <ORIGINATOR>{}</ORIGINATOR>", ORIGINATOR).unwrap();
+    writeln!(xml, "  </header>").unwrap();
+    writeln!(xml, "  <body>").unwrap();
+
+    for segment in segments {
+
+        let Some(metadata) = metadata(segment) else {
+            continue;
+        };
+
+        writeln!(xml, "    <segment>").unwrap();
+        writeln!(xml, "      <metadata>").unwrap();
+        for (keyword, value) in metadata {
+            writeln!(xml, "        <{0}>{1}</{0}>", keyword, value).unwrap();
+        }
+        writeln!(xml, "      </metadata>").unwrap();
+        writeln!(xml, "      <data>").unwrap();
+
+        for state in &segment.states {
+            let (pos, vel) = (state.pos_ecef_km, state.vel_ecef_km_s);
+            writeln!(xml, "        <stateVector>").unwrap();
+            writeln!(xml, "          <EPOCH>{}</EPOCH>", utc(state.epoch)).unwrap();
+            for (keyword, value) in [("X", pos.x), ("Y", pos.y), ("Z", pos.z)] {
+                writeln!(xml, "          <{0}>{1:.6}</{0}>", keyword, value).unwrap();
+            }
+            for (keyword, value) in [("X_DOT", vel.x), ("Y_DOT", vel.y), ("Z_DOT", vel.z)] {
+                writeln!(xml, "          <{0}>{1:.9}</{0}>", keyword, value).unwrap();
+            }
+            writeln!(xml, "        </stateVector>").unwrap();
+        }
+
+        writeln!(xml, "      </data>").unwrap();
+        writeln!(xml, "    </segment>").unwrap();
+    }
+
+    writeln!(xml, "  </body>").unwrap();
+    writeln!(xml, "</oem>").unwrap();
+
+    xml
+}
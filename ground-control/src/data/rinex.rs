@@ -0,0 +1,212 @@
+use std::{collections::HashMap, io::{BufRead, BufReader}};
+
+use anyhow::anyhow;
+use bytes::Buf;
+use flate2::bufread::GzDecoder;
+use object_store::{path::Path, ObjectStore};
+use regex::{Captures, Regex};
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::{terminal_error, ApiError}, algo::ephemeris::BroadcastEphemeris, data::sp3::r2_cddis_bucket, gpst::{from_calendar, from_week_tow, TimeSystem}, product::{ephemeris::BroadcastEphemeridesClient, satellite::{Constellation, SatelliteId}, sv::{DataSource, SVSource}}};
+
+// GPS fit interval when the record has none, also the nominal validity of Galileo ephemerides
+const DEFAULT_FIT_INTERVAL_S:f64 = 14_400.0;
+// QZSS fit interval flag 0
+const QZSS_SHORT_FIT_INTERVAL_S:f64 = 7_200.0;
+
+// RINEX 3 long file name of a navigation file, e.g. BRDC00IGS_R_20240920000_01D_MN.rnx.gz
+pub fn rinex_nav_filename_parser(filename:&str) -> Option<Captures<'_>> {
+    let re = Regex::new(r"^(?<SITE>.{4})[0-9]{2}(?<CCC>.{3})_(?<SRC>[RSU])_(?<TIME>[0-9]{11})_(?<PER>[^_]*)_(?<TYP>[A-Z]N)\.rnx(\.gz)?$").unwrap();
+    re.captures(filename)
+}
+
+// orbit field of a record line, 19 characters with a D or E exponent, blank fields are zero
+fn field(line:&str, start:usize, index:usize) -> Result<f64, anyhow::Error> {
+
+    let from = (start + index * 19).min(line.len());
+    let to = (from + 19).min(line.len());
+    let value = line[from..to].trim();
+
+    if value.is_empty() {
+        return Ok(0.0);
+    }
+
+    value.replace(['D', 'd'], "E").parse::<f64>()
+        .map_err(|_| anyhow!("Invalid RINEX navigation field {:?}", value))
+}
+
+// GPS, QZSS or Galileo record of eight lines, the satellite and clock line followed by seven orbit lines
+fn parse_ephemeris(lines:&[String]) -> Result<BroadcastEphemeris, anyhow::Error> {
+
+    let satellite = lines[0].get(..3).unwrap_or_default().replace(' ', "0").parse::<SatelliteId>()?;
+    let system = TimeSystem::for_constellation(satellite.constellation);
+
+    // the first line starts its fields after the epoch, orbit lines after four spaces
+    let orbit = |row:usize, index:usize| field(&lines[row], if row == 0 { 23 } else { 4 }, index);
+
+    let date:Vec<&str> = lines[0].get(3..23).unwrap_or_default().split_whitespace().collect();
+    if date.len() != 6 {
+        return Err(anyhow!("Invalid RINEX navigation epoch {:?}", lines[0]));
+    }
+
+    let toc = from_calendar(system, date[0].parse()?, date[1].parse()?, date[2].parse()?, date[3].parse()?, date[4].parse()?, date[5].parse()?)?;
+
+    // RINEX 3 aligns the Galileo week with the continuous GPS week
+    let toe = from_week_tow(TimeSystem::Gpst, orbit(5, 2)? as u32, orbit(3, 0)?)?;
+
+    let fit_interval_s = match satellite.constellation {
+        Constellation::Gps if orbit(7, 1)? > 0.0 => orbit(7, 1)? * 3_600.0,
+        Constellation::Qzss if orbit(7, 1)? == 0.0 => QZSS_SHORT_FIT_INTERVAL_S,
+        _ => DEFAULT_FIT_INTERVAL_S,
+    };
+
+    Ok(BroadcastEphemeris {
+        satellite,
+        // IODE, IODnav for Galileo
+        iode: orbit(1, 0)? as u16,
+        toe: toe.to_gpst_seconds(),
+        toc: toc.to_gpst_seconds(),
+        sqrt_a: orbit(2, 3)?,
+        e: orbit(2, 1)?,
+        i0: orbit(4, 0)?,
+        omega0: orbit(3, 2)?,
+        omega: orbit(4, 2)?,
+        m0: orbit(1, 3)?,
+        delta_n: orbit(1, 2)?,
+        idot: orbit(5, 0)?,
+        omega_dot: orbit(4, 3)?,
+        cuc: orbit(2, 0)?,
+        cus: orbit(2, 2)?,
+        crc: orbit(4, 1)?,
+        crs: orbit(1, 1)?,
+        cic: orbit(3, 1)?,
+        cis: orbit(3, 3)?,
+        af0: orbit(0, 0)?,
+        af1: orbit(0, 1)?,
+        af2: orbit(0, 2)?,
+        fit_interval_s,
+    })
+}
+
+// Parses the GPS, QZSS and Galileo ephemerides of a RINEX 3 navigation file. BeiDou (BDT weeks and
+// GEO orbits), GLONASS and SBAS (state vectors) and NavIC records are skipped.
+pub fn parse_rinex_nav<R:BufRead>(reader:R) -> Result<Vec<BroadcastEphemeris>, anyhow::Error> {
+
+    let mut lines = reader.lines();
+    let mut version:Option<f64> = None;
+
+    for line in lines.by_ref() {
+        let line = line?;
+        match line.get(60..).map(str::trim) {
+            Some("RINEX VERSION / TYPE") => {
+                version = line.get(..9).and_then(|version| version.trim().parse().ok());
+                if line.get(20..21) != Some("N") {
+                    return Err(anyhow!("Not a RINEX navigation file"));
+                }
+            }
+            Some("END OF HEADER") => break,
+            _ => {}
+        }
+    }
+
+    if !version.is_some_and(|version| (3.0..4.0).contains(&version)) {
+        return Err(anyhow!("Unsupported RINEX navigation version {:?}", version));
+    }
+
+    let mut ephemerides = Vec::new();
+    let mut record:Vec<String> = Vec::new();
+
+    let mut push_record = |record:&[String]| -> Result<(), anyhow::Error> {
+        match record.first().and_then(|line| line.chars().next()) {
+            Some('G' | 'J' | 'E') if record.len() >= 8 => ephemerides.push(parse_ephemeris(record)?),
+            Some('G' | 'J' | 'E') => return Err(anyhow!("Incomplete RINEX navigation record {:?}", record[0])),
+            _ => {}
+        }
+        Ok(())
+    };
+
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // records start with the satellite, continuation lines with spaces
+        if !line.starts_with(' ') {
+            push_record(&record)?;
+            record.clear();
+        }
+        record.push(line);
+    }
+
+    push_record(&record)?;
+
+    Ok(ephemerides)
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct NavFile {
+    pub source:String,
+    pub archive_path:String,
+}
+
+impl NavFile {
+
+    // broadcast data source named after the file, e.g. cddis_igs_brdc for BRDC00IGS_R_...
+    pub fn data_source(&self) -> Result<DataSource, anyhow::Error> {
+
+        let filename = self.archive_path.rsplit('/').next().unwrap_or_default();
+        let parts = rinex_nav_filename_parser(filename)
+            .ok_or_else(|| anyhow!("Not a RINEX navigation file: {}", self.archive_path))?;
+
+        Ok(DataSource {
+            source: self.source.to_lowercase(),
+            analysis_center: parts["CCC"].to_lowercase(),
+            product_type: parts["SITE"].to_lowercase(),
+        })
+    }
+}
+
+#[restate_sdk::object]
+pub trait NavData {
+    #[name = "processNav"]
+    async fn process_nav_file(nav_file:Json<NavFile>) -> Result<(), HandlerError>;
+}
+
+pub struct NavDataImpl;
+
+impl NavData for NavDataImpl {
+
+    async fn process_nav_file(&self, ctx:ObjectContext<'_>, nav_file:Json<NavFile>) -> Result<(), HandlerError> {
+
+        let nav_file = nav_file.into_inner();
+
+        let data_source = nav_file.data_source()
+            .map_err(|err| terminal_error(err, ApiError::InvalidRequest))?;
+
+        let r2_bucket = r2_cddis_bucket()?;
+        let response = r2_bucket.get(&Path::from_absolute_path(&nav_file.archive_path)?).await?;
+        let nav_bytes = response.bytes().await?;
+
+        let ephemerides = if nav_file.archive_path.ends_with(".gz") {
+            parse_rinex_nav(BufReader::new(GzDecoder::new(nav_bytes.reader())))
+        } else {
+            parse_rinex_nav(nav_bytes.reader())
+        }.map_err(|err| TerminalError::from(ApiError::Unprocessable(format!("Unable to parse {}: {}", nav_file.archive_path, err))))?;
+
+        let mut by_satellite:HashMap<SatelliteId, Vec<BroadcastEphemeris>> = HashMap::new();
+        for ephemeris in ephemerides {
+            by_satellite.entry(ephemeris.satellite).or_default().push(ephemeris);
+        }
+
+        info!("loaded broadcast ephemerides of {} satellites from {}", by_satellite.len(), nav_file.archive_path);
+
+        for (satellite, ephemerides) in by_satellite {
+            let sv_source = SVSource { satellite, data_source: data_source.clone() };
+            ctx.object_client::<BroadcastEphemeridesClient>(sv_source.get_key()).load_broadcast(Json(ephemerides)).send();
+        }
+
+        Ok(())
+    }
+}
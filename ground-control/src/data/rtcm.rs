@@ -0,0 +1,234 @@
+use anyhow::anyhow;
+
+use hifitime::Epoch;
+
+use crate::{algo::ssr::{ClockCorrection, OrbitCorrection}, gpst::{week_tow, TimeSystem}, product::satellite::{Constellation, SatelliteId}};
+
+const PREAMBLE:u8 = 0xD3;
+const MAX_PAYLOAD_BYTES:usize = 1023;
+// DF387 number of satellites is a 6 bit field
+const MAX_MESSAGE_SATELLITES:usize = 63;
+
+// DF391 SSR update interval indicator, in seconds
+const UPDATE_INTERVALS_S:[f64; 16] = [1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 240.0, 300.0, 600.0, 900.0, 1800.0, 3600.0, 7200.0, 10800.0];
+
+// Identifies the correction stream in the SSR message headers
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Default)]
+#[serde(default)]
+pub struct SsrStream {
+    pub provider_id:u16,
+    pub solution_id:u8,
+    // issue of the SSR data, changed when the stream configuration changes
+    pub iod_ssr:u8,
+}
+
+// message numbers and satellite/IOD field widths of the SSR messages per constellation
+struct SsrLayout {
+    orbit_message:u16,
+    clock_message:u16,
+    satellite_bits:u32,
+    iod_bits:u32,
+}
+
+fn ssr_layout(constellation:Constellation) -> Option<SsrLayout> {
+    match constellation {
+        Constellation::Gps => Some(SsrLayout { orbit_message: 1057, clock_message: 1058, satellite_bits: 6, iod_bits: 8 }),
+        Constellation::Galileo => Some(SsrLayout { orbit_message: 1240, clock_message: 1241, satellite_bits: 6, iod_bits: 10 }),
+        Constellation::Qzss => Some(SsrLayout { orbit_message: 1246, clock_message: 1247, satellite_bits: 4, iod_bits: 8 }),
+        Constellation::BeiDou => Some(SsrLayout { orbit_message: 1258, clock_message: 1259, satellite_bits: 6, iod_bits: 8 }),
+        _ => None,
+    }
+}
+
+// CRC-24Q used by the RTCM 3 transport layer
+pub fn crc24q(data:&[u8]) -> u32 {
+
+    let mut crc:u32 = 0;
+
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= 0x186_4CFB;
+            }
+        }
+    }
+
+    crc & 0xFF_FFFF
+}
+
+// RTCM 3 frame: preamble, 6 reserved bits, 10 bit length, payload and CRC-24Q
+pub fn frame(payload:&[u8]) -> Result<Vec<u8>, anyhow::Error> {
+
+    if payload.len() > MAX_PAYLOAD_BYTES {
+        return Err(anyhow!("RTCM payload of {} bytes exceeds {}", payload.len(), MAX_PAYLOAD_BYTES));
+    }
+
+    let mut frame = vec![PREAMBLE, (payload.len() >> 8) as u8, payload.len() as u8];
+    frame.extend_from_slice(payload);
+
+    let crc = crc24q(&frame);
+    frame.extend_from_slice(&[(crc >> 16) as u8, (crc >> 8) as u8, crc as u8]);
+
+    Ok(frame)
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes:Vec<u8>,
+    bits:usize,
+}
+
+impl BitWriter {
+
+    fn unsigned(&mut self, value:u64, width:u32) {
+        for i in (0..width).rev() {
+            if self.bits.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                let last = self.bytes.len() - 1;
+                self.bytes[last] |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    fn checked(&mut self, value:u64, width:u32) -> Result<(), anyhow::Error> {
+        if value >> width != 0 {
+            return Err(anyhow!("{} does not fit a {} bit field", value, width));
+        }
+        self.unsigned(value, width);
+        Ok(())
+    }
+
+    // two's complement, values outside the field range are rejected
+    fn signed(&mut self, value:i64, width:u32) -> Result<(), anyhow::Error> {
+        let limit = 1i64 << (width - 1);
+        if value < -limit || value >= limit {
+            return Err(anyhow!("{} does not fit a {} bit field", value, width));
+        }
+        self.unsigned((value as u64) & ((1u64 << width) - 1), width);
+        Ok(())
+    }
+
+    // value in units of `resolution`, rounded
+    fn scaled(&mut self, value:f64, resolution:f64, width:u32) -> Result<(), anyhow::Error> {
+        self.signed((value / resolution).round() as i64, width)
+    }
+}
+
+fn update_interval_indicator(interval_s:f64) -> u64 {
+    UPDATE_INTERVALS_S.iter()
+        .position(|interval| *interval >= interval_s)
+        .unwrap_or(UPDATE_INTERVALS_S.len() - 1) as u64
+}
+
+// SSR epoch time, seconds of the GNSS week in the constellation's time scale
+fn epoch_time(constellation:Constellation, epoch:f64) -> u64 {
+    let system = TimeSystem::for_constellation(constellation);
+    week_tow(&Epoch::from_gpst_seconds(epoch), system).map(|(_, tow_s)| tow_s.floor() as u64).unwrap_or(0)
+}
+
+// corrections of one message: constellation, epoch and satellites
+type MessageGroup<'a, T> = (Constellation, f64, Vec<&'a T>);
+
+// Groups corrections into messages of one constellation and epoch, each at most `per_message`
+// satellites so the frame stays below the payload limit and the count fits DF387
+fn message_groups<T>(corrections:&[T], key:impl Fn(&T) -> (SatelliteId, f64), per_message:usize) -> Result<Vec<MessageGroup<'_, T>>, anyhow::Error> {
+
+    let per_message = per_message.min(MAX_MESSAGE_SATELLITES);
+    let mut groups:Vec<MessageGroup<T>> = Vec::new();
+
+    for correction in corrections {
+        let (satellite, epoch) = key(correction);
+        if ssr_layout(satellite.constellation).is_none() {
+            return Err(anyhow!("No RTCM SSR messages for {}", satellite));
+        }
+
+        match groups.iter_mut().find(|(constellation, group_epoch, members)| *constellation == satellite.constellation && *group_epoch == epoch && members.len() < per_message) {
+            Some((_, _, members)) => members.push(correction),
+            None => groups.push((satellite.constellation, epoch, vec![correction])),
+        }
+    }
+
+    groups.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    Ok(groups)
+}
+
+fn header<T>(writer:&mut BitWriter, message:u16, (constellation, epoch, members):&MessageGroup<T>, interval_s:f64, multiple:bool, stream:&SsrStream, reference_datum:bool) {
+
+    assert!(members.len() <= MAX_MESSAGE_SATELLITES, "{} satellites do not fit DF387", members.len());
+
+    writer.unsigned(message as u64, 12);
+    writer.unsigned(epoch_time(*constellation, *epoch), 20);
+    writer.unsigned(update_interval_indicator(interval_s), 4);
+    writer.unsigned(multiple as u64, 1);
+    if reference_datum {
+        // satellite reference datum, 0 = ITRF
+        writer.unsigned(0, 1);
+    }
+    writer.unsigned(stream.iod_ssr as u64, 4);
+    writer.unsigned(stream.provider_id as u64, 16);
+    writer.unsigned(stream.solution_id as u64, 4);
+    writer.unsigned(members.len() as u64, 6);
+}
+
+// multiple message indicator, set while more messages for the same epoch follow
+fn multiple_flags<T>(groups:&[MessageGroup<T>]) -> Vec<bool> {
+    (0..groups.len())
+        .map(|i| groups.get(i + 1).is_some_and(|next| next.1 == groups[i].1))
+        .collect()
+}
+
+// Framed SSR orbit correction messages (1057, 1240, 1246, 1258), one per constellation and epoch
+pub fn encode_orbit_corrections(corrections:&[OrbitCorrection], interval_s:f64, stream:&SsrStream) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+
+    let groups = message_groups(corrections, |c| (c.satellite, c.epoch), 55)?;
+    let multiple = multiple_flags(&groups);
+
+    groups.iter().zip(multiple).map(|(group, multiple)| {
+
+        let layout = ssr_layout(group.0).unwrap();
+        let mut writer = BitWriter::default();
+        header(&mut writer, layout.orbit_message, group, interval_s, multiple, stream, true);
+
+        for correction in &group.2 {
+            writer.checked(correction.satellite.prn as u64, layout.satellite_bits)?;
+            writer.checked(correction.iode as u64, layout.iod_bits)?;
+            writer.scaled(correction.radial_m, 1e-4, 22)?;
+            writer.scaled(correction.along_m, 4e-4, 20)?;
+            writer.scaled(correction.cross_m, 4e-4, 20)?;
+            writer.scaled(correction.radial_rate_m_s, 1e-6, 21)?;
+            writer.scaled(correction.along_rate_m_s, 4e-6, 19)?;
+            writer.scaled(correction.cross_rate_m_s, 4e-6, 19)?;
+        }
+
+        frame(&writer.bytes)
+    }).collect()
+}
+
+// Framed SSR clock correction messages (1058, 1241, 1247, 1259), one per constellation and epoch
+pub fn encode_clock_corrections(corrections:&[ClockCorrection], interval_s:f64, stream:&SsrStream) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+
+    let groups = message_groups(corrections, |c| (c.satellite, c.epoch), MAX_MESSAGE_SATELLITES)?;
+    let multiple = multiple_flags(&groups);
+
+    groups.iter().zip(multiple).map(|(group, multiple)| {
+
+        let layout = ssr_layout(group.0).unwrap();
+        let mut writer = BitWriter::default();
+        header(&mut writer, layout.clock_message, group, interval_s, multiple, stream, false);
+
+        for correction in &group.2 {
+            writer.checked(correction.satellite.prn as u64, layout.satellite_bits)?;
+            writer.scaled(correction.c0_m, 1e-4, 22)?;
+            writer.scaled(correction.c1_m_s, 1e-6, 21)?;
+            writer.scaled(correction.c2_m_s2, 2e-8, 27)?;
+        }
+
+        frame(&writer.bytes)
+    }).collect()
+}
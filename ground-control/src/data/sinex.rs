@@ -0,0 +1,134 @@
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::anyhow;
+use hifitime::{Epoch, Unit};
+
+use crate::product::{catalog::{SatelliteCatalog, SatelliteIdentity, Validity}, satellite::SatelliteId};
+
+// SINEX epochs are YYYY:DOY:SSSSS (or YY:DOY:SSSSS), 0000:000:00000 marks an open interval
+pub fn parse_sinex_epoch(epoch:&str) -> Result<Option<f64>, anyhow::Error> {
+
+    let parts:Vec<&str> = epoch.split(':').collect();
+    if parts.len() != 3 {
+        return Err(anyhow!("Invalid SINEX epoch {}", epoch));
+    }
+
+    let mut year = parts[0].parse::<i32>()?;
+    let doy = parts[1].parse::<u32>()?;
+    let seconds = parts[2].parse::<f64>()?;
+
+    if year == 0 && doy == 0 && seconds == 0.0 {
+        return Ok(None);
+    }
+
+    if parts[0].len() == 2 {
+        year += if year <= 50 { 2000 } else { 1900 };
+    }
+
+    let epoch = Epoch::from_gregorian_utc_at_midnight(year, 1, 1)
+        + Unit::Second * ((doy.max(1) - 1) as f64 * 86_400.0 + seconds);
+
+    Ok(Some(epoch.to_gpst_seconds()))
+}
+
+// SATELLITE/IDENTIFIER comments start with the launch date, e.g. "Launched 2018-12-23; NAVSTAR 74"
+pub fn parse_launch_date(comment:&str) -> Option<f64> {
+
+    let date = comment.strip_prefix("Launched")?.trim_start().get(..10)?;
+    let mut parts = date.split('-');
+
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u8>().ok()?;
+    let day = parts.next()?.parse::<u8>().ok()?;
+
+    let epoch = Epoch::maybe_from_gregorian_utc(year, month, day, 0, 0, 0, 0).ok()?;
+    Some(epoch.to_gpst_seconds())
+}
+
+fn parse_validity<T, F>(fields:&[&str], parse_value:F) -> Result<Validity<T>, anyhow::Error>
+where F: Fn(&str) -> Result<T, anyhow::Error> {
+
+    if fields.len() < 4 {
+        return Err(anyhow!("Incomplete SINEX record {:?}", fields));
+    }
+
+    Ok(Validity {
+        svn: fields[0].to_string(),
+        valid_from: parse_sinex_epoch(fields[1])?.unwrap_or(f64::MIN),
+        valid_to: parse_sinex_epoch(fields[2])?,
+        value: parse_value(fields[3])?,
+    })
+}
+
+// Parses the IGS satellite metadata SINEX (igs_satellite_metadata.snx)
+pub fn parse_satellite_metadata<R:BufRead>(reader:R) -> Result<SatelliteCatalog, anyhow::Error> {
+
+    let mut catalog = SatelliteCatalog {
+        identifiers: HashMap::new(),
+        prn: Vec::new(),
+        frequency_channel: Vec::new(),
+        mass_kg: Vec::new(),
+        tx_power_w: Vec::new(),
+    };
+
+    let mut block:Option<String> = None;
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if let Some(name) = line.strip_prefix('+') {
+            block = Some(name.trim().to_string());
+            continue;
+        }
+
+        if line.starts_with('-') {
+            block = None;
+            continue;
+        }
+
+        if line.starts_with('*') || line.starts_with('%') || line.trim().is_empty() {
+            continue;
+        }
+
+        let fields:Vec<&str> = line.split_whitespace().collect();
+
+        match block.as_deref() {
+            Some("SATELLITE/IDENTIFIER") => {
+                if fields.len() < 4 {
+                    return Err(anyhow!("Incomplete SINEX record {:?}", fields));
+                }
+                let comment = fields[4..].join(" ");
+                let identity = SatelliteIdentity {
+                    svn: fields[0].to_string(),
+                    cospar_id: fields[1].to_string(),
+                    satcat: fields[2].parse().ok(),
+                    block: fields[3].to_string(),
+                    launched: parse_launch_date(&comment),
+                    comment,
+                };
+                catalog.identifiers.insert(identity.svn.clone(), identity);
+            }
+            Some("SATELLITE/PRN") => {
+                catalog.prn.push(parse_validity(&fields, |v| v.parse::<SatelliteId>())?);
+            }
+            Some("SATELLITE/FREQUENCY_CHANNEL") => {
+                catalog.frequency_channel.push(parse_validity(&fields, |v| Ok(v.parse::<i32>()?))?);
+            }
+            Some("SATELLITE/MASS") => {
+                catalog.mass_kg.push(parse_validity(&fields, |v| Ok(v.parse::<f64>()?))?);
+            }
+            Some("SATELLITE/TX_POWER") => {
+                catalog.tx_power_w.push(parse_validity(&fields, |v| Ok(v.parse::<f64>()?))?);
+            }
+            _ => {
+                // other blocks (COM, ECCENTRICITY, PLANE, ...) are not used yet
+            }
+        }
+    }
+
+    if catalog.identifiers.is_empty() || catalog.prn.is_empty() {
+        return Err(anyhow!("No satellite identifiers or PRN assignments found"));
+    }
+
+    Ok(catalog)
+}
@@ -1,4 +1,4 @@
-use std::{env, io::BufReader, sync::Arc};
+use std::{collections::HashMap, env, io::{BufReader, Read}, sync::Arc};
 use anise::math::Vector3;
 use anyhow::anyhow;
 use arrow::compute::kernels::filter;
@@ -14,7 +14,7 @@ use object_store::{aws::{AmazonS3, AmazonS3Builder}, path::Path, ObjectStore};
 use sp3::SP3;
 use tracing::info;
 
-use crate::product::sv::{DataSource, Orbit, SVOrbitsClient, SVSource};
+use crate::{gpst::{from_calendar, TimeSystem}, product::{satellite::SatelliteId, sv::{DataSource, DataSourcesClient, Orbit, SVOrbitsClient, SVSource}}};
 
 pub fn r2_cddis_bucket() -> Result<AmazonS3, object_store::Error> {
 
@@ -41,6 +41,54 @@ pub fn cddis_path_parser(path:&str) -> Option<Captures<'_>> {
     return re.captures(path);
 }
 
+// SP3 clock values of 999999.999999 mark a bad or missing clock
+const SP3_BAD_CLOCK_USEC:f64 = 999_999.0;
+
+// epoch in GPST milliseconds, matching the epochs of the SP3 reader to those of the clock column
+fn epoch_key(gpst_seconds:f64) -> i64 {
+    (gpst_seconds * 1000.0).round() as i64
+}
+
+// Clock column of the SP3 position records as (GPST seconds, satellite, microseconds), the epochs
+// read in the time system of the first %c line. Bad or blank clocks are left out.
+pub fn sp3_clocks(text:&str) -> Result<Vec<(f64, SatelliteId, f64)>, anyhow::Error> {
+
+    let mut system = TimeSystem::Gpst;
+    let mut epoch:Option<f64> = None;
+    let mut clocks = Vec::new();
+
+    for line in text.lines() {
+        match line.get(..1).unwrap_or_default() {
+            // the second %c line holds "ccc" in place of the time system
+            "%" if line.starts_with("%c") => {
+                if let Ok(file_system) = line.get(9..12).unwrap_or_default().parse() {
+                    system = file_system;
+                }
+            }
+            "*" => {
+                let date:Vec<&str> = line[1..].split_whitespace().collect();
+                if date.len() != 6 {
+                    return Err(anyhow!("Invalid SP3 epoch {:?}", line));
+                }
+                let gpst = from_calendar(system, date[0].parse()?, date[1].parse()?, date[2].parse()?, date[3].parse()?, date[4].parse()?, date[5].parse()?)?;
+                epoch = Some(gpst.to_gpst_seconds());
+            }
+            "P" => {
+                let epoch = epoch.ok_or_else(|| anyhow!("SP3 position record before the first epoch"))?;
+                let Ok(satellite) = line.get(1..4).unwrap_or_default().replace(' ', "0").parse::<SatelliteId>() else {
+                    continue;
+                };
+                if let Ok(clock) = line.get(46..60).unwrap_or_default().trim().parse::<f64>() && clock.abs() < SP3_BAD_CLOCK_USEC {
+                    clocks.push((epoch, satellite, clock));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(clocks)
+}
+
 pub struct Sp3Table {
     table_name:String
 }
@@ -57,8 +105,10 @@ impl Sp3Table {
         let response = r2_bucket.get(&Path::from_absolute_path(&sp3_file.archive_path)?).await?;
 
         let sp3_bytes = response.bytes().await?;
-        let sp3_reader = GzDecoder::new(sp3_bytes.reader());
-        let mut buffered_sp3_reader = BufReader::new(sp3_reader);
+        let mut sp3_text = String::new();
+        GzDecoder::new(sp3_bytes.reader()).read_to_string(&mut sp3_text)?;
+
+        let mut buffered_sp3_reader = BufReader::new(sp3_text.as_bytes());
 
         let sp3_data_result = SP3::from_reader(&mut buffered_sp3_reader);
 
@@ -68,6 +118,13 @@ impl Sp3Table {
 
         let sp3_data = sp3_data_result.unwrap();
 
+        // the reader keeps positions only, the clock column is read from the records
+        let clocks:HashMap<(SatelliteId, i64), f64> = sp3_clocks(&sp3_text)
+            .map_err(|err| TerminalError::new(format!("Unable to parse the clocks of {}: {}", sp3_file.archive_path, err)))?
+            .into_iter()
+            .map(|(epoch, satellite, clock)| ((satellite, epoch_key(epoch)), clock))
+            .collect();
+
         let product_run_id = sp3_file.get_product_run_id()?;
 
         let mut gpst_seconds_vec = vec![];
@@ -75,16 +132,22 @@ impl Sp3Table {
         let mut satellite_vec = vec![];
         let mut constellation_vec = vec![];
         let mut pos_km_vec = vec![];
+        let mut clock_usec_vec = vec![];
 
         let vec_3d_type = Self::vec_3d_type();
 
         for (e, sv, _, _, vec_3d) in sp3_data.satellites_position_km_iter() {
 
+            let Ok(satellite) = sv.to_string().parse::<SatelliteId>() else {
+                info!("skipping unsupported satellite {} in {}", sv, sp3_file.archive_path);
+                continue;
+            };
+
             gpst_seconds_vec.push(e.to_gpst_seconds());
+            clock_usec_vec.push(clocks.get(&(satellite, epoch_key(e.to_gpst_seconds()))).copied());
             product_run_id_vec.push(product_run_id);
-            satellite_vec.push(sv.to_string());
-            let constellation = sv.to_string().chars().nth(0).unwrap().to_string();
-            constellation_vec.push(constellation);
+            satellite_vec.push(satellite.to_string());
+            constellation_vec.push(satellite.constellation.letter().to_string());
 
             // build vec within sequential
             pos_km_vec.push(vec_3d.0);
@@ -111,6 +174,8 @@ impl Sp3Table {
             .add_child_data(pos_km_vec_data_values)
             .build()?));
 
+        let clock_usec_array = Arc::new(Float64Array::from(clock_usec_vec));
+
 
 
         let schema = Self::arrow_schema();
@@ -123,7 +188,7 @@ impl Sp3Table {
                 satellite_array,
                 constellation_array,
                 pos_km_vec_data_array,
-
+                clock_usec_array,
             ]
             ).unwrap();
 
@@ -144,7 +209,7 @@ impl Sp3Table {
             Field::new("satellite", DataType::Utf8, false),
             Field::new("constellation", DataType::Utf8, false),
             Field::new("pos_km_vec", vec_3d_type.clone(), false),
-            // Field::new("clock_usec", DataType::Float64, true),
+            Field::new("clock_usec", DataType::Float64, true),
             // Field::new("vel_dms_vec", vec_3d_type.clone(), true), // (x,y,z) dm/sec
             // Field::new("clock_roc", DataType::Float64, true), // 10^-4 microseconds/second
         ]);
@@ -153,7 +218,7 @@ impl Sp3Table {
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize,Debug, PartialEq, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
 pub struct Sp3File {
     pub source:String,
     pub archive_path:String
@@ -363,11 +428,11 @@ impl Sp3Data for Sp3DataImpl {
                 .ok_or_else(|| arrow::error::ArrowError::CastError("Could not cast to StringArray".into()))?;
 
         // Find all unique satellite values
-        let mut unique_satellites:Vec<String> = Vec::new();
+        let mut unique_satellites:Vec<SatelliteId> = Vec::new();
         for i in 0..satellite_array.len() {
-            let sat = satellite_array.value(i);
-            if !unique_satellites.contains(&sat.to_string()) {
-                unique_satellites.push(sat.to_string());
+            let sat = satellite_array.value(i).parse::<SatelliteId>()?;
+            if !unique_satellites.contains(&sat) {
+                unique_satellites.push(sat);
             }
         }
 
@@ -395,11 +460,15 @@ impl Sp3Data for Sp3DataImpl {
 
         // Create a batch for each satellite
         //let mut batches_by_satellite = HashMap::new();s
+        let mut product_runs:Vec<(SatelliteId, u64)> = Vec::new();
+        let mut updates = Vec::new();
+
         for satellite in &unique_satellites {
 
             // Create filter mask for this satellite
+            let satellite_key = satellite.to_string();
             let filter_values: Vec<bool> = (0..satellite_array.len())
-                .map(|i| satellite_array.value(i) == satellite)
+                .map(|i| satellite_array.value(i) == satellite_key)
                 .collect();
             let filter_array = BooleanArray::from(filter_values);
 
@@ -420,7 +489,11 @@ impl Sp3Data for Sp3DataImpl {
                                     a.as_ref().unwrap().as_any().downcast_ref::<Float64Array>().unwrap().value(2))
                 ).collect();
 
-            let sv = SVSource {satellite:satellite.clone(), data_source: data_source.clone()};
+            // clocks only when every epoch has one, as for combined orbits
+            let clock_usec_array = filtered_batch.column(5).as_any().downcast_ref::<Float64Array>().unwrap();
+            let clock_usec = (clock_usec_array.null_count() == 0).then(|| clock_usec_array.values().to_vec());
+
+            let sv = SVSource {satellite:*satellite, data_source: data_source.clone()};
 
             let orbit = Orbit { sv,
                 product_run_id,
@@ -430,13 +503,21 @@ impl Sp3Data for Sp3DataImpl {
                 epochs:epoch_vec,
                 pos_ecef_km:pos_vec3d_km_vec,
                 pos_latlonalt:None,
-                clock_usec:None };
+                clock_usec,
+                metadata:None,
+                extrapolated:false };
 
-            ctx.object_client::<SVOrbitsClient>(orbit.sv.get_key()).update_orbit(Json(orbit)).send();
+            product_runs.push((orbit.sv.satellite, orbit.product_run_id));
+            updates.push(ctx.object_client::<SVOrbitsClient>(orbit.sv.get_key()).update_orbit(Json(orbit)).call());
 
         }
 
-        ctx.object_client::<SVOrbitsClient>(data_source.get_key()).update_satellites(Json(unique_satellites)).send();
+        // the run is only published once the orbits and their index are stored
+        for update in updates {
+            update.await?;
+        }
+        ctx.object_client::<SVOrbitsClient>(data_source.get_key()).update_product_runs(Json(product_runs)).call().await?;
+        ctx.object_client::<DataSourcesClient>("orbits").publish_product_run(Json(data_source)).send();
 
         Ok(())
     }
@@ -0,0 +1,145 @@
+use std::fmt;
+
+use restate_sdk::errors::TerminalError;
+use serde::Serialize;
+
+// Error kinds of the REST API. The orbit code returns them inside anyhow errors and the Restate
+// handlers as terminal errors with the HTTP status as code, so the API can answer with the same kind
+// whether a position was computed in process or behind the ingress.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    InvalidSatellite(String),
+    InvalidSource(String),
+    InvalidEpoch(String),
+    // any other malformed parameter or body
+    InvalidRequest(String),
+    // data source without stored orbits
+    UnknownSource(String),
+    NotFound(String),
+    // epoch outside the span covered by the orbit, including the extrapolation limit
+    OutsideCoverage(String),
+    // valid request the computation failed on, e.g. a fit without enough samples
+    Unprocessable(String),
+    // Restate ingress unreachable or failing
+    BackendUnavailable(String),
+    Internal(String),
+}
+
+// JSON body of error responses
+#[derive(Serialize, utoipa::ToSchema, Debug)]
+pub struct ErrorBody {
+    // kind, e.g. invalid_epoch or outside_coverage
+    pub error:&'static str,
+    pub message:String,
+}
+
+impl ApiError {
+
+    pub fn invalid_satellite(sv:&str) -> Self {
+        ApiError::InvalidSatellite(format!("Invalid satellite {}", sv))
+    }
+
+    pub fn invalid_source(source:&str) -> Self {
+        ApiError::InvalidSource(format!("Invalid source {}", source))
+    }
+
+    pub fn invalid_epoch(epoch:&str) -> Self {
+        ApiError::InvalidEpoch(format!("Invalid epoch {}", epoch))
+    }
+
+    pub fn unsupported_format(format:&str) -> Self {
+        ApiError::InvalidRequest(format!("Unsupported format {}", format))
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::InvalidSatellite(_) | ApiError::InvalidSource(_) | ApiError::InvalidEpoch(_) | ApiError::InvalidRequest(_) => 400,
+            ApiError::UnknownSource(_) | ApiError::NotFound(_) => 404,
+            ApiError::OutsideCoverage(_) => 416,
+            ApiError::Unprocessable(_) => 422,
+            ApiError::BackendUnavailable(_) => 503,
+            ApiError::Internal(_) => 500,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::InvalidSatellite(_) => "invalid_satellite",
+            ApiError::InvalidSource(_) => "invalid_source",
+            ApiError::InvalidEpoch(_) => "invalid_epoch",
+            ApiError::InvalidRequest(_) => "invalid_request",
+            ApiError::UnknownSource(_) => "unknown_source",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::OutsideCoverage(_) => "outside_coverage",
+            ApiError::Unprocessable(_) => "unprocessable",
+            ApiError::BackendUnavailable(_) => "backend_unavailable",
+            ApiError::Internal(_) => "internal",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ApiError::InvalidSatellite(message)
+            | ApiError::InvalidSource(message)
+            | ApiError::InvalidEpoch(message)
+            | ApiError::InvalidRequest(message)
+            | ApiError::UnknownSource(message)
+            | ApiError::NotFound(message)
+            | ApiError::OutsideCoverage(message)
+            | ApiError::Unprocessable(message)
+            | ApiError::BackendUnavailable(message)
+            | ApiError::Internal(message) => message,
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody { error: self.kind(), message: self.message().to_string() }
+    }
+
+    // error kind of a status returned by the ingress for a handler call, the 400 kinds are told
+    // apart by the API before calling so they come back as InvalidRequest
+    pub fn from_status(status:u16, message:String) -> Self {
+        match status {
+            400 => ApiError::InvalidRequest(message),
+            404 => ApiError::NotFound(message),
+            416 => ApiError::OutsideCoverage(message),
+            422 => ApiError::Unprocessable(message),
+            502..=504 => ApiError::BackendUnavailable(message),
+            _ => ApiError::Internal(message),
+        }
+    }
+
+    // kind carried by an error from the orbit code, `untyped` for errors without one
+    pub fn from_anyhow(err:anyhow::Error, untyped:fn(String) -> ApiError) -> Self {
+        match err.downcast::<ApiError>() {
+            Ok(api_error) => api_error,
+            Err(err) => untyped(err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err:anyhow::Error) -> Self {
+        ApiError::from_anyhow(err, ApiError::Internal)
+    }
+}
+
+// terminal error answered by the ingress with the status of the kind
+impl From<ApiError> for TerminalError {
+    fn from(err:ApiError) -> Self {
+        TerminalError::new_with_code(err.status(), err.message())
+    }
+}
+
+// terminal error for a failed computation in a handler, typed errors keep their kind
+pub fn terminal_error(err:anyhow::Error, untyped:fn(String) -> ApiError) -> TerminalError {
+    ApiError::from_anyhow(err, untyped).into()
+}
@@ -1,12 +1,527 @@
-use hifitime::Epoch;
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use hifitime::{Epoch, TimeScale, Unit};
 use restate_sdk::errors::HandlerError;
+use serde::{ser::{Error, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant}, Serialize, Serializer};
+
+use crate::product::satellite::Constellation;
+
+const SECONDS_PER_DAY:f64 = 86_400.0;
+
+// GLONASST is UTC(SU) + 3 h, the only system without a hifitime time scale
+const GLONASST_MINUS_UTC_S:f64 = 10_800.0;
+
+// GNSS time systems, named by their RINEX/SP3 identifiers
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TimeSystem {
+    Gpst,
+    Gst,
+    Bdt,
+    Glonasst,
+    Qzsst,
+    Tai,
+    Utc,
+}
+
+impl TimeSystem {
+
+    // time system of the constellation's navigation messages, NavIC and SBAS are steered to GPST
+    pub fn for_constellation(constellation:Constellation) -> Self {
+        match constellation {
+            Constellation::Glonass => TimeSystem::Glonasst,
+            Constellation::Galileo => TimeSystem::Gst,
+            Constellation::BeiDou => TimeSystem::Bdt,
+            Constellation::Qzss => TimeSystem::Qzsst,
+            _ => TimeSystem::Gpst,
+        }
+    }
+
+    // RINEX and SP3 header identifier
+    pub fn code(&self) -> &'static str {
+        match self {
+            TimeSystem::Gpst => "GPS",
+            TimeSystem::Gst => "GAL",
+            TimeSystem::Bdt => "BDT",
+            TimeSystem::Glonasst => "GLO",
+            TimeSystem::Qzsst => "QZS",
+            TimeSystem::Tai => "TAI",
+            TimeSystem::Utc => "UTC",
+        }
+    }
+
+    // hifitime time scale of the system, GLONASST runs on UTC shifted by three hours
+    pub fn time_scale(&self) -> TimeScale {
+        match self {
+            TimeSystem::Gpst => TimeScale::GPST,
+            TimeSystem::Gst => TimeScale::GST,
+            TimeSystem::Bdt => TimeScale::BDT,
+            TimeSystem::Qzsst => TimeScale::QZSST,
+            TimeSystem::Tai => TimeScale::TAI,
+            TimeSystem::Utc | TimeSystem::Glonasst => TimeScale::UTC,
+        }
+    }
+
+    // calendar offset from the time scale's own calendar
+    fn calendar_offset_s(&self) -> f64 {
+        match self {
+            TimeSystem::Glonasst => GLONASST_MINUS_UTC_S,
+            _ => 0.0,
+        }
+    }
+
+    // Time scale the system counts weeks in. hifitime starts Galileo weeks at the first GPS
+    // rollover (1999-08-22) and BeiDou weeks at 2006-01-01, GLONASST, TAI and UTC have no weeks.
+    fn week_scale(&self) -> Result<TimeScale, anyhow::Error> {
+        match self {
+            TimeSystem::Glonasst | TimeSystem::Tai | TimeSystem::Utc => Err(anyhow!("{} has no week numbering", self)),
+            _ => Ok(self.time_scale()),
+        }
+    }
+
+    // Weeks after which the broadcast week number rolls over: 10 bits for GPS and QZSS LNAV,
+    // 12 bits for Galileo and 13 bits for BeiDou, as in the RTCM ephemeris messages
+    pub fn week_rollover(&self) -> Option<u32> {
+        match self {
+            TimeSystem::Gpst | TimeSystem::Qzsst => Some(1_024),
+            TimeSystem::Gst => Some(4_096),
+            TimeSystem::Bdt => Some(8_192),
+            TimeSystem::Glonasst | TimeSystem::Tai | TimeSystem::Utc => None,
+        }
+    }
+}
+
+impl fmt::Display for TimeSystem {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for TimeSystem {
+    type Err = anyhow::Error;
+
+    // RINEX/SP3 identifiers or the system names, GPST or GPS, GST or GAL, ...
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "GPS" | "GPST" => Ok(TimeSystem::Gpst),
+            "GAL" | "GST" => Ok(TimeSystem::Gst),
+            "BDT" | "BDS" => Ok(TimeSystem::Bdt),
+            "GLO" | "GLONASST" | "GLONASS" => Ok(TimeSystem::Glonasst),
+            "QZS" | "QZSST" => Ok(TimeSystem::Qzsst),
+            "TAI" => Ok(TimeSystem::Tai),
+            "UTC" => Ok(TimeSystem::Utc),
+            _ => Err(anyhow!("Unknown time system {}", s)),
+        }
+    }
+}
+
+// Days since 1980-01-06 of a calendar date. GPST has no leap seconds, so its calendar counts
+// days and seconds of day for every system.
+fn calendar_days(year:i32, month:u8, day:u8) -> f64 {
+    Epoch::from_gregorian_at_midnight(year, month, day, TimeScale::GPST).to_gpst_days().round()
+}
+
+// Reading of the system's clock at the epoch, as seconds since 1980-01-06 00:00:00 on the
+// system's own calendar. UTC and GLONASST readings step with leap seconds.
+pub fn system_seconds(epoch:&Epoch, system:TimeSystem) -> f64 {
+    let (year, month, day, hour, minute, second) = to_calendar(epoch, system);
+    calendar_days(year, month, day) * SECONDS_PER_DAY + hour as f64 * 3_600.0 + minute as f64 * 60.0 + second
+}
+
+// epoch of a reading of the system's clock, the inverse of system_seconds
+pub fn from_system_seconds(seconds:f64, system:TimeSystem) -> Result<Epoch, anyhow::Error> {
+    let (year, month, day, hour, minute, second) = to_calendar(&Epoch::from_gpst_seconds(seconds), TimeSystem::Gpst);
+    from_calendar(system, year, month, day, hour, minute, second)
+}
+
+// epoch of a calendar date and time as read in the time system, e.g. an SP3 or RINEX epoch line
+pub fn from_calendar(system:TimeSystem, year:i32, month:u8, day:u8, hour:u8, minute:u8, second:f64) -> Result<Epoch, anyhow::Error> {
+
+    let whole = second.floor();
+    let nanos = (((second - whole) * 1e9).round() as u32).min(999_999_999);
+
+    let epoch = Epoch::maybe_from_gregorian(year, month, day, hour, minute, whole as u8, nanos, system.time_scale())
+        .map_err(|err| anyhow!("Invalid calendar epoch {:04}-{:02}-{:02}T{:02}:{:02}:{:09.6}: {}", year, month, day, hour, minute, second, err))?;
+
+    Ok(epoch - Unit::Second * system.calendar_offset_s())
+}
+
+// calendar date, hour, minute and second of the epoch in the time system
+pub fn to_calendar(epoch:&Epoch, system:TimeSystem) -> (i32, u8, u8, u8, u8, f64) {
+    let time_scale = system.time_scale();
+    let epoch = *epoch + Unit::Second * system.calendar_offset_s();
+    let (year, month, day, hour, minute, second, nanos) = Epoch::compute_gregorian(epoch.to_duration_in_time_scale(time_scale), time_scale);
+    (year, month, day, hour, minute, second as f64 + nanos as f64 / 1e9)
+}
+
+// full week number and seconds of week in the system's week numbering
+pub fn week_tow(epoch:&Epoch, system:TimeSystem) -> Result<(u32, f64), anyhow::Error> {
+
+    let time_scale = system.week_scale()?;
 
+    if *epoch < Epoch::from_time_of_week(0, 0, time_scale) {
+        return Err(anyhow!("Epoch before week 0 of {}", system));
+    }
+
+    let (week, nanos) = epoch.to_time_scale(time_scale).to_time_of_week();
+    Ok((week, nanos as f64 / 1e9))
+}
+
+// epoch of a full week number and seconds of week in the system's week numbering
+pub fn from_week_tow(system:TimeSystem, week:u32, tow_s:f64) -> Result<Epoch, anyhow::Error> {
+    let time_scale = system.week_scale()?;
+    Ok(Epoch::from_time_of_week(week, 0, time_scale) + Unit::Second * tow_s)
+}
+
+// Full week number of a broadcast week truncated to the system's rollover, the candidate closest
+// to the week of a reference epoch such as the receiving or file time
+pub fn resolve_week_rollover(system:TimeSystem, week:u32, reference:&Epoch) -> Result<u32, anyhow::Error> {
+
+    let rollover = system.week_rollover().ok_or_else(|| anyhow!("{} has no week numbering", system))?;
+    let (reference_week, _) = week_tow(reference, system)?;
+
+    let week = week % rollover;
+    let cycles = ((reference_week as f64 - week as f64) / rollover as f64).round().max(0.0) as u32;
+
+    Ok(week + cycles * rollover)
+}
+
+// year, day of year (1-366) and seconds of day of the epoch in the time system
+pub fn day_of_year(epoch:&Epoch, system:TimeSystem) -> (i32, u16, f64) {
+    let (year, month, day, hour, minute, second) = to_calendar(epoch, system);
+    let doy = calendar_days(year, month, day) - calendar_days(year, 1, 1) + 1.0;
+    (year, doy as u16, hour as f64 * 3_600.0 + minute as f64 * 60.0 + second)
+}
+
+// epoch of a year, day of year and seconds of day in the time system
+pub fn from_day_of_year(system:TimeSystem, year:i32, doy:u16, seconds_of_day:f64) -> Result<Epoch, anyhow::Error> {
+    from_system_seconds((calendar_days(year, 1, 1) + doy as f64 - 1.0) * SECONDS_PER_DAY + seconds_of_day, system)
+}
+
+// ISO 8601 calendar date and time in the time system, followed by its code
+pub fn iso_in(epoch:&Epoch, system:TimeSystem) -> String {
+    let (year, month, day, hour, minute, second) = to_calendar(epoch, system);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:09.6} {}", year, month, day, hour, minute, second, system)
+}
+
+// YYYY-MM-DD[THH:MM[:SS[.ffffff]]] in the time system
+fn parse_calendar(input:&str, system:TimeSystem) -> Result<Epoch, anyhow::Error> {
+
+    let invalid = || anyhow!("Invalid calendar epoch {}", input);
+
+    let (date, time) = input.split_once('T').unwrap_or((input, "00:00"));
+
+    let date:Vec<&str> = date.split('-').collect();
+    let [year, month, day] = date.as_slice() else {
+        return Err(invalid());
+    };
+    let (year, month, day) = (year.parse::<i32>()?, month.parse::<u8>()?, day.parse::<u8>()?);
+
+    let time:Vec<&str> = time.split(':').collect();
+    let (hour, minute, second) = match time.as_slice() {
+        [hour, minute] => (hour.parse::<u8>()?, minute.parse::<u8>()?, 0.0),
+        [hour, minute, second] => (hour.parse::<u8>()?, minute.parse::<u8>()?, second.parse::<f64>()?),
+        _ => return Err(invalid()),
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || !(0.0..61.0).contains(&second) {
+        return Err(invalid());
+    }
+
+    from_calendar(system, year, month, day, hour, minute, second)
+}
+
+// Epoch from an API input: GPST seconds, "now", an ISO 8601 date and time in UTC ("Z" or no
+// suffix) or followed by a time system code as written by iso_in, "unix:<seconds>",
+// "week:<GPS week>:<seconds of week>" or "mjd:<UTC modified Julian date>"
+pub fn parse_epoch(input:&str) -> Result<Epoch, anyhow::Error> {
+
+    let input = input.trim();
+
+    if let Ok(gpst_seconds) = input.parse::<f64>() {
+        return Ok(Epoch::from_gpst_seconds(gpst_seconds));
+    }
+
+    if input.eq_ignore_ascii_case("now") {
+        return Epoch::now().map_err(|err| anyhow!("{}", err));
+    }
+
+    if let Some((kind, value)) = input.split_once(':') && !kind.contains('-') {
+        return match kind.to_ascii_lowercase().as_str() {
+            "unix" => Ok(Epoch::from_unix_seconds(value.parse()?)),
+            "mjd" => Ok(Epoch::from_mjd_utc(value.parse()?)),
+            "week" => {
+                let (week, tow_s) = value.split_once(':').ok_or_else(|| anyhow!("Expected week:<week>:<seconds of week>"))?;
+                from_week_tow(TimeSystem::Gpst, week.parse()?, tow_s.parse()?)
+            }
+            _ => Err(anyhow!("Unknown epoch format {}", input)),
+        };
+    }
+
+    match input.split_once(' ') {
+        Some((calendar, system)) => parse_calendar(calendar, system.parse()?),
+        None => parse_calendar(input.trim_end_matches('Z'), TimeSystem::Utc),
+    }
+}
 
 pub fn gpst_week(epoch:&Epoch) -> u32 {
     let gpst_week = (epoch.to_gpst_days() / 7.0).floor() as u32;
     gpst_week
 }
 
+// ISO 8601 calendar date in UTC with microseconds, 2024-05-01T00:00:00.000000
+pub fn iso_utc(epoch:&Epoch) -> String {
+    let (year, month, day, hour, minute, second, nanos) = epoch.to_gregorian_utc();
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}", year, month, day, hour, minute, second, nanos / 1_000)
+}
+
+// newtype name marking GPST seconds for EpochSerializer, other serializers write the seconds
+const EPOCH_TOKEN:&str = "$ground_control::gpst::Epoch";
+
+// serialize_with of the fields holding GPST seconds
+pub fn serialize_epoch<S:Serializer>(seconds:&f64, serializer:S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_newtype_struct(EPOCH_TOKEN, seconds)
+}
+
+pub fn serialize_optional_epoch<S:Serializer>(seconds:&Option<f64>, serializer:S) -> Result<S::Ok, S::Error> {
+    match seconds {
+        Some(seconds) => serializer.serialize_some(&ResponseEpoch(*seconds)),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn serialize_epochs<S:Serializer>(seconds:&[f64], serializer:S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(seconds.iter().map(|seconds| ResponseEpoch(*seconds)))
+}
+
+struct ResponseEpoch(f64);
+
+impl Serialize for ResponseEpoch {
+    fn serialize<S:Serializer>(&self, serializer:S) -> Result<S::Ok, S::Error> {
+        serialize_epoch(&self.0, serializer)
+    }
+}
+
+// Value serialized with its epoch fields written as ISO 8601 strings in the time system, GPST
+// seconds without one. Restate state and handler payloads are serialized without it.
+pub struct InTimeSystem<'a, T:?Sized>(pub &'a T, pub Option<TimeSystem>);
+
+impl<T:Serialize + ?Sized> Serialize for InTimeSystem<'_, T> {
+    fn serialize<S:Serializer>(&self, serializer:S) -> Result<S::Ok, S::Error> {
+        match self.1 {
+            Some(system) => self.0.serialize(EpochSerializer { inner: serializer, system }),
+            None => self.0.serialize(serializer),
+        }
+    }
+}
+
+// Forwards to the inner serializer, writing the values marked by serialize_epoch as iso_in strings
+struct EpochSerializer<S> {
+    inner:S,
+    system:TimeSystem,
+}
+
+impl<S:Serializer> Serializer for EpochSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = EpochCompound<S::SerializeSeq>;
+    type SerializeTuple = EpochCompound<S::SerializeTuple>;
+    type SerializeTupleStruct = EpochCompound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = EpochCompound<S::SerializeTupleVariant>;
+    type SerializeMap = EpochCompound<S::SerializeMap>;
+    type SerializeStruct = EpochCompound<S::SerializeStruct>;
+    type SerializeStructVariant = EpochCompound<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v:bool) -> Result<S::Ok, S::Error> { self.inner.serialize_bool(v) }
+    fn serialize_i8(self, v:i8) -> Result<S::Ok, S::Error> { self.inner.serialize_i8(v) }
+    fn serialize_i16(self, v:i16) -> Result<S::Ok, S::Error> { self.inner.serialize_i16(v) }
+    fn serialize_i32(self, v:i32) -> Result<S::Ok, S::Error> { self.inner.serialize_i32(v) }
+    fn serialize_i64(self, v:i64) -> Result<S::Ok, S::Error> { self.inner.serialize_i64(v) }
+    fn serialize_i128(self, v:i128) -> Result<S::Ok, S::Error> { self.inner.serialize_i128(v) }
+    fn serialize_u8(self, v:u8) -> Result<S::Ok, S::Error> { self.inner.serialize_u8(v) }
+    fn serialize_u16(self, v:u16) -> Result<S::Ok, S::Error> { self.inner.serialize_u16(v) }
+    fn serialize_u32(self, v:u32) -> Result<S::Ok, S::Error> { self.inner.serialize_u32(v) }
+    fn serialize_u64(self, v:u64) -> Result<S::Ok, S::Error> { self.inner.serialize_u64(v) }
+    fn serialize_u128(self, v:u128) -> Result<S::Ok, S::Error> { self.inner.serialize_u128(v) }
+    fn serialize_f32(self, v:f32) -> Result<S::Ok, S::Error> { self.inner.serialize_f32(v) }
+    fn serialize_f64(self, v:f64) -> Result<S::Ok, S::Error> { self.inner.serialize_f64(v) }
+    fn serialize_char(self, v:char) -> Result<S::Ok, S::Error> { self.inner.serialize_char(v) }
+    fn serialize_str(self, v:&str) -> Result<S::Ok, S::Error> { self.inner.serialize_str(v) }
+    fn serialize_bytes(self, v:&[u8]) -> Result<S::Ok, S::Error> { self.inner.serialize_bytes(v) }
+    fn serialize_none(self) -> Result<S::Ok, S::Error> { self.inner.serialize_none() }
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> { self.inner.serialize_unit() }
+
+    fn serialize_some<T:Serialize + ?Sized>(self, value:&T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn serialize_unit_struct(self, name:&'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name:&'static str, index:u32, variant:&'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<T:Serialize + ?Sized>(self, name:&'static str, value:&T) -> Result<S::Ok, S::Error> {
+
+        if name != EPOCH_TOKEN {
+            return self.inner.serialize_newtype_struct(name, &InTimeSystem(value, Some(self.system)));
+        }
+
+        let seconds = serde_json::to_value(value).ok()
+            .and_then(|seconds| seconds.as_f64())
+            .ok_or_else(|| S::Error::custom("epoch is not GPST seconds"))?;
+
+        self.inner.serialize_str(&iso_in(&Epoch::from_gpst_seconds(seconds), self.system))
+    }
+
+    fn serialize_newtype_variant<T:Serialize + ?Sized>(self, name:&'static str, index:u32, variant:&'static str, value:&T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, &InTimeSystem(value, Some(self.system)))
+    }
+
+    fn serialize_seq(self, len:Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_seq(len)?, system: self.system })
+    }
+
+    fn serialize_tuple(self, len:usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_tuple(len)?, system: self.system })
+    }
+
+    fn serialize_tuple_struct(self, name:&'static str, len:usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_tuple_struct(name, len)?, system: self.system })
+    }
+
+    fn serialize_tuple_variant(self, name:&'static str, index:u32, variant:&'static str, len:usize) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_tuple_variant(name, index, variant, len)?, system: self.system })
+    }
+
+    fn serialize_map(self, len:Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_map(len)?, system: self.system })
+    }
+
+    fn serialize_struct(self, name:&'static str, len:usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_struct(name, len)?, system: self.system })
+    }
+
+    fn serialize_struct_variant(self, name:&'static str, index:u32, variant:&'static str, len:usize) -> Result<Self::SerializeStructVariant, S::Error> {
+        Ok(EpochCompound { inner: self.inner.serialize_struct_variant(name, index, variant, len)?, system: self.system })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+// sequence, tuple, map or struct of an EpochSerializer, its elements serialized the same way
+struct EpochCompound<C> {
+    inner:C,
+    system:TimeSystem,
+}
+
+impl<C:SerializeSeq> SerializeSeq for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T:Serialize + ?Sized>(&mut self, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeTuple> SerializeTuple for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T:Serialize + ?Sized>(&mut self, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeTupleStruct> SerializeTupleStruct for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T:Serialize + ?Sized>(&mut self, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeTupleVariant> SerializeTupleVariant for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T:Serialize + ?Sized>(&mut self, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeMap> SerializeMap for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T:Serialize + ?Sized>(&mut self, key:&T) -> Result<(), C::Error> {
+        self.inner.serialize_key(&InTimeSystem(key, Some(self.system)))
+    }
+
+    fn serialize_value<T:Serialize + ?Sized>(&mut self, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_value(&InTimeSystem(value, Some(self.system)))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeStruct> SerializeStruct for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T:Serialize + ?Sized>(&mut self, key:&'static str, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &InTimeSystem(value, Some(self.system)))
+    }
+
+    fn skip_field(&mut self, key:&'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C:SerializeStructVariant> SerializeStructVariant for EpochCompound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T:Serialize + ?Sized>(&mut self, key:&'static str, value:&T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &InTimeSystem(value, Some(self.system)))
+    }
+
+    fn skip_field(&mut self, key:&'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
 // async so it can be wraped in a restate run closure
 pub async fn current_gpst_seconds() -> Result<f64, HandlerError> {
     Ok(Epoch::now()?.to_gpst_seconds())
@@ -1,3 +1,5 @@
 pub mod gpst;
+pub mod error;
 pub mod product;
 pub mod data;
+pub mod algo;
@@ -1,152 +1,1465 @@
 mod data;
 mod product;
 mod algo;
+mod gpst;
+mod error;
 
-use axum::{extract::Path, response::IntoResponse, routing::{get, post}, Json, Router};
-use axum::http::{HeaderValue, Method};
+use algo::{combination::{combined_source, CombinationConfig, CombinationReport}, dop::{dop_series, DopEpoch}, ephemeris::{EphemerisConfig, EphemerisFit}, grid::{visibility_grid, GridField, VisibilityGrid}, look::{look_angles_all, LookAngles, Observer}, pass::{predict_passes_all, ElevationMask, Pass}, sisre::{SisreConfig, SisreMonitoring}, ssr::{SsrConfig, SsrCorrections}, sun::{sun_moon_ecef_km, SatelliteState}, tle::{Sgp4Comparison, TleConfig, TleFit}, track::SatellitePosition};
+use arrow::{error::ArrowError, ipc::writer::StreamWriter};
+use arrow_array::RecordBatch;
+use axum::{extract::{ws::{Message, WebSocket, WebSocketUpgrade}, FromRequestParts, Path, Query}, response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response}, routing::{get, post}, Json, Router};
+use axum::http::{header, request::Parts, HeaderValue, Method};
+use data::czml::czml;
+use data::geojson::{ground_tracks_geojson, positions_geojson};
+use data::kml::{ground_tracks_kml, positions_kml};
+use data::oem::{oem_kvn, oem_xml, EphemerisSegment};
+use data::rinex::{NavData, NavDataImpl, NavFile};
+use data::rtcm::{encode_clock_corrections, encode_orbit_corrections, SsrStream};
 use data::sp3::{Sp3Data, Sp3DataImpl, Sp3File};
+use error::{ApiError, ErrorBody};
+use gpst::{parse_epoch, InTimeSystem, TimeSystem};
 use hifitime::Epoch;
-use product::sv::{DataSource, DataSources, DataSourcesImpl, Orbit, SVOrbits, SVOrbitsImpl, SVSource};
+use product::cache::OrbitCache;
+use product::kernels::{self, KernelManager};
+use product::catalog::{is_svn, SatelliteInfo, SatelliteMetadata, SatelliteMetadataImpl, SatelliteQuery, CATALOG_KEY};
+use product::combination::{CombinationRequest, OrbitCombination, OrbitCombinationImpl};
+use product::ephemeris::{BroadcastEphemerides, BroadcastEphemeridesImpl};
+use product::satellite::{Constellation, SatelliteId};
+use product::sisre::{SisreMonitor, SisreMonitorImpl, SisreRequest};
+use product::ssr::{SsrGenerator, SsrGeneratorImpl, SsrRequest};
+use product::stream::{PositionStream, PositionUpdate, SatelliteFilter};
+use product::tle::{MeanElementSets, MeanElementSetsImpl, Sgp4ComparisonRequest};
+use product::sv::{best_positions, orbit_positions, satellite_states, set_latlonalt, DataSource, DataSources, DataSourcesImpl, Orbit, SourceChain, SVOrbits, SVOrbitsImpl, SVSource, BEST_SOURCE};
 use reqwest::StatusCode;
 use restate_sdk::prelude::{Endpoint, HttpServer};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::Value;
+use std::{collections::{BTreeMap, HashMap}, convert::Infallible, sync::Arc, time::Duration};
+use tokio::{sync::{broadcast::error::RecvError, mpsc}, time::{Instant, MissedTickBehavior}};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tracing::info;
+use utoipa::{openapi::{response::ResponseBuilder, ContentBuilder, Ref, RefOr, Response as OpenApiResponse}, IntoResponses, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
 
 const INGRESS_URL:&str = "http://127.0.0.1:8080/";
 
+// updates buffered per live position stream before the producer waits for the client
+const STREAM_BUFFER:usize = 4;
+// interval between polls of the product runs published by the workflows
+const PRODUCT_RUN_POLL:Duration = Duration::from_secs(1);
+
+const CORS_HEADERS:[(&str, &str); 3] = [
+    ("Access-Control-Allow-Origin", "*"),
+    ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
+    ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
+];
+
 async fn add_cors_headers() -> impl IntoResponse {
-    (
+    (CORS_HEADERS, "")
+}
+
+// error responses carry the status of the kind and a {"error", "message"} body
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, CORS_HEADERS, Json(self.body())).into_response()
+    }
+}
+
+// Query extractor answering malformed parameters, such as an unparsable epoch, with an ApiError body
+// instead of the plain text rejection of Query
+struct ApiQuery<T>(T);
+
+impl<T:DeserializeOwned + Send, S:Send + Sync> FromRequestParts<S> for ApiQuery<T> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts:&mut Parts, state:&S) -> Result<Self, ApiError> {
+        Query::<T>::from_request_parts(parts, state).await
+            .map(|Query(query)| ApiQuery(query))
+            .map_err(|rejection| ApiError::InvalidRequest(rejection.body_text()))
+    }
+}
+
+// error statuses documented on every route, all with an ErrorBody
+struct ErrorResponses;
+
+impl IntoResponses for ErrorResponses {
+    fn responses() -> BTreeMap<String, RefOr<OpenApiResponse>> {
         [
-            ("Access-Control-Allow-Origin", "*"),
-            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
-            ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
-        ],
-        "",
-    )
+            ("400", "Invalid satellite, source, epoch or other parameter"),
+            ("404", "Unknown source or missing data"),
+            ("416", "Epoch outside the orbit coverage"),
+            ("422", "Computation failed for a valid request"),
+            ("500", "Internal error"),
+            ("503", "Restate ingress unavailable"),
+        ].into_iter()
+            .map(|(status, description)| {
+                let response = ResponseBuilder::new()
+                    .description(description)
+                    .content("application/json", ContentBuilder::new().schema(Some(Ref::from_schema_name("ErrorBody"))).build())
+                    .build();
+                (status.to_string(), response.into())
+            })
+            .collect()
+    }
 }
 
-async fn process_sp3(Json(payload): Json<Sp3File>) -> impl IntoResponse {
+fn data_source(source:&str) -> Result<DataSource, ApiError> {
+    DataSource::from_key(source.to_string()).map_err(|_| ApiError::invalid_source(source))
+}
 
-    let response = reqwest::Client::new()
-        .get(format!("{}/Sp3Data/processSp3", INGRESS_URL))
-        .header("Accept", "application/json")
-        .header("Content-Type", "*/*")
-        .json(&payload)
-        .send()
-        .await;
+fn satellite(sv:&str) -> Result<SatelliteId, ApiError> {
+    sv.parse().map_err(|_| ApiError::invalid_satellite(sv))
+}
 
-    info!("{:?}", response);
+fn source_chain(source:&str, chain:Option<&str>) -> Result<SourceChain, ApiError> {
+    SourceChain::resolve(source, chain).map_err(|_| ApiError::invalid_source(source))
+}
 
-    (StatusCode::OK, ()).into_response()
+// SVOrbits of a data source without stored orbits answer 404
+fn unknown_source(err:ApiError, data_source:&DataSource) -> ApiError {
+    match err {
+        ApiError::NotFound(_) => ApiError::UnknownSource(format!("No orbits for source {}", data_source.get_key())),
+        err => err,
+    }
 }
 
-async fn get_sources() -> impl IntoResponse {
+#[utoipa::path(post, path = "/orbit/source", tag = "admin",
+    request_body = Sp3File,
+    responses((status = 202, description = "SP3 file queued for processing, with the Restate invocation"), ErrorResponses))]
+async fn process_sp3(Json(payload): Json<Sp3File>) -> Result<Response, ApiError> {
 
-    let response = reqwest::Client::new()
-        .post(format!("{}/DataSources/orbits/getSources", INGRESS_URL))
-        .header("Accept", "application/json")
-        .header("Content-Type", "*/*")
-        .send()
-        .await;
+    if !payload.is_sp3() {
+        return Err(ApiError::InvalidRequest(format!("Not an SP3 file: {}", payload.archive_path)));
+    }
 
-    (
-        [
-            ("Access-Control-Allow-Origin", "*"),
-            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
-            ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
-        ],
-        Json(response.unwrap().text().await.unwrap())
-    ).into_response()
+    // Sp3Data is keyed by the archive source, the same key the archiver sends to
+    let invocation = call_handler::<Value>(&reqwest::Client::new(),
+        format!("Sp3Data/{}/processSp3/send", payload.source),
+        serde_json::to_string(&payload).unwrap()).await?;
+
+    info!("queued {} {:?}", payload.archive_path, invocation);
+
+    Ok((StatusCode::ACCEPTED, CORS_HEADERS, Json(invocation)).into_response())
+}
+
+#[utoipa::path(post, path = "/nav/source", tag = "admin",
+    request_body = NavFile,
+    responses((status = 202, description = "RINEX navigation file queued for processing, with the Restate invocation"), ErrorResponses))]
+async fn process_nav(Json(payload): Json<NavFile>) -> Result<Response, ApiError> {
+
+    payload.data_source().map_err(|err| ApiError::InvalidRequest(err.to_string()))?;
+
+    let invocation = call_handler::<Value>(&reqwest::Client::new(),
+        format!("NavData/{}/processNav/send", payload.source),
+        serde_json::to_string(&payload).unwrap()).await?;
+
+    info!("queued {} {:?}", payload.archive_path, invocation);
+
+    Ok((StatusCode::ACCEPTED, CORS_HEADERS, Json(invocation)).into_response())
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema, Debug)]
+struct CombineRequest {
+    // data source keys of the analysis center products to combine
+    sources:Vec<String>,
+    // combined data source key, defaults to the first source with analysis center cmb
+    target:Option<String>,
+    config:Option<CombinationConfig>,
+}
+
+#[utoipa::path(post, path = "/orbit/combine", tag = "products",
+    request_body = CombineRequest,
+    responses((status = 200, body = CombinationReport), ErrorResponses))]
+async fn combine_orbits(Json(payload): Json<CombineRequest>) -> Result<Response, ApiError> {
+
+    let sources = payload.sources.iter()
+        .map(|source| data_source(source))
+        .collect::<Result<Vec<DataSource>, ApiError>>()?;
+
+    let target = match payload.target {
+        Some(target) => Some(data_source(&target)?),
+        None => combined_source(&sources),
+    };
+
+    let Some(target) = target else {
+        return Err(ApiError::InvalidRequest("No combination target for the sources".to_string()));
+    };
+
+    let request = CombinationRequest { sources, config: payload.config };
+
+    let report = call_handler::<CombinationReport>(&reqwest::Client::new(),
+        format!("OrbitCombination/{}/combine", target.get_key()),
+        serde_json::to_string(&request).unwrap()).await?;
+
+    Ok((CORS_HEADERS, Json(report)).into_response())
 }
 
-async fn get_orbit(Path((source, sv, epoch_input)): Path<(String, String, Option<f64>)>) -> impl IntoResponse  {
+#[utoipa::path(get, path = "/orbit/combine/{source}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin")),
+    responses((status = 200, body = CombinationReport), ErrorResponses))]
+async fn get_combination_report(Path(source): Path<String>) -> Result<Response, ApiError> {
+
+    let data_source = data_source(&source)?;
+
+    let report = call_handler::<CombinationReport>(&reqwest::Client::new(),
+        format!("OrbitCombination/{}/getReport", data_source.get_key()),
+        String::new()).await?;
+
+    Ok((CORS_HEADERS, Json(report)).into_response())
+}
+
+#[utoipa::path(post, path = "/ephemeris/{source}/{sv}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), TimeQuery),
+    request_body = EphemerisConfig,
+    responses((status = 200, body = Vec<EphemerisFit>), ErrorResponses))]
+async fn fit_broadcast_ephemerides(Path((source, sv)): Path<(String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>, Json(config): Json<EphemerisConfig>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
 
-    let epoch:f64;
-    if epoch_input.is_none() {
-        epoch = Epoch::now().unwrap().to_gpst_seconds();
+    let fits = call_handler::<Vec<EphemerisFit>>(&reqwest::Client::new(),
+        format!("BroadcastEphemerides/{}/fit", sv_source.get_key()),
+        serde_json::to_string(&config).unwrap()).await?;
+
+    json_response(&fits, &time_query)
+}
+
+#[utoipa::path(get, path = "/ephemeris/{source}/{sv}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), TimeQuery),
+    responses((status = 200, body = Vec<EphemerisFit>), ErrorResponses))]
+async fn get_broadcast_ephemerides(Path((source, sv)): Path<(String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+
+    let fits = call_handler::<Vec<EphemerisFit>>(&reqwest::Client::new(),
+        format!("BroadcastEphemerides/{}/getFits", sv_source.get_key()),
+        String::new()).await?;
+
+    json_response(&fits, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema, Debug)]
+struct SsrApiRequest {
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[schema(value_type = String)]
+    from:f64,
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[schema(value_type = String)]
+    to:f64,
+    // navigation data source key of the broadcast ephemerides, e.g. cddis_igs_brdc
+    broadcast:Option<String>,
+    #[serde(default)]
+    config:SsrConfig,
+    // provider, solution and IOD SSR written to RTCM output
+    #[serde(default)]
+    stream:SsrStream,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct SsrQuery {
+    // json or rtcm
+    format:Option<String>,
+}
+
+#[utoipa::path(post, path = "/ssr/{source}/{sv}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), SsrQuery, TimeQuery),
+    request_body = SsrApiRequest,
+    responses((status = 200, description = "JSON corrections, or RTCM frames with format=rtcm", body = SsrCorrections), ErrorResponses))]
+async fn get_ssr_corrections(Path((source, sv)): Path<(String, String)>, ApiQuery(ssr_query): ApiQuery<SsrQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>, Json(payload): Json<SsrApiRequest>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+
+    let broadcast = payload.broadcast.as_deref()
+        .ok_or_else(|| ApiError::InvalidRequest("Missing broadcast source, e.g. cddis_igs_brdc".to_string()))?;
+    let broadcast_source = data_source(broadcast)?;
+
+    let request = SsrRequest { from: payload.from, to: payload.to, broadcast_source, config: payload.config.clone() };
+
+    let corrections = call_handler::<SsrCorrections>(&reqwest::Client::new(),
+        format!("SsrGenerator/{}/getCorrections", sv_source.get_key()),
+        serde_json::to_string(&request).unwrap()).await?;
+
+    match ssr_query.format.as_deref().unwrap_or("json") {
+        "json" => json_response(&corrections, &time_query),
+        "rtcm" => {
+            let mut frames = encode_orbit_corrections(&corrections.orbit, payload.config.orbit_interval_s, &payload.stream)
+                .map_err(|err| ApiError::Unprocessable(err.to_string()))?;
+            frames.extend(encode_clock_corrections(&corrections.clock, payload.config.clock_interval_s, &payload.stream)
+                .map_err(|err| ApiError::Unprocessable(err.to_string()))?);
+
+            Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/octet-stream")], frames.concat()).into_response())
+        }
+        format => Err(ApiError::unsupported_format(format)),
     }
-    else {
-        epoch = epoch_input.unwrap();
+}
+
+#[utoipa::path(post, path = "/tle/{source}/{sv}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), TimeQuery),
+    request_body = TleConfig,
+    responses((status = 200, body = TleFit), ErrorResponses))]
+async fn fit_mean_elements(Path((source, sv)): Path<(String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>, Json(config): Json<TleConfig>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+
+    let fit = call_handler::<TleFit>(&reqwest::Client::new(),
+        format!("MeanElementSets/{}/fit", sv_source.get_key()),
+        serde_json::to_string(&config).unwrap()).await?;
+
+    json_response(&fit, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct TleQuery {
+    // json, tle or omm
+    format:Option<String>,
+}
+
+#[utoipa::path(get, path = "/tle/{source}/{sv}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), TleQuery, TimeQuery),
+    responses((status = 200, description = "JSON fit, or a TLE or OMM as text", body = TleFit), ErrorResponses))]
+async fn get_mean_elements(Path((source, sv)): Path<(String, String)>, ApiQuery(tle_query): ApiQuery<TleQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+
+    let fit = call_handler::<TleFit>(&reqwest::Client::new(),
+        format!("MeanElementSets/{}/getFit", sv_source.get_key()),
+        String::new()).await?;
+
+    match tle_query.format.as_deref().unwrap_or("json") {
+        "json" => json_response(&fit, &time_query),
+        "tle" => {
+            let [line1, line2] = fit.elements.tle(&fit.identity)
+                .map_err(|err| ApiError::Unprocessable(err.to_string()))?;
+            Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "text/plain")], format!("{}\n{}\n{}\n", fit.identity.name, line1, line2)).into_response())
+        }
+        "omm" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "text/plain")], fit.elements.omm(&fit.identity, &now()?)).into_response()),
+        format => Err(ApiError::unsupported_format(format)),
     }
+}
 
-    let sv_source = SVSource {satellite:sv.to_uppercase(), data_source:DataSource::from_key(source).unwrap()};
+#[utoipa::path(post, path = "/tle/{source}/{sv}/compare", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), TimeQuery),
+    request_body = Sgp4ComparisonRequest,
+    responses((status = 200, body = Sgp4Comparison), ErrorResponses))]
+async fn compare_tle(Path((source, sv)): Path<(String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>, Json(request): Json<Sgp4ComparisonRequest>) -> Result<Response, ApiError> {
 
-    let response = reqwest::Client::new()
-        .post(format!("{}/SVOrbits/{}/getOrbitPosition", INGRESS_URL, sv_source.get_key()))
-        .header("Accept", "application/json")
-        .header("Content-Type", "*/*")
-        .body(epoch.to_string())
-        .send()
-        .await;
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+
+    let comparison = call_handler::<Sgp4Comparison>(&reqwest::Client::new(),
+        format!("MeanElementSets/{}/compare", sv_source.get_key()),
+        serde_json::to_string(&request).unwrap()).await?;
+
+    json_response(&comparison, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema, Debug)]
+struct SisreApiRequest {
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[schema(value_type = String)]
+    from:f64,
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[schema(value_type = String)]
+    to:f64,
+    // navigation data source key of the broadcast ephemerides, e.g. cddis_igs_brdc
+    broadcast:Option<String>,
+    #[serde(default)]
+    config:SisreConfig,
+}
+
+#[utoipa::path(post, path = "/sisre/{source}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), TimeQuery),
+    request_body = SisreApiRequest,
+    responses((status = 200, body = SisreMonitoring), ErrorResponses))]
+async fn monitor_sisre(Path(source): Path<String>, ApiQuery(time_query): ApiQuery<TimeQuery>, Json(payload): Json<SisreApiRequest>) -> Result<Response, ApiError> {
+
+    let broadcast = payload.broadcast.as_deref()
+        .ok_or_else(|| ApiError::InvalidRequest("Missing broadcast source, e.g. cddis_igs_brdc".to_string()))?;
+    let broadcast_source = data_source(broadcast)?;
+    let data_source = data_source(&source)?;
+
+    let request = SisreRequest { from: payload.from, to: payload.to, broadcast_source, config: payload.config };
+
+    let monitoring = call_handler::<SisreMonitoring>(&reqwest::Client::new(),
+        format!("SisreMonitor/{}/monitor", data_source.get_key()),
+        serde_json::to_string(&request).unwrap()).await?;
+
+    json_response(&monitoring, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct SisreQuery {
+    // json or arrow
+    format:Option<String>,
+    // samples or statistics for arrow output
+    table:Option<String>,
+}
+
+#[utoipa::path(get, path = "/sisre/{source}", tag = "products",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), SisreQuery, TimeQuery),
+    responses((status = 200, description = "JSON monitoring, or an Arrow IPC stream with format=arrow", body = SisreMonitoring), ErrorResponses))]
+async fn get_sisre(Path(source): Path<String>, ApiQuery(sisre_query): ApiQuery<SisreQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
 
-    if response.is_ok() && response.as_ref().unwrap().status().is_success() {
-        let orbit:Orbit = serde_json::from_str(response.unwrap().text().await.unwrap().as_str()).unwrap();
+    let data_source = data_source(&source)?;
 
-        return (
-            [
-                ("Access-Control-Allow-Origin", "*"),
-                ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
-                ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
-            ],
-            Json(orbit)
-        ).into_response();
+    let monitoring = call_handler::<SisreMonitoring>(&reqwest::Client::new(),
+        format!("SisreMonitor/{}/getMonitoring", data_source.get_key()),
+        String::new()).await?;
+
+    match sisre_query.format.as_deref().unwrap_or("json") {
+        "json" => json_response(&monitoring, &time_query),
+        "arrow" => {
+            let batch = match sisre_query.table.as_deref().unwrap_or("samples") {
+                "samples" => monitoring.samples_record_batch(),
+                "statistics" => monitoring.statistics_record_batch(),
+                table => return Err(ApiError::InvalidRequest(format!("Unsupported table {}", table))),
+            };
+
+            let bytes = batch.and_then(|batch| arrow_ipc_bytes(&batch))
+                .map_err(|err| ApiError::Internal(err.to_string()))?;
+
+            Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")], bytes).into_response())
+        }
+        format => Err(ApiError::unsupported_format(format)),
     }
+}
 
-    (
-        StatusCode::NOT_FOUND,
-        [
-            ("Access-Control-Allow-Origin", "*"),
-            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
-            ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
-        ],
-        format!("SV {} not found", sv)
-    ).into_response()
+#[utoipa::path(get, path = "/orbit/sources", tag = "orbits",
+    responses((status = 200, description = "Data sources by key", body = HashMap<String, DataSource>), ErrorResponses))]
+async fn get_sources() -> Result<Response, ApiError> {
+
+    let sources = call_handler::<HashMap<String, DataSource>>(&reqwest::Client::new(),
+        "DataSources/orbits/getSources".to_string(),
+        String::new()).await?;
+
+    Ok((CORS_HEADERS, Json(sources)).into_response())
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct OrbitQuery {
+    // comma separated data source keys overriding ORBIT_SOURCE_CHAIN for the best source
+    chain:Option<String>,
+    // json, geojson or kml
+    format:Option<String>,
+}
+
+#[utoipa::path(get, path = "/orbit/{source}/{sv}/{epoch}", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), ("sv" = String, Path, description = "Satellite, e.g. G05"), ("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), OrbitQuery, TimeQuery),
+    responses((status = 200, description = "Orbit window around the epoch, or GeoJSON/KML with format", body = Orbit), ErrorResponses))]
+async fn get_orbit(Path((source, sv, epoch)): Path<(String, String, String)>, ApiQuery(orbit_query): ApiQuery<OrbitQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let chain = source_chain(&source, orbit_query.chain.as_deref())?;
+    let satellite = satellite(&sv)?;
+    let epoch = gpst_seconds(&epoch)?;
+
+    let orbit = fetch_position(&reqwest::Client::new(), &chain, satellite, epoch).await?;
+
+    match orbit_query.format.as_deref().unwrap_or("json") {
+        "json" => json_response(&orbit, &time_query),
+        format => positions_response(&[orbit], epoch, format),
+    }
+}
+
+#[utoipa::path(get, path = "/orbits/{source}/{epoch}", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), ("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), OrbitQuery, TimeQuery),
+    responses((status = 200, description = "Orbit windows of every satellite, or GeoJSON/KML with format", body = Vec<Orbit>), ErrorResponses))]
+async fn get_orbits(Path((source, epoch)): Path<(String, String)>, ApiQuery(orbit_query): ApiQuery<OrbitQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let chain = source_chain(&source, orbit_query.chain.as_deref())?;
+    let epoch = gpst_seconds(&epoch)?;
+
+    let orbits = chain_positions(&reqwest::Client::new(), &chain, epoch).await?;
+
+    info!("got {} orbits from {}", orbits.len(), source);
+
+    match orbit_query.format.as_deref().unwrap_or("json") {
+        "json" => json_response(&orbits, &time_query),
+        format => positions_response(&orbits, epoch, format),
+    }
+}
+
+// positions of every satellite at the epoch from one batched call per source of the chain, merged to
+// the best position per satellite
+async fn chain_positions(client:&reqwest::Client, chain:&SourceChain, epoch:f64) -> Result<Vec<Orbit>, ApiError> {
+
+    let mut positions:Vec<Vec<Orbit>> = Vec::new();
+    let mut error:Option<ApiError> = None;
+
+    for data_source in &chain.sources {
+        let source_positions = match cached_source_orbits(client, data_source).await {
+            Ok(orbits) => local_positions(orbits, epoch).await
+                .map(|positions| positions.into_iter().filter_map(Result::ok).collect()),
+            // products stored before the per source product runs
+            Err(ApiError::NotFound(_)) => call_handler::<Vec<Orbit>>(client,
+                format!("SVOrbits/{}/getOrbitPositions", data_source.get_key()),
+                epoch.to_string()).await,
+            Err(err) => Err(err),
+        };
+
+        match source_positions {
+            Ok(source_positions) => positions.push(source_positions),
+            Err(err @ ApiError::BackendUnavailable(_)) => return Err(err),
+            // the other sources of the chain may still answer
+            Err(err) => error = Some(unknown_source(err, data_source)),
+        }
+    }
+
+    if let Some(error) = error && positions.is_empty() {
+        return Err(error);
+    }
+
+    Ok(best_positions(positions))
+}
+
+// sub-satellite points of orbit position responses as GeoJSON or KML
+fn positions_response(orbits:&[Orbit], epoch:f64, format:&str) -> Result<Response, ApiError> {
+
+    let positions:Vec<SatellitePosition> = orbits.iter().filter_map(|orbit| SatellitePosition::from_orbit(orbit, epoch)).collect();
+
+    match format {
+        "geojson" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/geo+json")], positions_geojson(&positions).to_string()).into_response()),
+        "kml" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/vnd.google-earth.kml+xml")], positions_kml(&positions)).into_response()),
+        format => Err(ApiError::unsupported_format(format)),
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct OrbitRangeQuery {
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[param(value_type = String)]
+    from:f64,
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[param(value_type = String)]
+    to:f64,
+    // resampling step in seconds, the product samples when not set
+    step:Option<f64>,
+    // json, oem, oem-xml, czml, or geojson and kml ground tracks
+    format:Option<String>,
+    chain:Option<String>,
+    // comma separated satellites for the all satellite range, all when not set
+    satellites:Option<String>,
+}
+
+impl OrbitRangeQuery {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.to < self.from || self.step.is_some_and(|step| step <= 0.0) {
+            return Err(ApiError::InvalidRequest("Invalid orbit range".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[utoipa::path(get, path = "/orbit/{source}/{sv}/range", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), ("sv" = String, Path, description = "Satellite, e.g. G05"), OrbitRangeQuery, TimeQuery),
+    responses((status = 200, description = "States as JSON, or OEM, CZML, GeoJSON or KML with format", body = Vec<EphemerisSegment>), ErrorResponses))]
+async fn get_orbit_range(Path((source, sv)): Path<(String, String)>, ApiQuery(range_query): ApiQuery<OrbitRangeQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let chain = source_chain(&source, range_query.chain.as_deref())?;
+    let satellite = satellite(&sv)?;
+    range_query.validate()?;
+
+    let orbit = fetch_orbit_range(&reqwest::Client::new(), &chain, satellite, range_query.from, range_query.to).await?;
+
+    let segment = EphemerisSegment::from_orbit(&orbit, range_query.from, range_query.to, range_query.step)
+        .map_err(|err| ApiError::from_anyhow(err, ApiError::Unprocessable))?;
+
+    ephemeris_response(&[segment], range_query.format.as_deref(), &time_query)
+}
 
+#[utoipa::path(get, path = "/orbits/{source}/range", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), OrbitRangeQuery, TimeQuery),
+    responses((status = 200, description = "States as JSON, or OEM, CZML, GeoJSON or KML with format", body = Vec<EphemerisSegment>), ErrorResponses))]
+async fn get_orbits_range(Path(source): Path<String>, ApiQuery(range_query): ApiQuery<OrbitRangeQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let chain = source_chain(&source, range_query.chain.as_deref())?;
+    range_query.validate()?;
+
+    let client = reqwest::Client::new();
+
+    let satellites:Vec<SatelliteId> = match range_query.satellites.as_deref() {
+        Some(list) => list.split(',')
+            .map(|sv| satellite(sv.trim()))
+            .collect::<Result<_, _>>()?,
+        None => {
+            let mut satellites:Vec<SatelliteId> = Vec::new();
+            let mut error:Option<ApiError> = None;
+
+            for data_source in &chain.sources {
+                let source_satellites = call_handler::<Vec<SatelliteId>>(&client,
+                    format!("SVOrbits/{}/getSatellites", data_source.get_key()),
+                    String::new()).await;
+
+                match source_satellites {
+                    Ok(source_satellites) => {
+                        for satellite in source_satellites {
+                            if !satellites.contains(&satellite) {
+                                satellites.push(satellite);
+                            }
+                        }
+                    }
+                    Err(err @ ApiError::BackendUnavailable(_)) => return Err(err),
+                    Err(err) => error = Some(unknown_source(err, data_source)),
+                }
+            }
+
+            if let Some(error) = error && satellites.is_empty() {
+                return Err(error);
+            }
+            satellites
+        }
+    };
+
+    let mut segments:Vec<EphemerisSegment> = Vec::new();
+
+    for satellite in satellites {
+        let orbit = match fetch_orbit_range(&client, &chain, satellite, range_query.from, range_query.to).await {
+            Ok(orbit) => orbit,
+            Err(err @ ApiError::BackendUnavailable(_)) => return Err(err),
+            // satellites without an orbit over the range are left out
+            Err(_) => continue,
+        };
+
+        let segment = EphemerisSegment::from_orbit(&orbit, range_query.from, range_query.to, range_query.step)
+            .map_err(|err| ApiError::from_anyhow(err, ApiError::Unprocessable))?;
+
+        if !segment.states.is_empty() {
+            segments.push(segment);
+        }
+    }
+
+    ephemeris_response(&segments, range_query.format.as_deref(), &time_query)
 }
 
-async fn get_orbits(Path((source, epoch)): Path<(String, f64)>) -> impl IntoResponse  {
+// orbit range segments as JSON states, CCSDS OEM (KVN or XML), CZML or GeoJSON/KML ground tracks
+fn ephemeris_response(segments:&[EphemerisSegment], format:Option<&str>, time_query:&TimeQuery) -> Result<Response, ApiError> {
+
+    match format.unwrap_or("json") {
+        "json" => json_response(&segments, time_query),
+        "oem" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "text/plain")], oem_kvn(segments, &now()?)).into_response()),
+        "oem-xml" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/xml")], oem_xml(segments, &now()?)).into_response()),
+        "czml" => Ok((CORS_HEADERS, Json(czml(segments))).into_response()),
+        "geojson" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/geo+json")], ground_tracks_geojson(segments).to_string()).into_response()),
+        "kml" => Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/vnd.google-earth.kml+xml")], ground_tracks_kml(segments)).into_response()),
+        format => Err(ApiError::unsupported_format(format)),
+    }
+}
+
+// keeps the error that tells the most about a chain lookup: a source holding the satellite outside
+// the requested epochs over one without it, a failing backend over both
+fn chain_error(current:Option<ApiError>, err:ApiError) -> Option<ApiError> {
+    match (&current, &err) {
+        (Some(ApiError::BackendUnavailable(_)), _) => current,
+        (Some(ApiError::OutsideCoverage(_)), ApiError::NotFound(_)) => current,
+        _ => Some(err),
+    }
+}
+
+// Stored orbit from the first source in the chain overlapping [from, to]
+async fn fetch_orbit_range(client:&reqwest::Client, chain:&SourceChain, satellite:SatelliteId, from:f64, to:f64) -> Result<Orbit, ApiError> {
+
+    let mut error:Option<ApiError> = None;
+
+    for data_source in &chain.sources {
+        let sv_source = SVSource {satellite, data_source: data_source.clone()};
+
+        match cached_orbit(client, &sv_source).await {
+            Ok(orbit) if orbit.valid_from <= to && orbit.valid_to >= from => return Ok((*orbit).clone()),
+            Ok(orbit) => error = chain_error(error, ApiError::OutsideCoverage(
+                format!("Orbit of {} covers {} to {}", sv_source.get_key(), orbit.valid_from, orbit.valid_to))),
+            Err(err) => error = chain_error(error, err),
+        }
+    }
+
+    Err(error.unwrap_or_else(|| ApiError::NotFound(format!("No orbit for {}", satellite))))
+}
+
+// Position from the first source in the chain covering the satellite at the epoch. When no source
+// covers it, the propagated position from the most recent product is used. The returned orbit's
+// sv.data_source reports which source answered.
+async fn fetch_position(client:&reqwest::Client, chain:&SourceChain, satellite:SatelliteId, epoch:f64) -> Result<Orbit, ApiError> {
+
+    let mut extrapolated:Option<Orbit> = None;
+    let mut error:Option<ApiError> = None;
+
+    for data_source in &chain.sources {
+
+        let sv_source = SVSource {satellite, data_source: data_source.clone()};
+
+        let orbit = match cached_orbit(client, &sv_source).await {
+            Ok(orbit) => local_positions(vec![orbit], epoch).await
+                .and_then(|mut positions| positions.remove(0)),
+            Err(err) => Err(err),
+        };
+
+        match orbit {
+            Ok(orbit) if !orbit.extrapolated => return Ok(orbit),
+            Ok(orbit) => {
+                if extrapolated.as_ref().is_none_or(|current| orbit.valid_to > current.valid_to) {
+                    extrapolated = Some(orbit);
+                }
+            }
+            Err(err) => error = chain_error(error, err),
+        }
+    }
+
+    extrapolated.ok_or_else(|| error.unwrap_or_else(|| ApiError::NotFound(format!("No orbit for {}", satellite))))
+}
+
+// Orbit of a satellite source from the in-process cache. After the TTL the cached copy is revalidated
+// against the product run stored in Restate and fetched again when the run changed.
+async fn cached_orbit(client:&reqwest::Client, sv_source:&SVSource) -> Result<Arc<Orbit>, ApiError> {
+
+    let cache = OrbitCache::global();
+    let key = sv_source.get_key();
+
+    if let Some(orbit) = cache.get(sv_source) {
+
+        if cache.is_fresh(&key) {
+            return Ok(orbit);
+        }
+
+        let product_run_id = call_handler::<u64>(client,
+            format!("SVOrbits/{}/getProductRunId", key),
+            String::new()).await;
+
+        if product_run_id.is_ok_and(|product_run_id| product_run_id == orbit.product_run_id) {
+            cache.mark_checked(&key);
+            return Ok(orbit);
+        }
+    }
+
+    let orbit = call_handler::<Orbit>(client,
+        format!("SVOrbits/{}/getOrbit", key),
+        String::new()).await?;
 
-    let response = reqwest::Client::new()
-        .post(format!("{}/SVOrbits/{}/getSatellites", INGRESS_URL, source))
+    let orbit = with_metadata(client, vec![orbit]).await.remove(0);
+
+    Ok(cache.insert(orbit))
+}
+
+// Orbits of every satellite in a data source from the in-process cache. After the TTL they are
+// revalidated with one call for the product runs and one for the orbits whose run changed.
+async fn cached_source_orbits(client:&reqwest::Client, data_source:&DataSource) -> Result<Vec<Arc<Orbit>>, ApiError> {
+
+    let cache = OrbitCache::global();
+    let key = data_source.get_key();
+
+    if !cache.is_fresh(&key) {
+
+        let product_runs = call_handler::<Vec<(SatelliteId, u64)>>(client,
+            format!("SVOrbits/{}/getProductRuns", key),
+            String::new()).await?;
+
+        let stale = cache.stale_satellites(data_source, &product_runs);
+
+        if !stale.is_empty() {
+            let orbits = call_handler::<Vec<Orbit>>(client,
+                format!("SVOrbits/{}/getOrbits", key),
+                serde_json::to_string(&stale).unwrap()).await?;
+
+            info!("cached {} orbits of {}", orbits.len(), key);
+
+            for orbit in with_metadata(client, orbits).await {
+                cache.insert(orbit);
+            }
+        }
+
+        cache.mark_checked(&key);
+    }
+
+    Ok(cache.source_orbits(data_source))
+}
+
+// catalog entries at the middle of each orbit, a PRN does not change satellite within a product
+async fn with_metadata(client:&reqwest::Client, mut orbits:Vec<Orbit>) -> Vec<Orbit> {
+
+    let queries:Vec<SatelliteQuery> = orbits.iter()
+        .map(|orbit| SatelliteQuery { sv: orbit.sv.satellite.to_string(), epoch: (orbit.valid_from + orbit.valid_to) / 2.0 })
+        .collect();
+
+    let metadata = call_handler::<Vec<Option<SatelliteInfo>>>(client,
+        format!("SatelliteMetadata/{}/getSatellites", CATALOG_KEY),
+        serde_json::to_string(&queries).unwrap()).await;
+
+    if let Ok(metadata) = metadata {
+        for (orbit, info) in orbits.iter_mut().zip(metadata) {
+            orbit.metadata = info;
+        }
+    }
+
+    orbits
+}
+
+// positions interpolated in process from cached orbits, as getOrbitPositions does in Restate, with
+// the error kind of each orbit that does not cover the epoch
+async fn local_positions(orbits:Vec<Arc<Orbit>>, epoch:f64) -> Result<Vec<Result<Orbit, ApiError>>, ApiError> {
+
+    let frame = KernelManager::global().earth_frame()?;
+    let positions = tokio::task::spawn_blocking(move || orbit_positions(&orbits, epoch)).await
+        .map_err(|err| ApiError::Internal(err.to_string()))?;
+
+    Ok(positions.into_iter()
+        .map(|position| {
+            let mut position = position.map_err(|err| ApiError::from_anyhow(err, ApiError::Unprocessable))?;
+            set_latlonalt(&mut position, frame);
+            Ok(position)
+        })
+        .collect())
+}
+
+// POSTs to a Restate handler through the ingress and decodes the JSON response. Terminal errors of the
+// handler come back with the status of their ApiError kind, an unreachable ingress as unavailable.
+async fn call_handler<T:DeserializeOwned>(client:&reqwest::Client, handler_path:String, body:String) -> Result<T, ApiError> {
+
+    let response = client
+        .post(format!("{}/{}", INGRESS_URL, handler_path))
         .header("Accept", "application/json")
         .header("Content-Type", "*/*")
+        .body(body)
         .send()
-        .await.unwrap();
+        .await
+        .map_err(|err| ApiError::BackendUnavailable(format!("Restate ingress unavailable: {}", err)))?;
+
+    let status = response.status();
+
+    if !status.is_success() {
+        // the ingress answers with a JSON body holding the terminal error message
+        let text = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&text).ok()
+            .and_then(|body| body.get("message").and_then(Value::as_str).map(str::to_string))
+            .unwrap_or(text);
+
+        let message = match message.is_empty() {
+            true => format!("{} returned {}", handler_path, status),
+            false => message,
+        };
+
+        return Err(ApiError::from_status(status.as_u16(), message));
+    }
+
+    response.json().await
+        .map_err(|err| ApiError::Internal(format!("Invalid response from {}: {}", handler_path, err)))
+}
+
+// epoch path segment or query value as GPST seconds, see gpst::parse_epoch for the formats
+fn gpst_seconds(input:&str) -> Result<f64, ApiError> {
+    parse_epoch(input)
+        .map(|epoch| epoch.to_gpst_seconds())
+        .map_err(|err| ApiError::InvalidEpoch(format!("Invalid epoch {}: {}", input, err)))
+}
+
+// current epoch from the system clock
+fn now() -> Result<Epoch, ApiError> {
+    Epoch::now().map_err(|err| ApiError::Internal(format!("Unable to read the system clock: {}", err)))
+}
+
+// epoch of a query value or a request body field, a JSON number is taken as GPST seconds
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum EpochInput {
+    Seconds(f64),
+    Text(String),
+}
+
+fn deserialize_epoch<'de, D:Deserializer<'de>>(deserializer:D) -> Result<f64, D::Error> {
+    match EpochInput::deserialize(deserializer)? {
+        EpochInput::Seconds(seconds) => Ok(seconds),
+        EpochInput::Text(input) => gpst_seconds(&input).map_err(serde::de::Error::custom),
+    }
+}
+
+fn deserialize_optional_epoch<'de, D:Deserializer<'de>>(deserializer:D) -> Result<Option<f64>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|input| gpst_seconds(&input))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct TimeQuery {
+    // time system of the response epochs (GPS, GAL, BDT, GLO, QZS, TAI or UTC) written as ISO 8601
+    // strings, GPST seconds when not set
+    time:Option<String>,
+}
+
+// JSON response with epochs in the time system requested by ?time=
+fn json_response<T:serde::Serialize>(value:&T, time_query:&TimeQuery) -> Result<Response, ApiError> {
+
+    let system = time_query.time.as_deref()
+        .map(str::parse::<TimeSystem>)
+        .transpose()
+        .map_err(|err| ApiError::InvalidRequest(err.to_string()))?;
 
-    let satellites:Vec<String> = response.json().await.unwrap();
+    Ok((CORS_HEADERS, Json(InTimeSystem(value, system))).into_response())
+}
+
+#[utoipa::path(get, path = "/state/{source}/{sv}/{epoch}", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("sv" = String, Path, description = "Satellite, e.g. G05"), ("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), TimeQuery),
+    responses((status = 200, body = SatelliteState), ErrorResponses))]
+async fn get_satellite_state(Path((source, sv, epoch)): Path<(String, String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let sv_source = SVSource {satellite: satellite(&sv)?, data_source: data_source(&source)?};
+    let epoch = gpst_seconds(&epoch)?;
+
+    let state = call_handler::<SatelliteState>(&reqwest::Client::new(),
+        format!("SVOrbits/{}/getSatelliteState", sv_source.get_key()),
+        epoch.to_string()).await?;
+
+    json_response(&state, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct SatelliteMetadataQuery {
+    // defaults to now
+    #[serde(default, deserialize_with = "deserialize_optional_epoch")]
+    #[param(value_type = Option<String>)]
+    epoch:Option<f64>,
+}
+
+#[utoipa::path(get, path = "/satellites/{sv}", tag = "catalog",
+    params(("sv" = String, Path, description = "PRN (G04) or SVN (G074)"), SatelliteMetadataQuery, TimeQuery),
+    responses((status = 200, body = SatelliteInfo), ErrorResponses))]
+async fn get_satellite_metadata(Path(sv): Path<String>, ApiQuery(query): ApiQuery<SatelliteMetadataQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    // PRN or SVN
+    let sv = match sv.parse::<SatelliteId>() {
+        Ok(prn) => prn.to_string(),
+        Err(_) if is_svn(&sv) => sv.to_uppercase(),
+        Err(_) => return Err(ApiError::invalid_satellite(&sv)),
+    };
+
+    let epoch = match query.epoch {
+        Some(epoch) => epoch,
+        None => now()?.to_gpst_seconds(),
+    };
+    let satellite_query = SatelliteQuery {sv, epoch};
+
+    let info = call_handler::<SatelliteInfo>(&reqwest::Client::new(),
+        format!("SatelliteMetadata/{}/getSatellite", CATALOG_KEY),
+        serde_json::to_string(&satellite_query).unwrap()).await?;
+
+    json_response(&info, &time_query)
+}
+
+#[utoipa::path(get, path = "/states/{source}/{epoch}", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), TimeQuery),
+    responses((status = 200, body = Vec<SatelliteState>), ErrorResponses))]
+async fn get_satellite_states(Path((source, epoch)): Path<(String, String)>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let data_source = data_source(&source)?;
+    let epoch = gpst_seconds(&epoch)?;
+
+    // computed in process from the source orbits with one Sun and Moon position for all satellites
+    let orbits = fetch_orbits(&data_source).await?;
+    let (sun, moon) = KernelManager::global().planetary_almanac()
+        .and_then(|almanac| sun_moon_ecef_km(&almanac, epoch))
+        .map_err(|err| ApiError::from_anyhow(err, ApiError::Unprocessable))?;
+
+    let mut states:Vec<SatelliteState> = Vec::new();
+
+    for state in satellite_states(&orbits, epoch, &sun, &moon) {
+        match state.map_err(|err| ApiError::from_anyhow(err, ApiError::Unprocessable)) {
+            Ok(state) => states.push(state),
+            // satellites not covering the epoch are left out
+            Err(ApiError::OutsideCoverage(_)) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    json_response(&states, &time_query)
+}
+
+// Loads the full orbit state of every satellite in a data source
+async fn fetch_orbits(data_source:&DataSource) -> Result<Vec<Orbit>, ApiError> {
+
+    let client = reqwest::Client::new();
+
+    match cached_source_orbits(&client, data_source).await {
+        Ok(orbits) => return Ok(orbits.iter().map(|orbit| (**orbit).clone()).collect()),
+        // products stored before the per source product runs
+        Err(ApiError::NotFound(_)) => {}
+        Err(err) => return Err(err),
+    }
+
+    let satellites:Vec<SatelliteId> = call_handler(&client,
+        format!("SVOrbits/{}/getSatellites", data_source.get_key()),
+        String::new()).await
+        .map_err(|err| unknown_source(err, data_source))?;
+
+    // one request per satellite, all in flight at once
+    let calls:Vec<_> = satellites.into_iter()
+        .map(|satellite| {
+            let client = client.clone();
+            let sv_source = SVSource {satellite, data_source: data_source.clone()};
+            tokio::spawn(async move {
+                call_handler::<Orbit>(&client, format!("SVOrbits/{}/getOrbit", sv_source.get_key()), String::new()).await
+            })
+        })
+        .collect();
 
     let mut orbits:Vec<Orbit> = Vec::new();
 
-    let data_source:DataSource = DataSource::from_key(source).unwrap();
+    for call in calls {
+        let orbit = call.await.map_err(|err| ApiError::Internal(err.to_string()))?;
+        match orbit {
+            Ok(orbit) => orbits.push(orbit),
+            Err(err @ ApiError::BackendUnavailable(_)) => return Err(err),
+            Err(_) => {}
+        }
+    }
 
-    info!("getting orbits for: {:?}", satellites);
+    Ok(orbits)
+}
 
-    for satellite in satellites {
+// Loads orbits from several data sources, the first source listed wins for satellites present in more than one
+async fn fetch_orbits_from_sources(data_sources:&[DataSource]) -> Result<Vec<Orbit>, ApiError> {
 
-        let sv_source = SVSource {satellite, data_source: data_source.clone()};
+    let mut orbits:Vec<Orbit> = Vec::new();
 
-        let response = reqwest::Client::new()
-            .post(format!("{}/SVOrbits/{}/getOrbitPosition", INGRESS_URL, sv_source.get_key()))
-            .header("Accept", "application/json")
-            .header("Content-Type", "*/*")
-            .body(epoch.to_string())
-            .send()
-            .await;
+    for data_source in data_sources {
+        for orbit in fetch_orbits(data_source).await? {
+            if !orbits.iter().any(|o| o.sv.satellite == orbit.sv.satellite) {
+                orbits.push(orbit);
+            }
+        }
+    }
 
-        if response.is_ok() && response.as_ref().unwrap().status().is_success() {
-            let orbit:Orbit = serde_json::from_str(response.unwrap().text().await.unwrap().as_str()).unwrap();
+    Ok(orbits)
+}
 
-            orbits.push(orbit);
+// parses a comma separated constellation selection such as "G,E" or "gps,galileo"
+fn parse_constellations(list:Option<&str>) -> Result<Vec<Constellation>, ApiError> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(|c| c.parse::<Constellation>().map_err(|_| ApiError::InvalidRequest(format!("Invalid constellation {}", c))))
+        .collect()
+}
+
+// comma separated data source keys in priority order, or the best source chain
+fn parse_sources(sources:&str) -> Result<Vec<DataSource>, ApiError> {
+    let chain = match sources {
+        BEST_SOURCE => SourceChain::from_env(),
+        sources => SourceChain::parse(sources),
+    };
+    chain.map(|chain| chain.sources).map_err(|_| ApiError::invalid_source(sources))
+}
+
+// serializes a record batch as an Arrow IPC stream
+fn arrow_ipc_bytes(record_batch:&RecordBatch) -> Result<Vec<u8>, ArrowError> {
+    let mut writer = StreamWriter::try_new(Vec::new(), &record_batch.schema())?;
+    writer.write(record_batch)?;
+    writer.finish()?;
+    writer.into_inner()
+}
+
+// observer location as either lat/lon (degrees) and alt (meters) or ECEF x/y/z (meters)
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct ObserverQuery {
+    lat:Option<f64>,
+    lon:Option<f64>,
+    alt:Option<f64>,
+    x:Option<f64>,
+    y:Option<f64>,
+    z:Option<f64>,
+}
+
+impl ObserverQuery {
+    fn observer(&self) -> Result<Observer, ApiError> {
+        match (self.lat, self.lon, self.x, self.y, self.z) {
+            (Some(lat_deg), Some(lon_deg), None, None, None) => Ok(Observer::Geodetic { lat_deg, lon_deg, alt_m: self.alt.unwrap_or(0.0) }),
+            (None, None, Some(x_m), Some(y_m), Some(z_m)) => Ok(Observer::Ecef { x_m, y_m, z_m }),
+            _ => Err(ApiError::InvalidRequest("Observer requires lat/lon[/alt] or x/y/z".to_string())),
         }
     }
+}
 
-    (
-        [
-            ("Access-Control-Allow-Origin", "*"),
-            ("Access-Control-Allow-Methods", "GET, POST, OPTIONS"),
-            ("Access-Control-Allow-Headers", "Content-Type, Authorization"),
-        ],
-        Json(orbits)
-    ).into_response()
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct LookQuery {
+    // minimum elevation in degrees, all satellites are returned when not set
+    mask:Option<f64>,
+}
+
+#[utoipa::path(get, path = "/look/{source}/{epoch}", tag = "observers",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), ObserverQuery, LookQuery, TimeQuery),
+    responses((status = 200, body = Vec<LookAngles>), ErrorResponses))]
+async fn get_look_angles(Path((source, epoch)): Path<(String, String)>, ApiQuery(observer): ApiQuery<ObserverQuery>, ApiQuery(look): ApiQuery<LookQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let observer = observer.observer()?;
+    let epoch = gpst_seconds(&epoch)?;
+    let data_source = data_source(&source)?;
+
+    let orbits = fetch_orbits(&data_source).await?;
+
+    let mut look_angles = look_angles_all(&observer, &orbits, epoch);
+
+    if let Some(mask) = look.mask {
+        look_angles.retain(|l| l.elevation_deg >= mask);
+    }
+
+    look_angles.sort_by(|a, b| b.elevation_deg.total_cmp(&a.elevation_deg));
+
+    json_response(&look_angles, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct PassQuery {
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[param(value_type = String)]
+    start:f64,
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[param(value_type = String)]
+    end:f64,
+    // minimum elevation in degrees
+    mask:Option<f64>,
+    // azimuth dependent horizon as "az:el,az:el,..." in degrees
+    horizon:Option<String>,
+    // coarse search step in seconds
+    step:Option<f64>,
+}
+
+#[utoipa::path(get, path = "/passes/{source}", tag = "observers",
+    params(("source" = String, Path, description = "Data source key, e.g. cddis_cod_fin"), ObserverQuery, PassQuery, TimeQuery),
+    responses((status = 200, body = Vec<Pass>), ErrorResponses))]
+async fn get_passes(Path(source): Path<String>, ApiQuery(observer): ApiQuery<ObserverQuery>, ApiQuery(pass_query): ApiQuery<PassQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let observer = observer.observer()?;
+    let data_source = data_source(&source)?;
+
+    let step = pass_query.step.unwrap_or(60.0);
+    if pass_query.end <= pass_query.start || step <= 0.0 {
+        return Err(ApiError::InvalidRequest("Invalid pass window".to_string()));
+    }
+
+    let min_elevation = pass_query.mask.unwrap_or(0.0);
+    let mask = match pass_query.horizon.as_deref().map(ElevationMask::parse_horizon) {
+        None => ElevationMask::new(min_elevation),
+        Some(Ok(horizon)) => ElevationMask::with_horizon(min_elevation, horizon),
+        Some(Err(err)) => return Err(ApiError::InvalidRequest(err.to_string())),
+    };
+
+    let orbits = fetch_orbits(&data_source).await?;
+
+    let passes = predict_passes_all(&observer, &orbits, &mask, pass_query.start, pass_query.end, step);
+
+    json_response(&passes, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct DopQuery {
+    // comma separated data source keys
+    sources:String,
+    #[serde(deserialize_with = "deserialize_epoch")]
+    #[param(value_type = String)]
+    start:f64,
+    // single epoch when not set
+    #[serde(default, deserialize_with = "deserialize_optional_epoch")]
+    #[param(value_type = Option<String>)]
+    end:Option<f64>,
+    step:Option<f64>,
+    mask:Option<f64>,
+    // comma separated constellations (G,R,E,C,J or names), all when not set
+    constellations:Option<String>,
+}
+
+#[utoipa::path(get, path = "/dop", tag = "observers",
+    params(ObserverQuery, DopQuery, TimeQuery),
+    responses((status = 200, body = Vec<DopEpoch>), ErrorResponses))]
+async fn get_dop(ApiQuery(observer): ApiQuery<ObserverQuery>, ApiQuery(dop_query): ApiQuery<DopQuery>, ApiQuery(time_query): ApiQuery<TimeQuery>) -> Result<Response, ApiError> {
+
+    let observer = observer.observer()?;
+    let data_sources = parse_sources(&dop_query.sources)?;
+
+    let end = dop_query.end.unwrap_or(dop_query.start);
+    let step = dop_query.step.unwrap_or(300.0);
+    if end < dop_query.start || step <= 0.0 {
+        return Err(ApiError::InvalidRequest("Invalid DOP window".to_string()));
+    }
+
+    let constellations = parse_constellations(dop_query.constellations.as_deref())?;
+    let mask = ElevationMask::new(dop_query.mask.unwrap_or(0.0));
+
+    let orbits = fetch_orbits_from_sources(&data_sources).await?;
+
+    let series = dop_series(&observer, &orbits, dop_query.start, end, step, &mask, &constellations);
+
+    json_response(&series, &time_query)
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct GridQuery {
+    // comma separated data source keys
+    sources:String,
+    // grid spacing in degrees
+    step:Option<f64>,
+    mask:Option<f64>,
+    constellations:Option<String>,
+    // json, arrow or geojson
+    format:Option<String>,
+    // contoured field for geojson output
+    field:Option<GridField>,
+    // comma separated contour levels for geojson output
+    levels:Option<String>,
+}
+
+#[utoipa::path(get, path = "/grid/{epoch}", tag = "observers",
+    params(("epoch" = String, Path, description = "GPST seconds or another epoch format, e.g. 2024-05-01T12:00:00Z"), GridQuery),
+    responses((status = 200, description = "JSON grid, an Arrow IPC stream or GeoJSON contours with format", body = VisibilityGrid), ErrorResponses))]
+async fn get_grid(Path(epoch): Path<String>, ApiQuery(grid_query): ApiQuery<GridQuery>) -> Result<Response, ApiError> {
+
+    let epoch = gpst_seconds(&epoch)?;
+    let data_sources = parse_sources(&grid_query.sources)?;
+
+    let step = grid_query.step.unwrap_or(5.0);
+    if !(1.0..=45.0).contains(&step) {
+        return Err(ApiError::InvalidRequest("Grid step must be between 1 and 45 degrees".to_string()));
+    }
+
+    let constellations = parse_constellations(grid_query.constellations.as_deref())?;
+    let mask = ElevationMask::new(grid_query.mask.unwrap_or(0.0));
+
+    let orbits = fetch_orbits_from_sources(&data_sources).await?;
+
+    let grid = visibility_grid(&orbits, epoch, step, &mask, &constellations);
+
+    match grid_query.format.as_deref().unwrap_or("json") {
+        "json" => Ok((CORS_HEADERS, Json(grid)).into_response()),
+        "arrow" => {
+            let bytes = grid.to_record_batch().and_then(|batch| arrow_ipc_bytes(&batch))
+                .map_err(|err| ApiError::Internal(err.to_string()))?;
+
+            Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/vnd.apache.arrow.stream")], bytes).into_response())
+        }
+        "geojson" => {
+            let field = grid_query.field.unwrap_or(GridField::Pdop);
+            let levels:Vec<f64> = match grid_query.levels {
+                Some(levels) => levels.split(',')
+                    .map(|l| l.trim().parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| ApiError::InvalidRequest("Invalid contour levels".to_string()))?,
+                None => match field {
+                    GridField::Pdop => vec![1.5, 2.0, 3.0, 4.0, 6.0],
+                    GridField::Visible => (4..=20).step_by(2).map(|l| l as f64).collect(),
+                },
+            };
+
+            Ok((CORS_HEADERS, [(header::CONTENT_TYPE, "application/geo+json")], grid.contours(field, &levels).to_string()).into_response())
+        }
+        format => Err(ApiError::unsupported_format(format)),
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+struct StreamQuery {
+    // comma separated data source keys overriding ORBIT_SOURCE_CHAIN for the best source
+    chain:Option<String>,
+    // comma separated satellites and constellations to stream, all when neither is set
+    satellites:Option<String>,
+    constellations:Option<String>,
+    // seconds between updates, 1 by default and at least 1
+    rate:Option<f64>,
+    // epoch of the first update, now by default
+    #[serde(default, deserialize_with = "deserialize_optional_epoch")]
+    #[param(value_type = Option<String>)]
+    epoch:Option<f64>,
+    // GPST seconds the stream advances per wall clock second, 1 by default and 0 to hold the epoch
+    speed:Option<f64>,
+}
+
+impl StreamQuery {
+
+    fn subscription(&self, source:&str) -> Result<(SourceChain, PositionStream), ApiError> {
+
+        let chain = source_chain(source, self.chain.as_deref())?;
+
+        let filter = SatelliteFilter {
+            satellites: self.satellites.as_deref().unwrap_or_default()
+                .split(',')
+                .map(|sv| sv.trim())
+                .filter(|sv| !sv.is_empty())
+                .map(satellite)
+                .collect::<Result<_, _>>()?,
+            constellations: parse_constellations(self.constellations.as_deref())?,
+        };
+
+        let epoch = match self.epoch {
+            Some(epoch) => epoch,
+            None => now()?.to_gpst_seconds(),
+        };
+
+        let stream = PositionStream::new(filter, epoch, self.speed.unwrap_or(1.0), self.rate.unwrap_or(1.0))
+            .map_err(|err| ApiError::InvalidRequest(err.to_string()))?;
+
+        Ok((chain, stream))
+    }
+}
+
+// JSON of a stream message, the update or the error body of an update that failed
+fn stream_message(update:Result<PositionUpdate, ApiError>) -> String {
+    match update {
+        Ok(update) => serde_json::to_string(&update).unwrap(),
+        Err(err) => serde_json::to_string(&err.body()).unwrap(),
+    }
+}
+
+// Polls the product runs published once the workflows have stored them and drops the cached orbits of
+// the data sources with a new one, which also wakes the live position streams. Works whether or not the
+// API runs in the process serving the Restate handlers.
+async fn watch_product_runs() {
+
+    let client = reqwest::Client::new();
+    let mut ticks = tokio::time::interval(PRODUCT_RUN_POLL);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut published:Option<HashMap<String, u64>> = None;
+
+    loop {
+        ticks.tick().await;
+
+        // retried at the next tick, e.g. while Restate is starting
+        let Ok(current) = call_handler::<HashMap<String, u64>>(&client,
+            "DataSources/orbits/getPublished".to_string(),
+            String::new()).await else {
+            continue;
+        };
+
+        if let Some(published) = &published {
+            for (key, count) in &current {
+                if published.get(key) != Some(count) && let Ok(data_source) = DataSource::from_key(key.clone()) {
+                    OrbitCache::global().invalidate_source(&data_source);
+                }
+            }
+        }
+
+        published = Some(current);
+    }
+}
+
+// Pushes the updates of a subscription until the receiver is dropped: one at every tick of the rate and
+// one when a new product run of a source in the chain is published. Failed updates
+// are sent as errors and the stream carries on, a later product run may cover the epoch.
+async fn stream_positions(chain:SourceChain, mut stream:PositionStream, sender:mpsc::Sender<Result<PositionUpdate, ApiError>>) {
+
+    let client = reqwest::Client::new();
+    let started = Instant::now();
+
+    let mut ticks = tokio::time::interval_at(started, Duration::from_secs_f64(stream.rate_s()));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let mut updates = OrbitCache::global().subscribe();
+
+    loop {
+        let now = tokio::select! {
+            // epochs of the periodic updates are whole multiples of the rate after the start
+            tick = ticks.tick() => tick,
+            update = updates.recv() => match update {
+                Ok(data_source) if !chain.sources.contains(&data_source) => continue,
+                // a stream that missed invalidations updates in case one was of its chain
+                Ok(_) | Err(RecvError::Lagged(_)) => Instant::now(),
+                Err(RecvError::Closed) => return,
+            },
+            _ = sender.closed() => return,
+        };
+
+        let epoch = stream.epoch_at((now - started).as_secs_f64());
+        let update = chain_positions(&client, &chain, epoch).await
+            .map(|positions| stream.update(epoch, &positions));
+
+        if sender.send(update).await.is_err() {
+            return;
+        }
+    }
+}
+
+#[utoipa::path(get, path = "/positions/{source}/events", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), StreamQuery),
+    responses((status = 200, description = "Server-Sent Events, each a PositionUpdate or an ErrorBody", content_type = "text/event-stream", body = PositionUpdate), ErrorResponses))]
+async fn get_position_events(Path(source): Path<String>, ApiQuery(stream_query): ApiQuery<StreamQuery>) -> Result<Response, ApiError> {
+
+    let (chain, stream) = stream_query.subscription(&source)?;
+
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(stream_positions(chain, stream, sender));
+
+    let events = ReceiverStream::new(receiver)
+        .map(|update| Ok::<Event, Infallible>(Event::default().data(stream_message(update))));
+
+    Ok((CORS_HEADERS, Sse::new(events).keep_alive(KeepAlive::default())).into_response())
+}
+
+#[utoipa::path(get, path = "/positions/{source}/ws", tag = "orbits",
+    params(("source" = String, Path, description = "Data source key, or best for the source chain"), StreamQuery),
+    responses((status = 101, description = "WebSocket of text messages, each a PositionUpdate or an ErrorBody", body = PositionUpdate), ErrorResponses))]
+async fn get_position_socket(websocket:WebSocketUpgrade, Path(source): Path<String>, ApiQuery(stream_query): ApiQuery<StreamQuery>) -> Result<Response, ApiError> {
+
+    let (chain, stream) = stream_query.subscription(&source)?;
+
+    Ok(websocket.on_upgrade(move |socket| send_positions(socket, chain, stream)))
+}
+
+// forwards the updates of a subscription to the socket until either side closes
+async fn send_positions(mut socket:WebSocket, chain:SourceChain, stream:PositionStream) {
+
+    let (sender, mut receiver) = mpsc::channel(STREAM_BUFFER);
+    tokio::spawn(stream_positions(chain, stream, sender));
+
+    loop {
+        tokio::select! {
+            update = receiver.recv() => {
+                let Some(update) = update else { return };
+
+                if socket.send(Message::Text(stream_message(update).into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                if let None | Some(Err(_)) | Some(Ok(Message::Close(_))) = message {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+// OpenAPI document of the REST API, generated from the handler annotations and the ToSchema types
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "ground-control", description = "Satellite orbits, products and observer geometry from precise GNSS orbits"),
+    paths(
+        get_orbit, get_orbits, get_orbit_range, get_orbits_range, get_position_events, get_position_socket,
+        get_satellite_state, get_satellite_states, get_satellite_metadata,
+        get_look_angles, get_passes, get_dop, get_grid,
+        get_sources, process_sp3, process_nav,
+        combine_orbits, get_combination_report,
+        fit_broadcast_ephemerides, get_broadcast_ephemerides, get_ssr_corrections,
+        monitor_sisre, get_sisre, fit_mean_elements, get_mean_elements, compare_tle,
+    ),
+    components(schemas(ErrorBody, SatelliteId, DataSource, Orbit, SVSource)),
+    tags(
+        (name = "orbits", description = "Interpolated satellite positions and states"),
+        (name = "observers", description = "Look angles, passes, DOP and visibility from ground observers"),
+        (name = "catalog", description = "Satellite metadata"),
+        (name = "products", description = "Combined orbits, broadcast ephemerides, SSR, SISRE and TLE products"),
+        (name = "admin", description = "Data ingestion"),
+    ),
+)]
+struct ApiDoc;
+
+async fn get_openapi() -> impl IntoResponse {
+    (CORS_HEADERS, Json(ApiDoc::openapi()))
 }
 
 #[tokio::main]
@@ -165,12 +1478,40 @@ async fn main() -> Result<(), anyhow::Error> {
         std::process::exit(1);
     }
 
+    // missing or unreadable kernels fail at startup rather than on the first request
+    if let Err(err) = tokio::task::spawn_blocking(|| KernelManager::global().almanac()).await? {
+        tracing::info!("Failed to load kernels: {}", err);
+        std::process::exit(1);
+    }
+    let _kernel_task = KernelManager::global().spawn_refresh(kernels::CHECK_INTERVAL);
+
     // build our application with a route
     let app = Router::new()
+        .route("/orbit/{source}/{sv}/range", get(get_orbit_range).options(add_cors_headers))
         .route("/orbit/{source}/{sv}/{epoch}", get(get_orbit).options(add_cors_headers))
+        .route("/orbits/{source}/range", get(get_orbits_range).options(add_cors_headers))
         .route("/orbits/{source}/{epoch}", get(get_orbits).options(add_cors_headers))
+        .route("/look/{source}/{epoch}", get(get_look_angles).options(add_cors_headers))
+        .route("/passes/{source}", get(get_passes).options(add_cors_headers))
+        .route("/dop", get(get_dop).options(add_cors_headers))
+        .route("/grid/{epoch}", get(get_grid).options(add_cors_headers))
+        .route("/state/{source}/{sv}/{epoch}", get(get_satellite_state).options(add_cors_headers))
+        .route("/states/{source}/{epoch}", get(get_satellite_states).options(add_cors_headers))
+        .route("/satellites/{sv}", get(get_satellite_metadata).options(add_cors_headers))
         .route("/orbit/sources", get(get_sources).options(add_cors_headers))
-        .route("/orbit/source", post(process_sp3).options(add_cors_headers));
+        .route("/orbit/source", post(process_sp3).options(add_cors_headers))
+        .route("/nav/source", post(process_nav).options(add_cors_headers))
+        .route("/orbit/combine", post(combine_orbits).options(add_cors_headers))
+        .route("/orbit/combine/{source}", get(get_combination_report).options(add_cors_headers))
+        .route("/ephemeris/{source}/{sv}", get(get_broadcast_ephemerides).post(fit_broadcast_ephemerides).options(add_cors_headers))
+        .route("/ssr/{source}/{sv}", post(get_ssr_corrections).options(add_cors_headers))
+        .route("/sisre/{source}", get(get_sisre).post(monitor_sisre).options(add_cors_headers))
+        .route("/tle/{source}/{sv}", get(get_mean_elements).post(fit_mean_elements).options(add_cors_headers))
+        .route("/tle/{source}/{sv}/compare", post(compare_tle).options(add_cors_headers))
+        .route("/positions/{source}/events", get(get_position_events).options(add_cors_headers))
+        .route("/positions/{source}/ws", get(get_position_socket))
+        .route("/openapi.json", get(get_openapi).options(add_cors_headers))
+        .merge(SwaggerUi::new("/docs").config(utoipa_swagger_ui::Config::from("/openapi.json")));
 
     // run our app with hyper, listening globally on port 3000
     let _api_task = tokio::spawn(async move {
@@ -178,10 +1519,19 @@ async fn main() -> Result<(), anyhow::Error> {
         axum::serve(listener, app).await.unwrap();
     });
 
+    let _product_run_task = tokio::spawn(watch_product_runs());
+
     HttpServer::new(Endpoint::builder()
     .bind(SVOrbitsImpl.serve())
     .bind(Sp3DataImpl.serve())
+    .bind(NavDataImpl.serve())
     .bind(DataSourcesImpl.serve())
+    .bind(SatelliteMetadataImpl.serve())
+    .bind(OrbitCombinationImpl.serve())
+    .bind(BroadcastEphemeridesImpl.serve())
+    .bind(SsrGeneratorImpl.serve())
+    .bind(SisreMonitorImpl.serve())
+    .bind(MeanElementSetsImpl.serve())
     .build())
         .listen_and_serve("0.0.0.0:9080".parse().unwrap())
         .await;
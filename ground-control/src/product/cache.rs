@@ -0,0 +1,141 @@
+use std::{collections::HashMap, env, sync::{Arc, OnceLock, RwLock}, time::{Duration, Instant}};
+
+use tokio::sync::broadcast;
+
+use crate::{algo::propagate::OrbitFit, product::{satellite::SatelliteId, sv::{DataSource, Orbit, SVSource}}};
+
+// invalidations buffered per subscriber, a stream that falls further behind updates anyway
+const UPDATES_CAPACITY:usize = 64;
+
+// seconds before cached orbits are revalidated against the product runs stored in Restate
+const DEFAULT_TTL_S:f64 = 30.0;
+
+static ORBIT_CACHE:OnceLock<OrbitCache> = OnceLock::new();
+
+// In-process copy of the orbits held by SVOrbits, keyed by SVSource so positions can be interpolated
+// without a round trip to the ingress. Restate stays the source of truth: entries are dropped when a
+// product run of their data source is published and revalidated against the stored product run after the TTL.
+pub struct OrbitCache {
+    ttl:Duration,
+    orbits:RwLock<HashMap<String, Arc<Orbit>>>,
+    // last revalidation per SVSource or data source key
+    checked:RwLock<HashMap<String, Instant>>,
+    // data source of every invalidation, received by live position streams
+    updates:broadcast::Sender<DataSource>,
+    // extrapolation fit per SVSource key with the product run it was fitted to
+    fits:RwLock<HashMap<String, (u64, Arc<OrbitFit>)>>,
+}
+
+impl OrbitCache {
+
+    pub fn new(ttl:Duration) -> Self {
+        OrbitCache { ttl, orbits: RwLock::new(HashMap::new()), checked: RwLock::new(HashMap::new()), updates: broadcast::Sender::new(UPDATES_CAPACITY), fits: RwLock::new(HashMap::new()) }
+    }
+
+    // process wide cache, ORBIT_CACHE_TTL_S sets the revalidation interval
+    pub fn global() -> &'static OrbitCache {
+        ORBIT_CACHE.get_or_init(|| {
+            let ttl_s = env::var("ORBIT_CACHE_TTL_S").ok()
+                .and_then(|ttl| ttl.parse::<f64>().ok())
+                .filter(|ttl| *ttl >= 0.0)
+                .unwrap_or(DEFAULT_TTL_S);
+
+            OrbitCache::new(Duration::from_secs_f64(ttl_s))
+        })
+    }
+
+    // SVSource or data source key revalidated within the TTL
+    pub fn is_fresh(&self, key:&str) -> bool {
+        self.checked.read().unwrap()
+            .get(key)
+            .is_some_and(|checked| checked.elapsed() < self.ttl)
+    }
+
+    pub fn mark_checked(&self, key:&str) {
+        self.checked.write().unwrap().insert(key.to_string(), Instant::now());
+    }
+
+    // cached orbit whether or not it is fresh
+    pub fn get(&self, sv_source:&SVSource) -> Option<Arc<Orbit>> {
+        self.orbits.read().unwrap().get(&sv_source.get_key()).cloned()
+    }
+
+    pub fn insert(&self, orbit:Orbit) -> Arc<Orbit> {
+        let key = orbit.sv.get_key();
+        let orbit = Arc::new(orbit);
+        self.orbits.write().unwrap().insert(key.clone(), orbit.clone());
+        self.mark_checked(&key);
+        orbit
+    }
+
+    // cached orbits of the data source ordered by satellite
+    pub fn source_orbits(&self, data_source:&DataSource) -> Vec<Arc<Orbit>> {
+
+        let mut orbits:Vec<Arc<Orbit>> = self.orbits.read().unwrap().values()
+            .filter(|orbit| orbit.sv.data_source == *data_source)
+            .cloned()
+            .collect();
+
+        orbits.sort_by_key(|orbit| orbit.sv.satellite);
+        orbits
+    }
+
+    // Fit of the orbit's product run, computed by `fit` on the first extrapolation past the run.
+    // Failed fits are not kept.
+    pub fn fit(&self, orbit:&Orbit, fit:impl FnOnce() -> Result<OrbitFit, anyhow::Error>) -> Result<Arc<OrbitFit>, anyhow::Error> {
+
+        let key = orbit.sv.get_key();
+
+        if let Some((product_run_id, fit)) = self.fits.read().unwrap().get(&key) && *product_run_id == orbit.product_run_id {
+            return Ok(fit.clone());
+        }
+
+        let fit = Arc::new(fit()?);
+        self.fits.write().unwrap().insert(key, (orbit.product_run_id, fit.clone()));
+        Ok(fit)
+    }
+
+    // receives the data source whenever an orbit or the orbits of a data source are invalidated
+    pub fn subscribe(&self) -> broadcast::Receiver<DataSource> {
+        self.updates.subscribe()
+    }
+
+    pub fn invalidate(&self, sv_source:&SVSource) {
+        self.orbits.write().unwrap().remove(&sv_source.get_key());
+        self.fits.write().unwrap().remove(&sv_source.get_key());
+
+        let mut checked = self.checked.write().unwrap();
+        checked.remove(&sv_source.get_key());
+        checked.remove(&sv_source.data_source.get_key());
+
+        // fails only without subscribers
+        let _ = self.updates.send(sv_source.data_source.clone());
+    }
+
+    pub fn invalidate_source(&self, data_source:&DataSource) {
+        self.orbits.write().unwrap().retain(|_, orbit| orbit.sv.data_source != *data_source);
+
+        let prefix = format!("{}_", data_source.get_key());
+        self.fits.write().unwrap().retain(|key, _| !key.starts_with(&prefix));
+        self.checked.write().unwrap().retain(|key, _| *key != data_source.get_key() && !key.starts_with(&prefix));
+
+        let _ = self.updates.send(data_source.clone());
+    }
+
+    // Drops cached orbits of the data source that are missing from its product runs or from another
+    // run, and returns the satellites that have to be fetched again
+    pub fn stale_satellites(&self, data_source:&DataSource, product_runs:&[(SatelliteId, u64)]) -> Vec<SatelliteId> {
+
+        let mut orbits = self.orbits.write().unwrap();
+
+        orbits.retain(|_, orbit| {
+            orbit.sv.data_source != *data_source
+                || product_runs.contains(&(orbit.sv.satellite, orbit.product_run_id))
+        });
+
+        product_runs.iter()
+            .filter(|(satellite, _)| !orbits.contains_key(&SVSource { satellite: *satellite, data_source: data_source.clone() }.get_key()))
+            .map(|(satellite, _)| *satellite)
+            .collect()
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::HashMap, env, fs::File, io::BufReader, sync::{Arc, RwLock}};
+
+use anyhow::anyhow;
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::ApiError, algo::attitude::BlockType, data::sinex::parse_satellite_metadata, product::satellite::SatelliteId};
+
+pub const CATALOG_KEY:&str = "igs";
+
+// parsed catalog with the load revision it was read at
+type CachedCatalog = (u64, Arc<SatelliteCatalog>);
+
+// parsed catalog per object key, so lookups don't deserialize the stored catalog
+static CATALOG_CACHE:RwLock<Option<HashMap<String, CachedCatalog>>> = RwLock::new(None);
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SatelliteIdentity {
+    pub svn:String,
+    pub cospar_id:String,
+    pub satcat:Option<u32>,
+    // IGS block name, e.g. GPS-IIF, GLO-M, GAL-2, BDS-3M-CAST
+    pub block:String,
+    // launch date from the comment, e.g. "Launched 2018-12-23; NAVSTAR 74"
+    #[serde(default)]
+    pub launched:Option<f64>,
+    pub comment:String,
+}
+
+// SINEX record valid over [valid_from, valid_to), an open interval has no valid_to
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct Validity<T> {
+    pub svn:String,
+    pub valid_from:f64,
+    pub valid_to:Option<f64>,
+    pub value:T,
+}
+
+impl<T> Validity<T> {
+    pub fn contains(&self, epoch:f64) -> bool {
+        epoch >= self.valid_from && self.valid_to.is_none_or(|valid_to| epoch < valid_to)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SatelliteCatalog {
+    pub identifiers:HashMap<String, SatelliteIdentity>,
+    pub prn:Vec<Validity<SatelliteId>>,
+    pub frequency_channel:Vec<Validity<i32>>,
+    pub mass_kg:Vec<Validity<f64>>,
+    pub tx_power_w:Vec<Validity<f64>>,
+}
+
+// Metadata of the satellite occupying a PRN slot at an epoch
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct SatelliteInfo {
+    pub svn:String,
+    pub prn:Option<SatelliteId>,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
+    pub epoch:f64,
+    pub cospar_id:String,
+    pub satcat:Option<u32>,
+    pub block:String,
+    pub block_type:BlockType,
+    // launch date from SATELLITE/IDENTIFIER, decommissioned is the end of the last PRN assignment
+    // and None while the satellite holds a PRN
+    #[serde(serialize_with = "crate::gpst::serialize_optional_epoch")]
+    pub launched:Option<f64>,
+    #[serde(serialize_with = "crate::gpst::serialize_optional_epoch")]
+    pub decommissioned:Option<f64>,
+    pub frequency_channel:Option<i32>,
+    pub mass_kg:Option<f64>,
+    pub tx_power_w:Option<f64>,
+}
+
+// IGS SVN, a system letter followed by three digits
+pub fn is_svn(sv:&str) -> bool {
+    let bytes = sv.as_bytes();
+    bytes.len() == 4 && bytes[0].is_ascii_alphabetic() && bytes[1..].iter().all(|b| b.is_ascii_digit())
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SatelliteQuery {
+    // PRN (G01) or SVN (G074)
+    pub sv:String,
+    pub epoch:f64,
+}
+
+// overlapping records are resolved in favour of the latest start
+fn latest<'a, T>(records:impl Iterator<Item = &'a Validity<T>>) -> Option<&'a Validity<T>> where T:'a {
+    records.reduce(|latest, record| if record.valid_from >= latest.valid_from { record } else { latest })
+}
+
+fn value_at<T:Clone>(records:&[Validity<T>], svn:&str, epoch:f64) -> Option<T> {
+    latest(records.iter().filter(|record| record.svn == svn && record.contains(epoch)))
+        .map(|record| record.value.clone())
+}
+
+impl SatelliteCatalog {
+
+    pub fn from_file(path:&str) -> Result<SatelliteCatalog, anyhow::Error> {
+        let file = File::open(path).map_err(|err| anyhow!("Unable to open {}: {}", path, err))?;
+        parse_satellite_metadata(BufReader::new(file))
+    }
+
+    // SVN of the satellite transmitting as `prn` at the epoch
+    pub fn svn_for_prn(&self, prn:&SatelliteId, epoch:f64) -> Option<String> {
+        latest(self.prn.iter().filter(|record| record.value == *prn && record.contains(epoch)))
+            .map(|record| record.svn.clone())
+    }
+
+    pub fn satellite(&self, svn:&str, epoch:f64) -> Option<SatelliteInfo> {
+
+        let identity = self.identifiers.get(svn)?;
+
+        let assignments:Vec<&Validity<SatelliteId>> = self.prn.iter()
+            .filter(|record| record.svn == svn)
+            .collect();
+
+        let decommissioned = if assignments.iter().any(|record| record.valid_to.is_none()) {
+            None
+        } else {
+            assignments.iter().filter_map(|record| record.valid_to).reduce(f64::max)
+        };
+
+        Some(SatelliteInfo {
+            svn: identity.svn.clone(),
+            prn: value_at(&self.prn, svn, epoch),
+            epoch,
+            cospar_id: identity.cospar_id.clone(),
+            satcat: identity.satcat,
+            block: identity.block.clone(),
+            block_type: BlockType::from_igs_block(&identity.block),
+            launched: identity.launched,
+            decommissioned,
+            frequency_channel: value_at(&self.frequency_channel, svn, epoch),
+            mass_kg: value_at(&self.mass_kg, svn, epoch),
+            tx_power_w: value_at(&self.tx_power_w, svn, epoch),
+        })
+    }
+
+    // looks up a PRN (G04) at the epoch or an SVN (G074) directly
+    pub fn lookup(&self, sv:&str, epoch:f64) -> Option<SatelliteInfo> {
+        match sv.parse::<SatelliteId>() {
+            Ok(prn) => self.satellite(&self.svn_for_prn(&prn, epoch)?, epoch),
+            Err(_) => self.satellite(&sv.to_uppercase(), epoch),
+        }
+    }
+}
+
+#[restate_sdk::object]
+pub trait SatelliteMetadata {
+
+    // path to igs_satellite_metadata.snx, empty to use SAT_METADATA_PATH
+    #[name = "loadCatalog"]
+    async fn load_catalog(path:String) -> Result<(), HandlerError>;
+
+    #[shared]
+    #[name = "getSatellite"]
+    async fn get_satellite(query:Json<SatelliteQuery>) -> Result<Json<SatelliteInfo>, HandlerError>;
+
+    // one entry per query, None for unknown satellites
+    #[shared]
+    #[name = "getSatellites"]
+    async fn get_satellites(queries:Json<Vec<SatelliteQuery>>) -> Result<Json<Vec<Option<SatelliteInfo>>>, HandlerError>;
+}
+
+pub struct SatelliteMetadataImpl;
+
+// stored catalog, parsed once per load in this process
+async fn catalog(ctx:&SharedObjectContext<'_>) -> Result<Arc<SatelliteCatalog>, HandlerError> {
+
+    let revision = ctx.get::<u64>("revision").await?.unwrap_or(0);
+
+    let cached = CATALOG_CACHE.read().unwrap().as_ref()
+        .and_then(|catalogs| catalogs.get(ctx.key()))
+        .filter(|(cached_revision, _)| *cached_revision == revision)
+        .map(|(_, catalog)| catalog.clone());
+
+    if let Some(catalog) = cached {
+        return Ok(catalog);
+    }
+
+    let Some(catalog) = ctx.get::<Json<SatelliteCatalog>>("catalog").await? else {
+        return Err(TerminalError::from(ApiError::NotFound("Satellite catalog not loaded.".to_string())).into());
+    };
+
+    let catalog = Arc::new(catalog.into_inner());
+    CATALOG_CACHE.write().unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(ctx.key().to_string(), (revision, catalog.clone()));
+
+    Ok(catalog)
+}
+
+impl SatelliteMetadata for SatelliteMetadataImpl {
+
+    async fn load_catalog(&self, ctx:ObjectContext<'_>, path:String) -> Result<(), HandlerError> {
+
+        let path = if path.is_empty() {
+            env::var("SAT_METADATA_PATH").map_err(|_| TerminalError::from(ApiError::Internal("SAT_METADATA_PATH is not set".to_string())))?
+        } else {
+            path
+        };
+
+        let catalog = SatelliteCatalog::from_file(&path)
+            .map_err(|err| TerminalError::from(ApiError::Internal(format!("Unable to load {}: {}", path, err))))?;
+
+        info!("loaded {} satellites from {}", catalog.identifiers.len(), path);
+        let revision = ctx.get::<u64>("revision").await?.unwrap_or(0) + 1;
+        ctx.set("catalog", Json(catalog));
+        ctx.set("revision", revision);
+
+        Ok(())
+    }
+
+    async fn get_satellite(&self, ctx:SharedObjectContext<'_>, query:Json<SatelliteQuery>) -> Result<Json<SatelliteInfo>, HandlerError> {
+
+        let query = query.into_inner();
+
+        match catalog(&ctx).await?.lookup(&query.sv, query.epoch) {
+            Some(info) => Ok(Json(info)),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("Unknown satellite {}", query.sv))).into()),
+        }
+    }
+
+    async fn get_satellites(&self, ctx:SharedObjectContext<'_>, queries:Json<Vec<SatelliteQuery>>) -> Result<Json<Vec<Option<SatelliteInfo>>>, HandlerError> {
+
+        let catalog = catalog(&ctx).await?;
+
+        Ok(Json(queries.into_inner().iter().map(|query| catalog.lookup(&query.sv, query.epoch)).collect()))
+    }
+}
@@ -0,0 +1,87 @@
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::{terminal_error, ApiError}, algo::combination::{combine, CombinationConfig, CombinationReport}, product::{satellite::SatelliteId, sv::{DataSource, DataSourcesClient, Orbit, SVOrbitsClient, SVSource}}};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct CombinationRequest {
+    // analysis center products to combine, the first also sets the reference epochs
+    pub sources:Vec<DataSource>,
+    #[serde(default)]
+    pub config:Option<CombinationConfig>,
+}
+
+// keyed by the combined data source, e.g. cddis_cmb_fin
+#[restate_sdk::object]
+pub trait OrbitCombination {
+
+    #[name = "combine"]
+    async fn combine(request:Json<CombinationRequest>) -> Result<Json<CombinationReport>, HandlerError>;
+
+    #[shared]
+    #[name = "getReport"]
+    async fn get_report() -> Result<Json<CombinationReport>, HandlerError>;
+}
+
+pub struct OrbitCombinationImpl;
+
+impl OrbitCombination for OrbitCombinationImpl {
+
+    async fn combine(&self, ctx:ObjectContext<'_>, request:Json<CombinationRequest>) -> Result<Json<CombinationReport>, HandlerError> {
+
+        let request = request.into_inner();
+
+        let data_source = DataSource::from_key(ctx.key().to_string())
+            .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+
+        let mut orbits:Vec<Orbit> = Vec::new();
+
+        for source in &request.sources {
+
+            let satellites = ctx.object_client::<SVOrbitsClient>(source.get_key()).get_satellites().call().await?.into_inner();
+
+            let calls:Vec<_> = satellites.into_iter()
+                .map(|satellite| {
+                    let sv_source = SVSource {satellite, data_source: source.clone()};
+                    ctx.object_client::<SVOrbitsClient>(sv_source.get_key()).get_orbit().call()
+                })
+                .collect();
+
+            for call in calls {
+                if let Ok(orbit) = call.await {
+                    orbits.push(orbit.into_inner());
+                }
+            }
+        }
+
+        let combination = combine(&orbits, &data_source, &request.config.unwrap_or_default())
+            .map_err(|err| terminal_error(err, ApiError::Unprocessable))?;
+
+        info!("combined {} satellites into {}", combination.orbits.len(), data_source.get_key());
+
+        let updates:Vec<_> = combination.orbits.iter()
+            .map(|orbit| ctx.object_client::<SVOrbitsClient>(orbit.sv.get_key()).update_orbit(Json(orbit.clone())).call())
+            .collect();
+
+        // the run is only published once the orbits and their index are stored
+        for update in updates {
+            update.await?;
+        }
+
+        let product_runs:Vec<(SatelliteId, u64)> = combination.orbits.iter().map(|orbit| (orbit.sv.satellite, orbit.product_run_id)).collect();
+        ctx.object_client::<SVOrbitsClient>(data_source.get_key()).update_product_runs(Json(product_runs)).call().await?;
+        ctx.object_client::<DataSourcesClient>("orbits").publish_product_run(Json(data_source)).send();
+
+        ctx.set("report", Json(combination.report.clone()));
+
+        Ok(Json(combination.report))
+    }
+
+    async fn get_report(&self, ctx:SharedObjectContext<'_>) -> Result<Json<CombinationReport>, HandlerError> {
+
+        match ctx.get::<Json<CombinationReport>>("report").await? {
+            Some(report) => Ok(report),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No combination report for {}", ctx.key()))).into()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::{terminal_error, ApiError}, algo::ephemeris::{fit_ephemerides, BroadcastEphemeris, EphemerisConfig, EphemerisFit}, product::sv::SVOrbitsClient};
+
+// broadcast ephemerides kept behind the newest toe of a satellite
+const BROADCAST_RETENTION_S:f64 = 7.0 * 86_400.0;
+
+// Keyed like SVOrbits (source_ac_type_sat). Fits the stored precise orbit of that key, or holds
+// the ephemerides actually broadcast when the key is a navigation data source such as cddis_igs_brdc.
+#[restate_sdk::object]
+pub trait BroadcastEphemerides {
+
+    #[name = "fit"]
+    async fn fit(config:Json<EphemerisConfig>) -> Result<Json<Vec<EphemerisFit>>, HandlerError>;
+
+    #[shared]
+    #[name = "getFits"]
+    async fn get_fits() -> Result<Json<Vec<EphemerisFit>>, HandlerError>;
+
+    // ephemerides parsed from RINEX navigation files, merged with those already loaded
+    #[name = "loadBroadcast"]
+    async fn load_broadcast(ephemerides:Json<Vec<BroadcastEphemeris>>) -> Result<(), HandlerError>;
+
+    #[shared]
+    #[name = "getBroadcast"]
+    async fn get_broadcast() -> Result<Json<Vec<BroadcastEphemeris>>, HandlerError>;
+
+    // ephemeris with the closest toe among those whose fit interval covers the epoch
+    #[shared]
+    #[name = "getEphemeris"]
+    async fn get_ephemeris(epoch:f64) -> Result<Json<BroadcastEphemeris>, HandlerError>;
+}
+
+pub struct BroadcastEphemeridesImpl;
+
+impl BroadcastEphemerides for BroadcastEphemeridesImpl {
+
+    async fn fit(&self, ctx:ObjectContext<'_>, config:Json<EphemerisConfig>) -> Result<Json<Vec<EphemerisFit>>, HandlerError> {
+
+        let orbit = ctx.object_client::<SVOrbitsClient>(ctx.key()).get_orbit().call().await?.into_inner();
+
+        let fits = fit_ephemerides(&orbit, &config.into_inner())
+            .map_err(|err| terminal_error(err, ApiError::Unprocessable))?;
+
+        info!("fitted {} ephemerides for {}", fits.len(), ctx.key());
+
+        ctx.set("fits", Json(fits.clone()));
+
+        Ok(Json(fits))
+    }
+
+    async fn load_broadcast(&self, ctx:ObjectContext<'_>, ephemerides:Json<Vec<BroadcastEphemeris>>) -> Result<(), HandlerError> {
+
+        let mut broadcast = ctx.get::<Json<Vec<BroadcastEphemeris>>>("broadcast").await?
+            .map(|broadcast| broadcast.into_inner())
+            .unwrap_or_default();
+
+        // the same ephemeris appears in consecutive files and once per Galileo message type
+        for ephemeris in ephemerides.into_inner() {
+            if !broadcast.iter().any(|loaded| loaded.toe == ephemeris.toe && loaded.iode == ephemeris.iode) {
+                broadcast.push(ephemeris);
+            }
+        }
+
+        broadcast.sort_by(|a, b| a.toe.total_cmp(&b.toe));
+        if let Some(newest) = broadcast.last().map(|ephemeris| ephemeris.toe) {
+            broadcast.retain(|ephemeris| ephemeris.toe >= newest - BROADCAST_RETENTION_S);
+        }
+
+        info!("holding {} broadcast ephemerides for {}", broadcast.len(), ctx.key());
+
+        ctx.set("broadcast", Json(broadcast));
+
+        Ok(())
+    }
+
+    async fn get_broadcast(&self, ctx:SharedObjectContext<'_>) -> Result<Json<Vec<BroadcastEphemeris>>, HandlerError> {
+
+        match ctx.get::<Json<Vec<BroadcastEphemeris>>>("broadcast").await? {
+            Some(broadcast) => Ok(broadcast),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No broadcast ephemerides for {}", ctx.key()))).into()),
+        }
+    }
+
+    async fn get_fits(&self, ctx:SharedObjectContext<'_>) -> Result<Json<Vec<EphemerisFit>>, HandlerError> {
+
+        match ctx.get::<Json<Vec<EphemerisFit>>>("fits").await? {
+            Some(fits) => Ok(fits),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No fitted ephemerides for {}", ctx.key()))).into()),
+        }
+    }
+
+    async fn get_ephemeris(&self, ctx:SharedObjectContext<'_>, epoch:f64) -> Result<Json<BroadcastEphemeris>, HandlerError> {
+
+        let fits = ctx.get::<Json<Vec<EphemerisFit>>>("fits").await?
+            .map(|fits| fits.into_inner())
+            .unwrap_or_default();
+
+        let ephemeris = fits.into_iter()
+            .map(|fit| fit.ephemeris)
+            .filter(|ephemeris| ephemeris.covers(epoch))
+            .min_by(|a, b| (a.toe - epoch).abs().total_cmp(&(b.toe - epoch).abs()));
+
+        match ephemeris {
+            Some(ephemeris) => Ok(Json(ephemeris)),
+            None => Err(TerminalError::from(ApiError::OutsideCoverage(format!("No ephemeris covering {}", epoch))).into()),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{env, fs, sync::{Arc, Mutex, MutexGuard, OnceLock, RwLock}, time::{Duration, SystemTime}};
+
+use anise::{constants::frames::EARTH_J2000, prelude::{Almanac, Frame}};
+use anyhow::anyhow;
+use bytes::Bytes;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::info;
+
+// Earth orientation and shape constants shipped with the repository, loaded before the configured kernels
+const EMBEDDED_PCK:&[u8] = include_bytes!("../../../pck08.pca");
+
+// interval between checks of the kernel files for updates
+pub const CHECK_INTERVAL:Duration = Duration::from_secs(60);
+
+static KERNELS:OnceLock<KernelManager> = OnceLock::new();
+
+// PCK, BPC and BSP kernels loaded once per process and shared by the handlers. A kernel file that
+// changes on disk is picked up by the background check: the new almanac is loaded next to the current
+// one, which keeps answering until the swap, and stays in use when the new kernels fail to load.
+pub struct KernelManager {
+    paths:Vec<String>,
+    almanac:RwLock<Option<Arc<Almanac>>>,
+    // modification times of the kernel files at the last load, held while loading
+    modified:Mutex<Vec<Option<SystemTime>>>,
+}
+
+impl KernelManager {
+
+    pub fn new(paths:Vec<String>) -> Self {
+        KernelManager { paths, almanac: RwLock::new(None), modified: Mutex::new(Vec::new()) }
+    }
+
+    // comma separated kernel files from ANISE_KERNELS, followed by DE440_BSP_PATH when set
+    pub fn from_env() -> Self {
+
+        let mut paths:Vec<String> = env::var("ANISE_KERNELS").unwrap_or_default()
+            .split(',')
+            .map(|path| path.trim().to_string())
+            .filter(|path| !path.is_empty())
+            .collect();
+
+        if let Ok(path) = env::var("DE440_BSP_PATH") && !paths.contains(&path) {
+            paths.push(path);
+        }
+
+        KernelManager::new(paths)
+    }
+
+    pub fn global() -> &'static KernelManager {
+        KERNELS.get_or_init(KernelManager::from_env)
+    }
+
+    pub fn paths(&self) -> &[String] {
+        &self.paths
+    }
+
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        self.paths.iter()
+            .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+            .collect()
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Vec<Option<SystemTime>>>, anyhow::Error> {
+        self.modified.lock().map_err(|_| anyhow!("Kernel loading failed"))
+    }
+
+    fn load(&self) -> Result<Almanac, anyhow::Error> {
+
+        let mut almanac = Almanac::default()
+            .load_from_bytes(Bytes::from_static(EMBEDDED_PCK))
+            .map_err(|err| anyhow!("Unable to load the embedded PCK: {}", err))?;
+
+        for path in &self.paths {
+            almanac = almanac.load(path).map_err(|err| anyhow!("Unable to load kernel {}: {}", path, err))?;
+        }
+
+        info!("loaded kernels: embedded pck08.pca {:?}", self.paths);
+
+        Ok(almanac)
+    }
+
+    // swaps in newly loaded kernels, the caller holds the lock on the modification times
+    fn store(&self, modified:&mut Vec<Option<SystemTime>>) -> Result<Arc<Almanac>, anyhow::Error> {
+
+        let current = self.modified();
+        let almanac = Arc::new(self.load()?);
+
+        *self.almanac.write().unwrap() = Some(almanac.clone());
+        *modified = current;
+
+        Ok(almanac)
+    }
+
+    // almanac with the embedded and configured kernels, only loaded here on first use, normally at startup
+    pub fn almanac(&self) -> Result<Arc<Almanac>, anyhow::Error> {
+
+        if let Some(almanac) = self.almanac.read().unwrap().clone() {
+            return Ok(almanac);
+        }
+
+        let mut modified = self.lock()?;
+
+        // loaded while waiting for the lock
+        if let Some(almanac) = self.almanac.read().unwrap().clone() {
+            return Ok(almanac);
+        }
+
+        self.store(&mut modified)
+    }
+
+    // loads the kernels again when a file changed since the last load, true when they were swapped
+    pub fn refresh(&self) -> Result<bool, anyhow::Error> {
+
+        let mut modified = self.lock()?;
+
+        if self.almanac.read().unwrap().is_some() && self.modified() == *modified {
+            return Ok(false);
+        }
+
+        self.store(&mut modified).map(|_| true)
+    }
+
+    // Checks the kernel files at every interval, CHECK_INTERVAL in the server, on the blocking pool. A
+    // failed load, e.g. of a kernel half written on disk, keeps the current kernels and is retried at
+    // the next check.
+    pub fn spawn_refresh(&'static self, interval:Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+
+            let mut ticks = tokio::time::interval(interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // the first tick completes immediately, the kernels were just loaded
+            ticks.tick().await;
+
+            loop {
+                ticks.tick().await;
+
+                match tokio::task::spawn_blocking(move || self.refresh()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => info!("keeping the loaded kernels: {}", err),
+                    Err(err) => info!("kernel check failed: {}", err),
+                }
+            }
+        })
+    }
+
+    // Earth frame with the shape from the PCK for geodetic coordinates
+    pub fn earth_frame(&self) -> Result<Frame, anyhow::Error> {
+        self.almanac()?
+            .frame_from_uid(EARTH_J2000)
+            .map_err(|err| anyhow!("Unable to load Earth frame: {}", err))
+    }
+
+    // almanac for Sun and Moon positions, requires planetary ephemerides such as DE440 among the kernels
+    pub fn planetary_almanac(&self) -> Result<Arc<Almanac>, anyhow::Error> {
+
+        if !self.paths.iter().any(|path| path.to_lowercase().ends_with(".bsp")) {
+            return Err(anyhow!("No planetary ephemerides configured, set ANISE_KERNELS or DE440_BSP_PATH"));
+        }
+
+        self.almanac()
+    }
+}
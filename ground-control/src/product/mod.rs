@@ -1 +1,11 @@
 pub mod sv;
+pub mod catalog;
+pub mod cache;
+pub mod kernels;
+pub mod satellite;
+pub mod combination;
+pub mod ephemeris;
+pub mod ssr;
+pub mod sisre;
+pub mod tle;
+pub mod stream;
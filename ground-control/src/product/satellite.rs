@@ -0,0 +1,138 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::anyhow;
+use utoipa::{openapi::{schema::{ObjectBuilder, Schema, Type}, RefOr}, PartialSchema, ToSchema};
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Constellation {
+    Gps,
+    Glonass,
+    Galileo,
+    BeiDou,
+    Qzss,
+    NavIC,
+    Sbas,
+    Leo,
+}
+
+impl Constellation {
+
+    // RINEX system identifier
+    pub fn letter(&self) -> char {
+        match self {
+            Constellation::Gps => 'G',
+            Constellation::Glonass => 'R',
+            Constellation::Galileo => 'E',
+            Constellation::BeiDou => 'C',
+            Constellation::Qzss => 'J',
+            Constellation::NavIC => 'I',
+            Constellation::Sbas => 'S',
+            Constellation::Leo => 'L',
+        }
+    }
+
+    pub fn from_letter(letter:char) -> Result<Self, anyhow::Error> {
+        match letter.to_ascii_uppercase() {
+            'G' => Ok(Constellation::Gps),
+            'R' => Ok(Constellation::Glonass),
+            'E' => Ok(Constellation::Galileo),
+            'C' => Ok(Constellation::BeiDou),
+            'J' => Ok(Constellation::Qzss),
+            'I' => Ok(Constellation::NavIC),
+            'S' => Ok(Constellation::Sbas),
+            'L' => Ok(Constellation::Leo),
+            _ => Err(anyhow!("Unknown constellation {}", letter)),
+        }
+    }
+}
+
+impl FromStr for Constellation {
+    type Err = anyhow::Error;
+
+    // single RINEX letter ("G") or name ("gps", "galileo")
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(letter), None) = (chars.next(), chars.next()) {
+            return Constellation::from_letter(letter);
+        }
+
+        match s.to_lowercase().as_str() {
+            "gps" => Ok(Constellation::Gps),
+            "glonass" => Ok(Constellation::Glonass),
+            "galileo" => Ok(Constellation::Galileo),
+            "beidou" | "bds" => Ok(Constellation::BeiDou),
+            "qzss" => Ok(Constellation::Qzss),
+            "navic" | "irnss" => Ok(Constellation::NavIC),
+            "sbas" => Ok(Constellation::Sbas),
+            "leo" => Ok(Constellation::Leo),
+            _ => Err(anyhow!("Unknown constellation {}", s)),
+        }
+    }
+}
+
+// RINEX style satellite identifier, e.g. G05 or E12. Serialized as its string form so
+// Restate keys and stored orbits keep the existing format.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[serde(try_from = "String", into = "String")]
+pub struct SatelliteId {
+    pub constellation:Constellation,
+    pub prn:u8,
+}
+
+impl SatelliteId {
+    pub fn new(constellation:Constellation, prn:u8) -> Result<Self, anyhow::Error> {
+        if prn == 0 || prn > 99 {
+            return Err(anyhow!("Invalid PRN {} for {:?}", prn, constellation));
+        }
+        Ok(SatelliteId { constellation, prn })
+    }
+}
+
+impl fmt::Display for SatelliteId {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:02}", self.constellation.letter(), self.prn)
+    }
+}
+
+// documented as the string it is serialized to
+impl PartialSchema for SatelliteId {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .pattern(Some("^[GRECJISLgrecjisl][0-9]{2}$"))
+            .description(Some("RINEX style satellite identifier, e.g. G05"))
+            .into()
+    }
+}
+
+impl ToSchema for SatelliteId {}
+
+impl FromStr for SatelliteId {
+    type Err = anyhow::Error;
+
+    // exactly one system letter followed by two digits
+    fn from_str(s:&str) -> Result<Self, Self::Err> {
+
+        let bytes = s.as_bytes();
+        if bytes.len() != 3 || !bytes[1].is_ascii_digit() || !bytes[2].is_ascii_digit() {
+            return Err(anyhow!("Invalid satellite {}", s));
+        }
+
+        let constellation = Constellation::from_letter(bytes[0] as char)?;
+        SatelliteId::new(constellation, s[1..].parse()?)
+    }
+}
+
+impl TryFrom<String> for SatelliteId {
+    type Error = anyhow::Error;
+
+    fn try_from(s:String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SatelliteId> for String {
+    fn from(satellite:SatelliteId) -> Self {
+        satellite.to_string()
+    }
+}
@@ -0,0 +1,96 @@
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::ApiError, algo::{attitude::BlockType, sisre::{sisre_series, SisreConfig, SisreMonitoring}}, product::{catalog::{SatelliteMetadataClient, SatelliteQuery, CATALOG_KEY}, ephemeris::BroadcastEphemeridesClient, sv::{DataSource, SVOrbitsClient, SVSource}}};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SisreRequest {
+    // GPST seconds
+    pub from:f64,
+    pub to:f64,
+    // navigation data source whose broadcast ephemerides are monitored, e.g. cddis_igs_brdc
+    pub broadcast_source:DataSource,
+    #[serde(default)]
+    pub config:SisreConfig,
+}
+
+// keyed by the precise data source, e.g. cddis_cod_fin
+#[restate_sdk::object]
+pub trait SisreMonitor {
+
+    #[name = "monitor"]
+    async fn monitor(request:Json<SisreRequest>) -> Result<Json<SisreMonitoring>, HandlerError>;
+
+    #[shared]
+    #[name = "getMonitoring"]
+    async fn get_monitoring() -> Result<Json<SisreMonitoring>, HandlerError>;
+}
+
+pub struct SisreMonitorImpl;
+
+impl SisreMonitor for SisreMonitorImpl {
+
+    async fn monitor(&self, ctx:ObjectContext<'_>, request:Json<SisreRequest>) -> Result<Json<SisreMonitoring>, HandlerError> {
+
+        let request = request.into_inner();
+
+        let data_source = DataSource::from_key(ctx.key().to_string())
+            .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+        let broadcast_source = request.broadcast_source;
+
+        let satellites = ctx.object_client::<SVOrbitsClient>(data_source.get_key()).get_satellites().call().await?.into_inner();
+
+        // blocks from the catalog when it is loaded, for the antenna phase center offsets
+        let queries:Vec<SatelliteQuery> = satellites.iter()
+            .map(|satellite| SatelliteQuery { sv: satellite.to_string(), epoch: request.from })
+            .collect();
+        let blocks:Vec<BlockType> = match ctx.object_client::<SatelliteMetadataClient>(CATALOG_KEY).get_satellites(Json(queries)).call().await {
+            Ok(metadata) => metadata.into_inner().into_iter().zip(&satellites)
+                .map(|(info, satellite)| info.map_or_else(|| BlockType::from_satellite(*satellite), |info| info.block_type))
+                .collect(),
+            Err(_) => satellites.iter().map(|satellite| BlockType::from_satellite(*satellite)).collect(),
+        };
+
+        let mut samples = Vec::new();
+        let mut monitored = 0;
+
+        for (satellite, block) in satellites.into_iter().zip(blocks) {
+
+            let precise = SVSource {satellite, data_source: data_source.clone()};
+            let broadcast = SVSource {satellite, data_source: broadcast_source.clone()};
+
+            // satellites without broadcast ephemerides in the navigation data are not monitored
+            let Ok(ephemerides) = ctx.object_client::<BroadcastEphemeridesClient>(broadcast.get_key()).get_broadcast().call().await else {
+                continue;
+            };
+            let orbit = ctx.object_client::<SVOrbitsClient>(precise.get_key()).get_orbit().call().await?.into_inner();
+
+            monitored += 1;
+            let ephemerides = ephemerides.into_inner();
+
+            if let Ok(series) = sisre_series(&orbit, &ephemerides, block, request.from, request.to, &request.config) {
+                samples.extend(series);
+            }
+        }
+
+        if monitored == 0 {
+            return Err(TerminalError::from(ApiError::NotFound(format!("No broadcast ephemerides in {} for the satellites of {}", broadcast_source.get_key(), data_source.get_key()))).into());
+        }
+
+        let monitoring = SisreMonitoring::new(samples, &request.config);
+
+        info!("monitored {} SISRE samples of {} with {} alerts", monitoring.samples.len(), data_source.get_key(), monitoring.alerts.len());
+
+        ctx.set("monitoring", Json(monitoring.clone()));
+
+        Ok(Json(monitoring))
+    }
+
+    async fn get_monitoring(&self, ctx:SharedObjectContext<'_>) -> Result<Json<SisreMonitoring>, HandlerError> {
+
+        match ctx.get::<Json<SisreMonitoring>>("monitoring").await? {
+            Some(monitoring) => Ok(monitoring),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No SISRE monitoring for {}", ctx.key()))).into()),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use restate_sdk::prelude::*;
+
+use crate::{error::{terminal_error, ApiError}, algo::{attitude::BlockType, ssr::{ssr_corrections, SsrConfig, SsrCorrections}}, product::{catalog::{SatelliteMetadataClient, SatelliteQuery, CATALOG_KEY}, ephemeris::BroadcastEphemeridesClient, sv::{DataSource, SVOrbitsClient, SVSource}}};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SsrRequest {
+    // GPST seconds
+    pub from:f64,
+    pub to:f64,
+    // navigation data source whose broadcast ephemerides the corrections refer to, e.g.
+    // cddis_igs_brdc, so the IODE matches the ephemeris receivers decode
+    pub broadcast_source:DataSource,
+    #[serde(default)]
+    pub config:SsrConfig,
+}
+
+// keyed like SVOrbits (source_ac_type_sat) by the precise orbit the corrections are derived from
+#[restate_sdk::object]
+pub trait SsrGenerator {
+
+    #[shared]
+    #[name = "getCorrections"]
+    async fn get_corrections(request:Json<SsrRequest>) -> Result<Json<SsrCorrections>, HandlerError>;
+}
+
+pub struct SsrGeneratorImpl;
+
+impl SsrGenerator for SsrGeneratorImpl {
+
+    async fn get_corrections(&self, ctx:SharedObjectContext<'_>, request:Json<SsrRequest>) -> Result<Json<SsrCorrections>, HandlerError> {
+
+        let request = request.into_inner();
+
+        let precise = SVSource::from_key(ctx.key())
+            .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+
+        let broadcast = SVSource {
+            satellite: precise.satellite,
+            data_source: request.broadcast_source,
+        };
+
+        let orbit = ctx.object_client::<SVOrbitsClient>(precise.get_key()).get_orbit().call().await?.into_inner();
+        let ephemerides = ctx.object_client::<BroadcastEphemeridesClient>(broadcast.get_key()).get_broadcast().call().await?.into_inner();
+
+        // block of the satellite from the catalog when it is loaded, for the antenna phase center offset
+        let query = SatelliteQuery { sv: precise.satellite.to_string(), epoch: request.from };
+        let block = match ctx.object_client::<SatelliteMetadataClient>(CATALOG_KEY).get_satellite(Json(query)).call().await {
+            Ok(info) => info.into_inner().block_type,
+            Err(_) => BlockType::from_satellite(precise.satellite),
+        };
+
+        match ssr_corrections(&orbit, &ephemerides, block, request.from, request.to, &request.config) {
+            Ok(corrections) => Ok(Json(corrections)),
+            Err(err) => Err(terminal_error(err, ApiError::Unprocessable).into()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use crate::{algo::track::SatellitePosition, product::{satellite::{Constellation, SatelliteId}, sv::Orbit}};
+
+// bounds of the seconds between two updates of a stream, every update interpolates the whole chain
+pub const MIN_RATE_S:f64 = 1.0;
+pub const MAX_RATE_S:f64 = 3600.0;
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateKind {
+    // periodic update at the stream rate
+    Positions,
+    // update pushed because a new product run of a streamed satellite was loaded
+    ProductRun,
+}
+
+// message of a live position stream, the same JSON over WebSocket and SSE
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct PositionUpdate {
+    pub kind:UpdateKind,
+    pub epoch:f64,
+    pub positions:Vec<SatellitePosition>,
+}
+
+// satellites of a stream, everything when both lists are empty
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SatelliteFilter {
+    pub satellites:Vec<SatelliteId>,
+    pub constellations:Vec<Constellation>,
+}
+
+impl SatelliteFilter {
+
+    pub fn matches(&self, satellite:&SatelliteId) -> bool {
+        (self.satellites.is_empty() && self.constellations.is_empty())
+            || self.satellites.contains(satellite)
+            || self.constellations.contains(&satellite.constellation)
+    }
+}
+
+// State of one subscription: the epoch advances with the wall clock from `start_epoch` at `speed`
+// times real time, so a replay of an older product runs as fast as requested, and the product run
+// of each streamed orbit is kept to tell a new run from a periodic update.
+#[derive(Debug, Clone)]
+pub struct PositionStream {
+    filter:SatelliteFilter,
+    start_epoch:f64,
+    speed:f64,
+    rate_s:f64,
+    // product run per SVSource key of the last update
+    product_runs:HashMap<String, u64>,
+}
+
+impl PositionStream {
+
+    pub fn new(filter:SatelliteFilter, start_epoch:f64, speed:f64, rate_s:f64) -> Result<Self, anyhow::Error> {
+
+        if !(MIN_RATE_S..=MAX_RATE_S).contains(&rate_s) {
+            return Err(anyhow!("Rate must be between {} and {} seconds", MIN_RATE_S, MAX_RATE_S));
+        }
+
+        if !speed.is_finite() || speed < 0.0 {
+            return Err(anyhow!("Speed must be zero or positive"));
+        }
+
+        Ok(PositionStream { filter, start_epoch, speed, rate_s, product_runs: HashMap::new() })
+    }
+
+    pub fn rate_s(&self) -> f64 {
+        self.rate_s
+    }
+
+    // GPST epoch of the stream `elapsed_s` wall clock seconds after it started
+    pub fn epoch_at(&self, elapsed_s:f64) -> f64 {
+        self.start_epoch + elapsed_s * self.speed
+    }
+
+    // Update for the positions of the source at the epoch. Positions of filtered out satellites are
+    // dropped, and the update is a product run update when an orbit comes from another run than in
+    // the previous update.
+    pub fn update(&mut self, epoch:f64, positions:&[Orbit]) -> PositionUpdate {
+
+        let positions:Vec<&Orbit> = positions.iter()
+            .filter(|orbit| self.filter.matches(&orbit.sv.satellite))
+            .collect();
+
+        let product_runs:HashMap<String, u64> = positions.iter()
+            .map(|orbit| (orbit.sv.get_key(), orbit.product_run_id))
+            .collect();
+
+        let new_run = product_runs.iter()
+            .any(|(key, run)| self.product_runs.get(key).is_some_and(|previous| previous != run));
+
+        self.product_runs = product_runs;
+
+        PositionUpdate {
+            kind: match new_run {
+                true => UpdateKind::ProductRun,
+                false => UpdateKind::Positions,
+            },
+            epoch,
+            positions: positions.into_iter()
+                .filter_map(|orbit| SatellitePosition::from_orbit(orbit, epoch))
+                .collect(),
+        }
+    }
+}
@@ -1,12 +1,32 @@
-use std::collections::HashMap;
+use std::{borrow::Borrow, collections::HashMap, env, fs::File, io::BufReader, ops::Range, sync::OnceLock};
 
 use anyhow::anyhow;
-use regex::Regex;
 use restate_sdk::prelude::*;
 use tracing::info;
-use anise::{constants::frames::EARTH_J2000, math::{cartesian::CartesianState, Vector3}, prelude::Almanac};
+use anise::{math::{cartesian::CartesianState, Vector3}, prelude::Frame};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+use crate::{error::{terminal_error, ApiError}, algo::{attitude::BlockType, gravity::GravityModel, propagate::{check_extrapolation, extrapolate_fit, fit_orbit, PropagatorConfig, State, PROPAGATION_DEGREE}, sun::{satellite_state, sun_moon_ecef_km, SatelliteState}}, data::icgem::parse_icgem, product::{cache::OrbitCache, catalog::{SatelliteInfo, SatelliteMetadataClient, SatelliteQuery, CATALOG_KEY}, kernels::KernelManager, satellite::SatelliteId}};
+
+static GRAVITY_MODEL:OnceLock<GravityModel> = OnceLock::new();
+
+// ICGEM field from GRAVITY_MODEL_PATH truncated for propagation, or the embedded EGM2008 terms
+fn gravity_model() -> &'static GravityModel {
+    GRAVITY_MODEL.get_or_init(|| {
+        let model = env::var("GRAVITY_MODEL_PATH").map_err(|err| anyhow!(err))
+            .and_then(|path| Ok(BufReader::new(File::open(path)?)))
+            .and_then(|reader| parse_icgem(reader, PROPAGATION_DEGREE));
+
+        match model {
+            Ok(model) => model,
+            Err(err) => {
+                info!("using embedded gravity model: {}", err);
+                GravityModel::egm2008()
+            }
+        }
+    })
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
 pub struct DataSource {
     pub source:String,
     pub analysis_center:String,
@@ -36,9 +56,58 @@ impl DataSource {
     }
 }
 
+// pseudo-source resolved through the configured source chain
+pub const BEST_SOURCE:&str = "best";
+
+// Data sources in priority order, e.g. final -> rapid -> ultra-rapid -> SSR -> broadcast.
+// Each satellite and epoch is answered by the first source that covers it.
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+pub struct SourceChain {
+    pub sources:Vec<DataSource>,
+}
+
+impl SourceChain {
+
+    // comma separated data source keys, highest priority first
+    pub fn parse(chain:&str) -> Result<Self, anyhow::Error> {
+
+        let sources = chain.split(',')
+            .map(|source| source.trim())
+            .filter(|source| !source.is_empty())
+            .map(|source| DataSource::from_key(source.to_string()))
+            .collect::<Result<Vec<DataSource>, anyhow::Error>>()?;
+
+        if sources.is_empty() {
+            return Err(anyhow!("Empty source chain"));
+        }
+
+        Ok(SourceChain { sources })
+    }
+
+    // ORBIT_SOURCE_CHAIN, or final, rapid and ultra-rapid products of the default analysis center
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        match env::var("ORBIT_SOURCE_CHAIN") {
+            Ok(chain) => SourceChain::parse(&chain),
+            Err(_) => SourceChain::parse("cddis_cod_fin,cddis_cod_rap,cddis_cod_ult"),
+        }
+    }
+
+    // `best` resolves to the chain, any other value is a single data source key
+    pub fn resolve(source:&str, chain:Option<&str>) -> Result<Self, anyhow::Error> {
+        if source != BEST_SOURCE {
+            return Ok(SourceChain { sources: vec![DataSource::from_key(source.to_string())?] });
+        }
+
+        match chain {
+            Some(chain) => SourceChain::parse(chain),
+            None => SourceChain::from_env(),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
 pub struct SVSource {
-    pub satellite:String,
+    pub satellite:SatelliteId,
     pub data_source:DataSource,
 }
 
@@ -48,39 +117,50 @@ impl SVSource{
         format!("{}_{}_{}_{}", self.data_source.source,
             self.data_source.analysis_center,
             self.data_source.product_type,
-            self.satellite.to_lowercase())
+            self.satellite.to_string().to_lowercase())
     }
 
+    // accepts a bare satellite (default data source) or a full source_ac_type_sat key
     pub fn from_key(key:&str) -> Result<Self, anyhow::Error> {
 
         let key_parts:Vec<&str> = key.splitn(4, "_").collect();
-        let satellite_regex = Regex::new(r"[a-zA-Z]\d\d")?;
-        if key_parts.len() == 1 &&
-            satellite_regex.is_match(key_parts[0]) {
-                let sv_source = SVSource {satellite:key_parts[0].to_string(), data_source:DataSource::defaults()};
-                return Ok(sv_source);
-        }
-        else if key_parts.len() == 4 {
 
+        match key_parts.len() {
+            1 => Ok(SVSource {satellite:key_parts[0].parse()?, data_source:DataSource::defaults()}),
+            4 => Ok(SVSource {
+                satellite:key_parts[3].parse()?,
+                data_source:DataSource::from_key(key_parts[..3].join("_"))?,
+            }),
+            _ => Err(anyhow!("invalid key format")),
         }
-
-        Err(anyhow!("invalid key format"))
     }
 }
 
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
 pub struct Orbit {
 
     pub sv:SVSource,
     pub product_run_id:u64,
     pub sampling_resolution:f64,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
     pub valid_from:f64,
+    #[serde(serialize_with = "crate::gpst::serialize_epoch")]
     pub valid_to:f64,
+    #[serde(serialize_with = "crate::gpst::serialize_epochs")]
     pub epochs:Vec<f64>,
+    // ECEF positions in km, one [x, y, z] per epoch
+    #[schema(value_type = Vec<Vec<f64>>)]
     pub pos_ecef_km:Vec<Vector3>,
+    #[schema(value_type = Option<Vec<Vec<f64>>>)]
     pub pos_latlonalt:Option<Vec<(f64, f64, f64)>>,
-    pub clock_usec:Option<Vec<f64>>
+    pub clock_usec:Option<Vec<f64>>,
+    // satellite catalog entry for the PRN at the requested epoch, set on position responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata:Option<SatelliteInfo>,
+    // propagated past the product span rather than interpolated from precise samples
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extrapolated:bool,
 
 }
 
@@ -93,9 +173,70 @@ impl Orbit {
         Ok(orbit)
     }
 
-    pub fn lagrange_orbit_interpolation(&self, epoch:f64, order:i64) -> Result<Orbit, anyhow::Error> {
+    // interpolated position without the surrounding sample window or logging,
+    // used by algorithms that evaluate many epochs per orbit
+    pub fn position_at(&self, epoch:f64) -> Result<Vector3, anyhow::Error> {
 
-        info!("caclulating orbit for {} at {}", self.sv.satellite, epoch);
+        let (interpolation_range, _) = self.interpolation_window(epoch, 17)?;
+
+        Ok(lagrange_interpolate(&self.epochs[interpolation_range.clone()], &self.pos_ecef_km[interpolation_range], epoch))
+    }
+
+    // ECEF velocity in km/s from a central difference of the interpolated positions
+    pub fn velocity_at(&self, epoch:f64) -> Result<Vector3, anyhow::Error> {
+
+        let h = 0.5;
+        let before = self.position_at(epoch - h)?;
+        let after = self.position_at(epoch + h)?;
+
+        Ok((after - before) / (2.0 * h))
+    }
+
+    // position and velocity with a ten sample window clamped to the orbit, so states are
+    // available up to the first and last sample at some loss of accuracy there
+    pub fn state_at(&self, epoch:f64) -> Result<State, anyhow::Error> {
+
+        if self.epochs.len() < 2 || epoch < self.valid_from || epoch > self.valid_to {
+            return Err(ApiError::OutsideCoverage(format!("Epoch {} outside the orbit of {}", epoch, self.sv.get_key())).into());
+        }
+
+        let nearest = ((epoch - self.valid_from) / self.sampling_resolution).round() as usize;
+        let start = nearest.saturating_sub(5).min(self.epochs.len().saturating_sub(10));
+        let window = start..(start + 10).min(self.epochs.len());
+
+        let interpolate = |epoch:f64| lagrange_interpolate(&self.epochs[window.clone()], &self.pos_ecef_km[window.clone()], epoch);
+
+        Ok(State {
+            epoch,
+            pos_ecef_km: interpolate(epoch),
+            vel_ecef_km_s: interpolate(epoch + 0.5) - interpolate(epoch - 0.5),
+        })
+    }
+
+    // states at the samples in [from, to], or every `step_s` seconds from `from` when given
+    pub fn states(&self, from:f64, to:f64, step_s:Option<f64>) -> Result<Vec<State>, anyhow::Error> {
+
+        let from = from.max(self.valid_from);
+        let to = to.min(self.valid_to);
+
+        match step_s {
+            Some(step_s) if step_s <= 0.0 => Err(anyhow!("Step must be positive")),
+            Some(step_s) => (0..).map(|i| from + i as f64 * step_s)
+                .take_while(|epoch| *epoch <= to)
+                .map(|epoch| self.state_at(epoch))
+                .collect(),
+            None => self.epochs.iter()
+                .filter(|epoch| **epoch >= from && **epoch <= to)
+                .map(|epoch| self.state_at(*epoch))
+                .collect(),
+        }
+    }
+
+    pub fn covers(&self, epoch:f64) -> bool {
+        self.interpolation_window(epoch, 17).is_ok()
+    }
+
+    fn interpolation_window(&self, epoch:f64, order:i64) -> Result<(Range<usize>, usize), anyhow::Error> {
 
         let odd_order = order % 2 > 0;
         let (min_before, min_after): (usize, usize) = match odd_order {
@@ -107,13 +248,21 @@ impl Orbit {
         let epoch_index = epoch_index_fractional.round() as usize;
 
         if epoch_index < min_before || epoch_index + min_after > self.epochs.len() - 1 {
-            return Err(anyhow!("Interpolation window outside epoch data range."));
+            return Err(ApiError::OutsideCoverage(format!("Epoch {} outside the interpolation window of {}", epoch, self.sv.get_key())).into());
         }
 
-        let interpolation_range = epoch_index - min_before..epoch_index + min_after;
+        Ok((epoch_index - min_before..epoch_index + min_after, min_before))
+    }
+
+    pub fn lagrange_orbit_interpolation(&self, epoch:f64, order:i64) -> Result<Orbit, anyhow::Error> {
+
+        info!("caclulating orbit for {} at {}", self.sv.satellite, epoch);
+
+        let (interpolation_range, min_before) = self.interpolation_window(epoch, order)?;
         let interpolation_epochs = &self.epochs[interpolation_range.clone()];
         let interpolation_positions = &self.pos_ecef_km[interpolation_range.clone()];
 
+        let epoch_index_fractional = (epoch - self.valid_from) / self.sampling_resolution;
         if epoch_index_fractional.fract() == 0.0 {
 
             let mut orbit = self.clone();
@@ -127,28 +276,7 @@ impl Orbit {
 
         }
 
-        let mut result_position = Vector3::new(0.0, 0.0, 0.0);
-
-        // Lagrange interpolation for SP3 orbit data
-        let n = interpolation_epochs.len();
-        for i in 0..n {
-            let orbit_i_epoch = &interpolation_epochs[i];
-            let mut li = 1.0_f64;
-
-            // Compute the lagrange basis polynomial l_i(t)
-            for j in 0..n {
-                if i != j {
-                    let orbit_j_epoch = &interpolation_epochs[j];
-                    let delta_t = orbit_i_epoch - orbit_j_epoch;
-                    li *= (epoch - orbit_j_epoch) / delta_t;
-                }
-            }
-
-            let orbit_i_position = interpolation_positions[i];
-
-            result_position += li * orbit_i_position;
-        }
-
+        let result_position = lagrange_interpolate(interpolation_epochs, interpolation_positions, epoch);
 
         let mut orbit = self.clone();
 
@@ -166,6 +294,116 @@ impl Orbit {
 
 }
 
+// Lagrange interpolation for SP3 orbit data
+pub(crate) fn lagrange_interpolate(interpolation_epochs:&[f64], interpolation_positions:&[Vector3], epoch:f64) -> Vector3 {
+
+    let mut result_position = Vector3::new(0.0, 0.0, 0.0);
+
+    let n = interpolation_epochs.len();
+    for i in 0..n {
+        let orbit_i_epoch = &interpolation_epochs[i];
+        let mut li = 1.0_f64;
+
+        // Compute the lagrange basis polynomial l_i(t)
+        for j in 0..n {
+            if i != j {
+                let orbit_j_epoch = &interpolation_epochs[j];
+                let delta_t = orbit_i_epoch - orbit_j_epoch;
+                li *= (epoch - orbit_j_epoch) / delta_t;
+            }
+        }
+
+        let orbit_i_position = interpolation_positions[i];
+
+        result_position += li * orbit_i_position;
+    }
+
+    result_position
+}
+
+// Position response at the epoch, propagated from the last precise states past the interpolation
+// window. The fit is computed once per SVSource and product run and kept in the orbit cache.
+pub fn orbit_position(orbit:&Orbit, epoch:f64) -> Result<Orbit, anyhow::Error> {
+    match orbit.get_position_at(epoch) {
+        Err(_) if epoch > orbit.valid_from => {
+            let config = PropagatorConfig::default();
+            check_extrapolation(orbit, epoch, &config)?;
+            let fit = OrbitCache::global().fit(orbit, || fit_orbit(orbit, gravity_model(), &config))?;
+            Ok(extrapolate_fit(orbit, &fit, epoch, gravity_model(), &config))
+        }
+        position => position,
+    }
+}
+
+// positions of all orbits at the epoch, in the order of the orbits, computed on one thread per core
+pub fn orbit_positions<O:Borrow<Orbit> + Sync>(orbits:&[O], epoch:f64) -> Vec<Result<Orbit, anyhow::Error>> {
+
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = orbits.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let handles:Vec<_> = orbits.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|orbit| orbit_position(orbit.borrow(), epoch)).collect::<Vec<_>>()))
+            .collect();
+
+        handles.into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|_| vec![Err(anyhow!("position thread panicked"))]))
+            .collect()
+    })
+}
+
+// states of all orbits at the epoch for one Sun and Moon position, in the order of the orbits, with
+// the block type of the catalog entry of each orbit or the constellation default
+pub fn satellite_states<O:Borrow<Orbit>>(orbits:&[O], epoch:f64, sun_pos:&Vector3, moon_pos:&Vector3) -> Vec<Result<SatelliteState, anyhow::Error>> {
+    orbits.iter()
+        .map(|orbit| {
+            let orbit = orbit.borrow();
+            let block = orbit.metadata.as_ref()
+                .map(|info| info.block_type)
+                .unwrap_or_else(|| BlockType::from_satellite(orbit.sv.satellite));
+            satellite_state(orbit, epoch, sun_pos, moon_pos, block)
+        })
+        .collect()
+}
+
+// positions per source in chain order merged to one per satellite: the first interpolated from precise
+// samples, otherwise the extrapolation from the orbit ending last
+pub fn best_positions(positions:Vec<Vec<Orbit>>) -> Vec<Orbit> {
+
+    let mut best:Vec<Orbit> = Vec::new();
+
+    for orbit in positions.into_iter().flatten() {
+        match best.iter_mut().find(|current| current.sv.satellite == orbit.sv.satellite) {
+            None => best.push(orbit),
+            Some(current) => {
+                if current.extrapolated && (!orbit.extrapolated || orbit.valid_to > current.valid_to) {
+                    *current = orbit;
+                }
+            }
+        }
+    }
+
+    best
+}
+
+pub fn set_latlonalt(position:&mut Orbit, frame:Frame) {
+
+    let mut pos_latlonalt:Vec<(f64, f64, f64)> = Vec::new();
+
+    for (epoch, pos) in position.epochs.iter().zip(&position.pos_ecef_km) {
+
+        let epoch = hifitime::Epoch::from_gpst_seconds(*epoch);
+        let cartian_state = CartesianState::from_position(pos.x, pos.y, pos.z, epoch, frame);
+
+        match cartian_state.latlongalt() {
+            Ok(latlonalt) => pos_latlonalt.push(latlonalt),
+            Err(err) => info!("latlonalt error: {}", err),
+        }
+    }
+
+    position.pos_latlonalt = Some(pos_latlonalt);
+}
+
 #[restate_sdk::object]
 pub trait DataSources {
     #[name = "updateSource"]
@@ -174,8 +412,20 @@ pub trait DataSources {
     #[shared]
     #[name = "getSources"]
     async fn get_sources() -> Result<Json<HashMap<String, DataSource>>, HandlerError>;
+
+    // called by the workflows once every orbit of a product run is stored
+    #[name = "publishProductRun"]
+    async fn publish_product_run(data_source:Json<DataSource>) -> Result<(), HandlerError>;
+
+    // publication count per data source key, polled by the API to drop its cached orbits
+    #[shared]
+    #[name = "getPublished"]
+    async fn get_published() -> Result<Json<HashMap<String, u64>>, HandlerError>;
 }
 
+// state key of the publication counts, next to the data sources
+const PUBLISHED:&str = "published";
+
 pub struct DataSourcesImpl;
 
 impl DataSources for DataSourcesImpl {
@@ -189,110 +439,280 @@ impl DataSources for DataSourcesImpl {
     }
 
     async fn get_sources(&self, ctx:SharedObjectContext<'_>) -> Result<Json<HashMap<String, DataSource>>, HandlerError> {
-        let keys = ctx.get_keys().await.unwrap();
+        let keys = ctx.get_keys().await?;
 
         let mut data_sources:HashMap<String, DataSource> = HashMap::new();
 
-        for key in keys {
-            data_sources.insert(key.clone(), ctx.get::<Json<DataSource>>(&key).await.unwrap().unwrap().into_inner());
+        for key in keys.into_iter().filter(|key| key != PUBLISHED) {
+            if let Some(data_source) = ctx.get::<Json<DataSource>>(&key).await? {
+                data_sources.insert(key, data_source.into_inner());
+            }
         }
 
         Ok(Json(data_sources))
     }
 
+    async fn publish_product_run(&self, ctx:ObjectContext<'_>, data_source:Json<DataSource>) -> Result<(), HandlerError> {
+        let data_source = data_source.into_inner();
+        info!("publish product run of {}", data_source.get_key());
+
+        let mut published = ctx.get::<Json<HashMap<String, u64>>>(PUBLISHED).await?.map(Json::into_inner).unwrap_or_default();
+        *published.entry(data_source.get_key()).or_default() += 1;
+
+        ctx.set(&data_source.get_key(), Json(data_source));
+        ctx.set(PUBLISHED, Json(published));
+
+        Ok(())
+    }
+
+    async fn get_published(&self, ctx:SharedObjectContext<'_>) -> Result<Json<HashMap<String, u64>>, HandlerError> {
+        let published = ctx.get::<Json<HashMap<String, u64>>>(PUBLISHED).await?.map(Json::into_inner).unwrap_or_default();
+        Ok(Json(published))
+    }
+
+}
+
+// catalog entry for the satellite holding the PRN at the epoch, None when no catalog is loaded
+async fn catalog_entry(ctx:&SharedObjectContext<'_>, satellite:&SatelliteId, epoch:f64) -> Option<SatelliteInfo> {
+    let query = SatelliteQuery { sv: satellite.to_string(), epoch };
+    ctx.object_client::<SatelliteMetadataClient>(CATALOG_KEY)
+        .get_satellite(Json(query))
+        .call()
+        .await
+        .ok()
+        .map(|info| info.into_inner())
+}
+
+// Orbits of the satellites of the data source, read from the objects keyed by SVSource. The object
+// keyed by the data source only indexes its satellites and product runs.
+async fn source_orbits(ctx:&SharedObjectContext<'_>, satellites:Vec<SatelliteId>) -> Result<Vec<Orbit>, HandlerError> {
+
+    let data_source = DataSource::from_key(ctx.key().to_string())
+        .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+
+    // every call is sent before the first result is awaited
+    let calls:Vec<_> = satellites.into_iter()
+        .map(|satellite| {
+            let sv_source = SVSource {satellite, data_source: data_source.clone()};
+            ctx.object_client::<SVOrbitsClient>(sv_source.get_key()).get_orbit().call()
+        })
+        .collect();
+
+    let mut orbits:Vec<Orbit> = Vec::new();
+
+    for call in calls {
+        if let Ok(orbit) = call.await {
+            orbits.push(orbit.into_inner());
+        }
+    }
+
+    Ok(orbits)
 }
 
 #[restate_sdk::object]
 pub trait SVOrbits {
 
     #[name = "updateSatellites"]
-    async fn update_satellites(satellites:Json<Vec<String>>) -> Result<(), HandlerError>;
+    async fn update_satellites(satellites:Json<Vec<SatelliteId>>) -> Result<(), HandlerError>;
 
+    #[shared]
     #[name = "getSatellites"]
-    async fn get_satellites() -> Result<Json<Vec<String>>, HandlerError>;
+    async fn get_satellites() -> Result<Json<Vec<SatelliteId>>, HandlerError>;
 
     #[name = "updateOrbit"]
     async fn update_orbit(orbit:Json<Orbit>) -> Result<(), HandlerError>;
 
+    // product run per satellite of a data source, on the object keyed by the data source, replacing
+    // its satellites
+    #[name = "updateProductRuns"]
+    async fn update_product_runs(product_runs:Json<Vec<(SatelliteId, u64)>>) -> Result<(), HandlerError>;
+
     #[shared]
     #[name = "getOrbitPosition"]
     async fn get_position(epoch:f64) -> Result<Json<Orbit>, HandlerError>;
+
+    // positions of every satellite of the data source in one call
+    #[shared]
+    #[name = "getOrbitPositions"]
+    async fn get_positions(epoch:f64) -> Result<Json<Vec<Orbit>>, HandlerError>;
+
+    #[shared]
+    #[name = "getOrbit"]
+    async fn get_orbit() -> Result<Json<Orbit>, HandlerError>;
+
+    // orbits of the given satellites of the data source in one call
+    #[shared]
+    #[name = "getOrbits"]
+    async fn get_orbits(satellites:Json<Vec<SatelliteId>>) -> Result<Json<Vec<Orbit>>, HandlerError>;
+
+    // product run of the stored orbit, for revalidating cached copies
+    #[shared]
+    #[name = "getProductRunId"]
+    async fn get_product_run_id() -> Result<u64, HandlerError>;
+
+    // product run per satellite of the data source
+    #[shared]
+    #[name = "getProductRuns"]
+    async fn get_product_runs() -> Result<Json<Vec<(SatelliteId, u64)>>, HandlerError>;
+
+    #[shared]
+    #[name = "getSatelliteState"]
+    async fn get_satellite_state(epoch:f64) -> Result<Json<SatelliteState>, HandlerError>;
 }
 
 pub struct SVOrbitsImpl;
 
 impl SVOrbits for SVOrbitsImpl {
 
-    async fn update_satellites(&self, ctx: ObjectContext<'_>, satellites:Json<Vec<String>>) -> Result<(), HandlerError> {
+    async fn update_satellites(&self, ctx: ObjectContext<'_>, satellites:Json<Vec<SatelliteId>>) -> Result<(), HandlerError> {
         ctx.set("satellites", satellites);
         Ok(())
     }
 
-    async fn get_satellites(&self, ctx: ObjectContext<'_>) -> Result<Json<Vec<String>>, HandlerError> {
-        let satellites:Json<Vec<String>> = ctx.get("satellites").await.unwrap().unwrap();
+    async fn get_satellites(&self, ctx: SharedObjectContext<'_>) -> Result<Json<Vec<SatelliteId>>, HandlerError> {
+        // a data source without stored orbits, answered as an unknown source by the API
+        let Some(satellites) = ctx.get::<Json<Vec<SatelliteId>>>("satellites").await? else {
+            return Err(TerminalError::from(ApiError::NotFound(format!("No orbits for {}", ctx.key()))).into());
+        };
         Ok(satellites)
     }
 
     async fn update_orbit(&self, ctx: ObjectContext<'_>, orbit:Json<Orbit>) -> Result<(), HandlerError> {
         let orbit = orbit.into_inner();
         info!("set orbit for {}", ctx.key());
-        ctx.set("orbit", Json(orbit.clone()));
-        ctx.object_client::<DataSourcesClient>("orbits").update_source(Json(orbit.sv.data_source)).send();
+        ctx.set("product_run_id", orbit.product_run_id);
+        ctx.set("orbit", Json(orbit));
+        Ok(())
+    }
+
+    async fn update_product_runs(&self, ctx: ObjectContext<'_>, product_runs:Json<Vec<(SatelliteId, u64)>>) -> Result<(), HandlerError> {
+
+        let product_runs = product_runs.into_inner();
+        info!("set {} product runs for {}", product_runs.len(), ctx.key());
+
+        // only data source keys hold a product run index
+        DataSource::from_key(ctx.key().to_string())
+            .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+
+        // also drops the orbit copies stored here by earlier versions
+        ctx.clear_all();
+
+        let satellites:Vec<SatelliteId> = product_runs.iter().map(|(satellite, _)| *satellite).collect();
+        ctx.set("satellites", Json(satellites));
+        ctx.set("product_runs", Json(product_runs));
+
         Ok(())
     }
 
     async fn get_position(&self, ctx: SharedObjectContext<'_>, epoch:f64) -> Result<Json<Orbit>, HandlerError> {
 
-        let orbit = ctx.get::<Json<Orbit>>("orbit").await?;
+        let Some(orbit) = ctx.get::<Json<Orbit>>("orbit").await? else {
+            return Err(TerminalError::from(ApiError::NotFound(format!("No orbit for {}", ctx.key()))).into());
+        };
 
-        if orbit.is_some() {
+        let frame = KernelManager::global().earth_frame().map_err(|err| terminal_error(err, ApiError::Internal))?;
 
-            let almanac = Almanac::new("/Users/kpw/shared/urbantraction/ground-control/pck08.pca");
-            if almanac.is_err() {
-                info!("alamanc error: {}", almanac.as_ref().err().unwrap().to_string())
-            }
-            let frame = almanac.as_ref().unwrap().frame_from_uid(EARTH_J2000);
+        let mut position = orbit_position(&orbit.into_inner(), epoch)
+            .map_err(|err| terminal_error(err, ApiError::Unprocessable))?;
 
-            if frame.is_err() {
-                info!("frame error: {}", frame.as_ref().err().unwrap().to_string())
-            }
+        set_latlonalt(&mut position, frame);
+        position.metadata = catalog_entry(&ctx, &position.sv.satellite, epoch).await;
 
-            let frame = frame.unwrap();
+        Ok(Json(position))
+    }
 
-            let position = orbit.unwrap().into_inner().get_position_at(epoch);
+    async fn get_positions(&self, ctx: SharedObjectContext<'_>, epoch:f64) -> Result<Json<Vec<Orbit>>, HandlerError> {
 
-            if position.is_err() {
-                return Err(TerminalError::new(position.err().unwrap().to_string()).into());
-            }
+        let Some(satellites) = ctx.get::<Json<Vec<SatelliteId>>>("satellites").await? else {
+            return Err(TerminalError::from(ApiError::UnknownSource(format!("No orbits for source {}", ctx.key()))).into());
+        };
 
-            let mut position = position.unwrap();
+        let orbits = source_orbits(&ctx, satellites.into_inner()).await?;
 
-            let mut pos_latlonalt:Vec<(f64, f64, f64)> = Vec::new();
+        let frame = KernelManager::global().earth_frame().map_err(|err| terminal_error(err, ApiError::Internal))?;
 
-            for i in 0..position.epochs.len() {
+        // propagation past the product end is CPU bound, keep it off the async workers
+        let positions = tokio::task::spawn_blocking(move || orbit_positions(&orbits, epoch)).await
+            .map_err(|err| TerminalError::from(ApiError::Internal(err.to_string())))?;
 
-                let epoch = position.epochs[i];
-                let pos = position.pos_ecef_km[i];
+        let mut positions:Vec<Orbit> = positions.into_iter().filter_map(Result::ok).collect();
 
-                let epoch = hifitime::Epoch::from_gpst_seconds(epoch);
-                let cartian_state = CartesianState::from_position(pos.x, pos.y, pos.z, epoch, frame);
-                let latlonalt = cartian_state.latlongalt();
+        for position in positions.iter_mut() {
+            set_latlonalt(position, frame);
+        }
 
-                if latlonalt.is_err() {
-                    info!("latlonalt error: {}", latlonalt.err().unwrap().to_string());
-                }
-                else {
-                    pos_latlonalt.push(latlonalt.unwrap());
-                }
+        // one catalog lookup for every satellite
+        let queries:Vec<SatelliteQuery> = positions.iter()
+            .map(|position| SatelliteQuery { sv: position.sv.satellite.to_string(), epoch })
+            .collect();
 
+        let metadata = ctx.object_client::<SatelliteMetadataClient>(CATALOG_KEY)
+            .get_satellites(Json(queries))
+            .call()
+            .await;
+
+        if let Ok(metadata) = metadata {
+            for (position, info) in positions.iter_mut().zip(metadata.into_inner()) {
+                position.metadata = info;
             }
+        }
+
+        Ok(Json(positions))
+    }
+
+    async fn get_orbit(&self, ctx: SharedObjectContext<'_>) -> Result<Json<Orbit>, HandlerError> {
+
+        let orbit = ctx.get::<Json<Orbit>>("orbit").await?;
+
+        if let Some(orbit) = orbit {
+            return Ok(orbit);
+        }
+
+        Err(TerminalError::from(ApiError::NotFound(format!("No orbit for {}", ctx.key()))).into())
+    }
+
+    async fn get_orbits(&self, ctx: SharedObjectContext<'_>, satellites:Json<Vec<SatelliteId>>) -> Result<Json<Vec<Orbit>>, HandlerError> {
+        Ok(Json(source_orbits(&ctx, satellites.into_inner()).await?))
+    }
+
+    async fn get_product_run_id(&self, ctx: SharedObjectContext<'_>) -> Result<u64, HandlerError> {
 
-            position.pos_latlonalt = Some(pos_latlonalt);
+        if let Some(product_run_id) = ctx.get::<u64>("product_run_id").await? {
+            return Ok(product_run_id);
+        }
+
+        match ctx.get::<Json<Orbit>>("orbit").await? {
+            Some(orbit) => Ok(orbit.into_inner().product_run_id),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No orbit for {}", ctx.key()))).into()),
+        }
+    }
 
-            return Ok(Json(position));
+    async fn get_product_runs(&self, ctx: SharedObjectContext<'_>) -> Result<Json<Vec<(SatelliteId, u64)>>, HandlerError> {
 
+        match ctx.get::<Json<Vec<(SatelliteId, u64)>>>("product_runs").await? {
+            Some(product_runs) => Ok(product_runs),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No product runs for {}", ctx.key()))).into()),
         }
+    }
+
+    async fn get_satellite_state(&self, ctx: SharedObjectContext<'_>, epoch:f64) -> Result<Json<SatelliteState>, HandlerError> {
 
-        Err(TerminalError::new("Missing orbit data.").into())
+        let Some(orbit) = ctx.get::<Json<Orbit>>("orbit").await? else {
+            return Err(TerminalError::from(ApiError::NotFound(format!("No orbit for {}", ctx.key()))).into());
+        };
+
+        let orbit = orbit.into_inner();
+        let block = catalog_entry(&ctx, &orbit.sv.satellite, epoch).await
+            .map(|info| info.block_type)
+            .unwrap_or_else(|| BlockType::from_satellite(orbit.sv.satellite));
+
+        let state = KernelManager::global().planetary_almanac()
+            .and_then(|almanac| sun_moon_ecef_km(&almanac, epoch))
+            .and_then(|(sun, moon)| satellite_state(&orbit, epoch, &sun, &moon, block));
+
+        match state {
+            Ok(state) => Ok(Json(state)),
+            Err(err) => Err(terminal_error(err, ApiError::Unprocessable).into()),
+        }
     }
 }
@@ -0,0 +1,89 @@
+use restate_sdk::prelude::*;
+use tracing::info;
+
+use crate::{error::{terminal_error, ApiError}, algo::tle::{compare_tle, fit_mean_elements, Sgp4Comparison, TleConfig, TleFit, TleIdentity, TwoLineElements}, product::{catalog::{SatelliteMetadataClient, SatelliteQuery, CATALOG_KEY}, sv::{SVOrbitsClient, SVSource}}};
+
+#[derive(serde::Serialize, serde::Deserialize, utoipa::ToSchema, Debug, PartialEq, Clone)]
+pub struct Sgp4ComparisonRequest {
+    pub tle:TwoLineElements,
+    // GPST seconds, defaults to the span of the precise orbit
+    #[serde(default)]
+    pub from:Option<f64>,
+    #[serde(default)]
+    pub to:Option<f64>,
+}
+
+// keyed like SVOrbits (source_ac_type_sat), fits and compares against the stored precise orbit
+#[restate_sdk::object]
+pub trait MeanElementSets {
+
+    #[name = "fit"]
+    async fn fit(config:Json<TleConfig>) -> Result<Json<TleFit>, HandlerError>;
+
+    #[shared]
+    #[name = "getFit"]
+    async fn get_fit() -> Result<Json<TleFit>, HandlerError>;
+
+    #[shared]
+    #[name = "compare"]
+    async fn compare(request:Json<Sgp4ComparisonRequest>) -> Result<Json<Sgp4Comparison>, HandlerError>;
+}
+
+pub struct MeanElementSetsImpl;
+
+impl MeanElementSets for MeanElementSetsImpl {
+
+    async fn fit(&self, ctx:ObjectContext<'_>, config:Json<TleConfig>) -> Result<Json<TleFit>, HandlerError> {
+
+        let sv_source = SVSource::from_key(ctx.key())
+            .map_err(|_| TerminalError::from(ApiError::invalid_source(ctx.key())))?;
+
+        let orbit = ctx.object_client::<SVOrbitsClient>(ctx.key()).get_orbit().call().await?.into_inner();
+
+        // NORAD and COSPAR identifiers from the satellite catalog when it is loaded
+        let query = SatelliteQuery { sv: sv_source.satellite.to_string(), epoch: orbit.valid_to };
+        let identity = match ctx.object_client::<SatelliteMetadataClient>(CATALOG_KEY).get_satellite(Json(query)).call().await {
+            Ok(info) => {
+                let info = info.into_inner();
+                TleIdentity {
+                    name: format!("{} ({})", info.block, sv_source.satellite),
+                    norad_id: info.satcat.unwrap_or(0),
+                    cospar_id: info.cospar_id,
+                }
+            }
+            Err(_) => TleIdentity::from_satellite(sv_source.satellite),
+        };
+
+        let fit = fit_mean_elements(&orbit, identity, &config.into_inner())
+            .map_err(|err| terminal_error(err, ApiError::Unprocessable))?;
+
+        info!("fitted mean elements for {} with {:.3} km rms", ctx.key(), fit.statistics.rms_km);
+
+        ctx.set("fit", Json(fit.clone()));
+
+        Ok(Json(fit))
+    }
+
+    async fn get_fit(&self, ctx:SharedObjectContext<'_>) -> Result<Json<TleFit>, HandlerError> {
+
+        match ctx.get::<Json<TleFit>>("fit").await? {
+            Some(fit) => Ok(fit),
+            None => Err(TerminalError::from(ApiError::NotFound(format!("No fitted mean elements for {}", ctx.key()))).into()),
+        }
+    }
+
+    async fn compare(&self, ctx:SharedObjectContext<'_>, request:Json<Sgp4ComparisonRequest>) -> Result<Json<Sgp4Comparison>, HandlerError> {
+
+        let request = request.into_inner();
+
+        let orbit = ctx.object_client::<SVOrbitsClient>(ctx.key()).get_orbit().call().await?.into_inner();
+
+        let from = request.from.unwrap_or(orbit.valid_from);
+        let to = request.to.unwrap_or(orbit.valid_to);
+
+        match compare_tle(&request.tle, &orbit, from, to) {
+            Ok(comparison) => Ok(Json(comparison)),
+            Err(err) => Err(terminal_error(err, ApiError::Unprocessable).into()),
+        }
+    }
+}
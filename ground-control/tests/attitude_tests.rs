@@ -0,0 +1,59 @@
+use anise::math::Vector3;
+use ground_control::algo::{attitude::{attitude, nominal_yaw_deg, yaw, AttitudeMode, BlockType}, sun::beta_and_orbit_angle};
+
+const RADIUS_KM:f64 = 26_560.0;
+// GPS orbit angle rate in deg/s
+const ORBIT_RATE_DEG_S:f64 = 360.0 / 43_082.0;
+
+#[test]
+fn nominal_axes_point_at_sun() {
+
+    let inclination = 55.0_f64.to_radians();
+    let sun = Vector3::new(1.0e8, 0.8e8, 0.3e8);
+
+    for k in 0..12 {
+        let u = (k as f64 * 30.0 + 5.0).to_radians();
+        let pos = RADIUS_KM * Vector3::new(u.cos(), u.sin() * inclination.cos(), u.sin() * inclination.sin());
+        let inertial_vel = 3.874 * Vector3::new(-u.sin(), u.cos() * inclination.cos(), u.cos() * inclination.sin());
+        let vel = inertial_vel - Vector3::new(0.0, 0.0, 7.2921151467e-5).cross(&pos);
+
+        let (beta, orbit_angle) = beta_and_orbit_angle(&pos, &vel, &sun);
+        let attitude = attitude(BlockType::Unknown, &pos, &vel, beta, orbit_angle);
+
+        // +Y is perpendicular to the Sun and +X points at the Sun projected onto the body x/y plane
+        let to_sun = (sun - pos).normalize();
+        let projected = (to_sun - to_sun.dot(&attitude.z_axis) * attitude.z_axis).normalize();
+
+        assert!(attitude.y_axis.dot(&to_sun).abs() < 1e-9);
+        assert!((attitude.x_axis.dot(&projected) - 1.0).abs() < 1e-9);
+        assert!((attitude.z_axis + pos.normalize()).norm() < 1e-12);
+    }
+}
+
+#[test]
+fn rate_limited_noon_turn() {
+
+    // at low beta the nominal yaw rate near noon far exceeds the GPS IIR hardware limit
+    let (yaw_deg, yaw_rate, mode) = yaw(BlockType::GpsIIR, 1.0, 180.0, ORBIT_RATE_DEG_S, RADIUS_KM);
+
+    assert_eq!(mode, AttitudeMode::NoonTurn);
+    assert!((yaw_rate.abs() - 0.2).abs() < 1e-9);
+    assert!((yaw_deg - nominal_yaw_deg(1.0, 180.0)).abs() > 1.0);
+
+    // outside the turn the satellite is back on the nominal law
+    let (yaw_deg, _, mode) = yaw(BlockType::GpsIIR, 1.0, 200.0, ORBIT_RATE_DEG_S, RADIUS_KM);
+
+    assert_eq!(mode, AttitudeMode::YawSteering);
+    assert!((yaw_deg - nominal_yaw_deg(1.0, 200.0)).abs() < 1e-3);
+}
+
+#[test]
+fn orbit_normal_at_low_beta() {
+
+    let (yaw_deg, _, mode) = yaw(BlockType::BeiDou2Meo, 2.0, 90.0, ORBIT_RATE_DEG_S, 27_900.0);
+    assert_eq!(mode, AttitudeMode::OrbitNormal);
+    assert_eq!(yaw_deg, 0.0);
+
+    let (_, _, mode) = yaw(BlockType::BeiDou2Meo, 10.0, 90.0, ORBIT_RATE_DEG_S, 27_900.0);
+    assert_eq!(mode, AttitudeMode::YawSteering);
+}
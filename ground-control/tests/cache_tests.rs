@@ -0,0 +1,125 @@
+mod common;
+
+use std::{cell::Cell, time::Duration};
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::{algo::propagate::{Ecom, OrbitFit, State}, product::{cache::OrbitCache, satellite::SatelliteId, sv::{DataSource, Orbit}}};
+
+// two samples of a satellite in a given product run
+fn product_run(sv:&str, source:&str, product_run_id:u64) -> Orbit {
+    let positions = vec![Vector3::new(26_560.0, 0.0, 0.0), Vector3::new(26_559.0, 115.0, 0.0)];
+    Orbit { product_run_id, ..orbit(sv, source, vec![0.0, 300.0], positions) }
+}
+
+#[test]
+fn freshness_and_invalidation() {
+
+    let cache = OrbitCache::new(Duration::from_secs(60));
+    let g01 = product_run("G01", "cddis_cod_fin", 1);
+    let sv = g01.sv.clone();
+
+    assert!(cache.get(&sv).is_none());
+    cache.insert(g01.clone());
+    assert_eq!(*cache.get(&sv).unwrap(), g01);
+    assert!(cache.is_fresh(&sv.get_key()));
+
+    cache.mark_checked(&sv.data_source.get_key());
+    cache.invalidate(&sv);
+    assert!(cache.get(&sv).is_none());
+    assert!(!cache.is_fresh(&sv.get_key()));
+    // the data source has to be revalidated too
+    assert!(!cache.is_fresh(&sv.data_source.get_key()));
+
+    // revalidated on every request
+    let cache = OrbitCache::new(Duration::ZERO);
+    cache.insert(g01);
+    assert!(cache.get(&sv).is_some());
+    assert!(!cache.is_fresh(&sv.get_key()));
+}
+
+#[test]
+fn product_run_changes() {
+
+    let cache = OrbitCache::new(Duration::from_secs(60));
+    let fin = DataSource::from_key("cddis_cod_fin".to_string()).unwrap();
+    let ult = DataSource::from_key("cddis_cod_ult".to_string()).unwrap();
+
+    for sv in ["G03", "G01", "G02"] {
+        cache.insert(product_run(sv, "cddis_cod_fin", 1));
+    }
+    cache.insert(product_run("G01", "cddis_cod_ult", 1));
+
+    let satellites:Vec<SatelliteId> = cache.source_orbits(&fin).iter().map(|orbit| orbit.sv.satellite).collect();
+    assert_eq!(satellites, ["G01".parse().unwrap(), "G02".parse().unwrap(), "G03".parse().unwrap()]);
+
+    // G02 from a new run, G03 dropped from the product and G04 added
+    let product_runs:Vec<(SatelliteId, u64)> = [("G01", 1), ("G02", 2), ("G04", 2)].iter()
+        .map(|(sv, run)| (sv.parse().unwrap(), *run))
+        .collect();
+
+    let stale = cache.stale_satellites(&fin, &product_runs);
+    assert_eq!(stale, ["G02".parse::<SatelliteId>().unwrap(), "G04".parse().unwrap()]);
+    assert_eq!(cache.source_orbits(&fin).len(), 1);
+    assert_eq!(cache.source_orbits(&ult).len(), 1);
+
+    cache.mark_checked(&fin.get_key());
+    cache.invalidate_source(&fin);
+    assert!(cache.source_orbits(&fin).is_empty());
+    assert!(!cache.is_fresh(&fin.get_key()));
+    assert_eq!(cache.source_orbits(&ult).len(), 1);
+}
+
+#[test]
+fn invalidation_notifies_streams() {
+
+    let cache = OrbitCache::new(Duration::from_secs(60));
+    let mut updates = cache.subscribe();
+    let g01 = product_run("G01", "cddis_cod_fin", 1);
+
+    cache.insert(g01.clone());
+    assert!(updates.try_recv().is_err());
+
+    cache.invalidate(&g01.sv);
+    assert_eq!(updates.try_recv().unwrap(), g01.sv.data_source);
+
+    cache.invalidate_source(&g01.sv.data_source);
+    assert_eq!(updates.try_recv().unwrap(), g01.sv.data_source);
+    assert!(updates.try_recv().is_err());
+}
+
+#[test]
+fn extrapolation_fits_per_product_run() {
+
+    let cache = OrbitCache::new(Duration::from_secs(60));
+    let fits = Cell::new(0);
+    let fit = |orbit:&Orbit| {
+        fits.set(fits.get() + 1);
+        Ok(OrbitFit {
+            state: State { epoch: orbit.valid_to, pos_ecef_km: orbit.pos_ecef_km[1], vel_ecef_km_s: Vector3::new(0.0, 3.9, 0.0) },
+            srp: Ecom::default(),
+            rms_km: 0.0,
+            samples: orbit.epochs.len(),
+        })
+    };
+
+    let run1 = product_run("G01", "cddis_cod_fin", 1);
+    cache.fit(&run1, || fit(&run1)).unwrap();
+    cache.fit(&run1, || fit(&run1)).unwrap();
+    assert_eq!(fits.get(), 1);
+
+    // a new product run is fitted again
+    let run2 = product_run("G01", "cddis_cod_fin", 2);
+    cache.fit(&run2, || fit(&run2)).unwrap();
+    assert_eq!(fits.get(), 2);
+
+    // failed fits are not kept
+    let g02 = product_run("G02", "cddis_cod_fin", 1);
+    assert!(cache.fit(&g02, || Err(anyhow::anyhow!("singular"))).is_err());
+    cache.fit(&g02, || fit(&g02)).unwrap();
+    assert_eq!(fits.get(), 3);
+
+    cache.invalidate_source(&run2.sv.data_source);
+    cache.fit(&run2, || fit(&run2)).unwrap();
+    assert_eq!(fits.get(), 4);
+}
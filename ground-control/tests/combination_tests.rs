@@ -0,0 +1,79 @@
+mod common;
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::{algo::combination::{combine, combined_source, CombinationConfig, Helmert}, product::sv::Orbit};
+
+const RADIUS_KM:f64 = 26_560.0;
+const STEP_S:f64 = 900.0;
+
+// twelve circular orbits in six planes, sampled every 15 minutes over a day
+fn truth(satellite:usize, epoch:f64) -> Vector3 {
+    let raan = (satellite % 6) as f64 * 60f64.to_radians();
+    let inclination = 55f64.to_radians();
+    let u = 2.0 * std::f64::consts::PI * epoch / 43_082.0 + satellite as f64;
+    let (x, y) = (RADIUS_KM * u.cos(), RADIUS_KM * u.sin());
+    Vector3::new(
+        x * raan.cos() - y * inclination.cos() * raan.sin(),
+        x * raan.sin() + y * inclination.cos() * raan.cos(),
+        y * inclination.sin())
+}
+
+fn orbits(analysis_center:&str, transform:impl Fn(usize, Vector3) -> Vector3) -> Vec<Orbit> {
+    let source = format!("cddis_{}_fin", analysis_center);
+    let epochs:Vec<f64> = (0..96).map(|i| i as f64 * STEP_S).collect();
+
+    (0..12).map(|satellite| {
+        let positions = epochs.iter().map(|epoch| transform(satellite, truth(satellite, *epoch))).collect();
+        orbit(&format!("G{:02}", satellite + 1), &source, epochs.clone(), positions)
+    }).collect()
+}
+
+#[test]
+fn helmert_recovers_transformation() {
+
+    let helmert = Helmert { translation_km: [0.0001, -0.0002, 0.00005], scale: 2e-9, rotation_rad: [1e-9, -2e-9, 5e-9] };
+    let pairs:Vec<(Vector3, Vector3)> = (0..12)
+        .flat_map(|satellite| (0..10).map(move |i| truth(satellite, i as f64 * 3_600.0)))
+        .map(|pos| (pos, helmert.apply(&pos)))
+        .collect();
+
+    let estimated = Helmert::estimate(&pairs).unwrap();
+    for i in 0..3 {
+        assert!((estimated.translation_km[i] - helmert.translation_km[i]).abs() < 1e-9);
+        assert!((estimated.rotation_rad[i] - helmert.rotation_rad[i]).abs() < 1e-13);
+    }
+    assert!((estimated.scale - helmert.scale).abs() < 1e-13);
+}
+
+#[test]
+fn combination_aligns_and_rejects_outliers() {
+
+    // one AC in a shifted frame, one with a 1 m along-z error on G03
+    let shift = Helmert { translation_km: [0.0002, 0.0, -0.0001], scale: 0.0, rotation_rad: [0.0, 0.0, 3e-9] };
+    let mut inputs = orbits("cod", |_, pos| pos);
+    inputs.extend(orbits("esa", |_, pos| shift.apply(&pos)));
+    inputs.extend(orbits("gfz", |satellite, pos| if satellite == 2 { pos + Vector3::new(0.0, 0.0, 0.001) } else { pos }));
+    inputs.extend(orbits("jpl", |_, pos| pos));
+
+    let target = combined_source(&[inputs[0].sv.data_source.clone()]).unwrap();
+    assert_eq!(target.get_key(), "cddis_cmb_fin");
+
+    let combination = combine(&inputs, &target, &CombinationConfig::default()).unwrap();
+    assert_eq!(combination.orbits.len(), 12);
+    assert!(combination.report.skipped_satellites.is_empty());
+
+    let rejected:Vec<String> = combination.report.contributions.iter()
+        .filter(|c| c.rejected)
+        .map(|c| format!("{}:{}", c.data_source.analysis_center, c.satellite))
+        .collect();
+    assert_eq!(rejected, ["gfz:G03"]);
+
+    for (satellite, orbit) in combination.orbits.iter().enumerate() {
+        assert_eq!(orbit.sv.data_source, target);
+        for (epoch, pos) in orbit.epochs.iter().zip(&orbit.pos_ecef_km) {
+            // aligned to within a millimeter of the truth
+            assert!((pos - truth(satellite, *epoch)).norm() < 1e-6);
+        }
+    }
+}
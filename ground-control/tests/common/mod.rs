@@ -0,0 +1,69 @@
+// Fixtures shared by the integration tests, each test file uses only some of them
+#![allow(dead_code)]
+
+use anise::math::Vector3;
+use ground_control::{algo::ephemeris::BroadcastEphemeris, product::sv::{DataSource, Orbit, SVSource}};
+
+// radius and angular rate of a GPS like circular orbit
+pub const GPS_RADIUS_KM:f64 = 26_560.0;
+pub const GPS_RATE_RAD_S:f64 = 1.4584e-4;
+
+// product run 1 of a satellite of a data source key, sampled at evenly spaced epochs
+pub fn orbit(sv:&str, source:&str, epochs:Vec<f64>, pos_ecef_km:Vec<Vector3>) -> Orbit {
+    Orbit {
+        sv: SVSource { satellite: sv.parse().unwrap(), data_source: DataSource::from_key(source.to_string()).unwrap() },
+        product_run_id: 1,
+        sampling_resolution: epochs[1] - epochs[0],
+        valid_from: epochs[0],
+        valid_to: epochs[epochs.len() - 1],
+        epochs,
+        pos_ecef_km,
+        pos_latlonalt: None,
+        clock_usec: None,
+        metadata: None,
+        extrapolated: false,
+    }
+}
+
+// two hours of an equatorial circular orbit sampled every five minutes
+pub fn circular_orbit(sv:&str, source:&str, start:f64) -> Orbit {
+
+    let epochs:Vec<f64> = (0..=24).map(|i| start + i as f64 * 300.0).collect();
+    let positions = epochs.iter()
+        .map(|epoch| {
+            let angle = GPS_RATE_RAD_S * (epoch - start);
+            Vector3::new(GPS_RADIUS_KM * angle.cos(), GPS_RADIUS_KM * angle.sin(), 0.0)
+        })
+        .collect();
+
+    orbit(sv, source, epochs, positions)
+}
+
+// GPS LNAV style ephemeris valid four hours around toe
+pub fn broadcast(satellite:&str, toe:f64) -> BroadcastEphemeris {
+    BroadcastEphemeris {
+        satellite: satellite.parse().unwrap(),
+        iode: 42,
+        toe,
+        toc: toe,
+        sqrt_a: 5_153.65,
+        e: 0.0095,
+        i0: 0.962,
+        omega0: -2.1,
+        omega: 0.71,
+        m0: 1.2,
+        delta_n: 4.5e-9,
+        idot: 1.2e-10,
+        omega_dot: -8.1e-9,
+        cuc: 1.1e-6,
+        cus: 8.2e-6,
+        crc: 210.0,
+        crs: 21.5,
+        cic: 1.3e-7,
+        cis: -5.2e-8,
+        af0: 2.5e-4,
+        af1: -3.4e-12,
+        af2: 0.0,
+        fit_interval_s: 14_400.0,
+    }
+}
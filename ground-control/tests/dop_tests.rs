@@ -0,0 +1,37 @@
+use ground_control::algo::{dop::dop, look::LookAngles};
+
+fn look(azimuth_deg:f64, elevation_deg:f64) -> LookAngles {
+    LookAngles {
+        satellite: "G01".parse().unwrap(),
+        epoch: 0.0,
+        azimuth_deg,
+        elevation_deg,
+        range_km: 20_000.0,
+        range_rate_km_s: 0.0,
+        light_time_s: 0.067,
+    }
+}
+
+#[test]
+fn dop_zenith_and_horizon_triangle() {
+
+    // zenith plus three horizon satellites 120 degrees apart has a closed form cofactor matrix
+    let geometry = vec![look(0.0, 90.0), look(0.0, 0.0), look(120.0, 0.0), look(240.0, 0.0)];
+
+    let dop = dop(&geometry).unwrap();
+
+    assert!((dop.hdop - (4.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+    assert!((dop.vdop - (4.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+    assert!((dop.pdop - (8.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+    assert!((dop.tdop - (1.0_f64 / 3.0).sqrt()).abs() < 1e-9);
+    assert!((dop.gdop - 3.0_f64.sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn dop_requires_four_satellites() {
+
+    assert!(dop(&[look(0.0, 90.0), look(0.0, 10.0), look(120.0, 10.0)]).is_none());
+
+    // all satellites in the same direction give a singular geometry
+    assert!(dop(&[look(45.0, 30.0), look(45.0, 30.0), look(45.0, 30.0), look(45.0, 30.0)]).is_none());
+}
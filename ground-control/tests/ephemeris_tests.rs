@@ -0,0 +1,63 @@
+mod common;
+
+use anise::math::Vector3;
+use common::{broadcast, orbit};
+use ground_control::{algo::{ephemeris::{fit_ephemerides, BroadcastEphemeris, EphemerisConfig}, gravity::GravityModel, look::EARTH_ROTATION_RAD_S, propagate::{Ecom, ForceModel, State}}, product::sv::Orbit};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+#[test]
+fn recovers_broadcast_ephemeris() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let truth = BroadcastEphemeris { iode: 0, ..broadcast("G12", start + 7_200.0) };
+
+    let epochs:Vec<f64> = (0..=48).map(|i| start + i as f64 * STEP_S).collect();
+    let positions = epochs.iter().map(|epoch| truth.position_ecef_km(*epoch)).collect();
+    let clocks = epochs.iter().map(|epoch| truth.clock_offset_s(*epoch) * 1e6).collect();
+
+    let fits = fit_ephemerides(&Orbit { clock_usec: Some(clocks), ..orbit("G12", "cddis_cod_ult", epochs.clone(), positions) }, &EphemerisConfig::default()).unwrap();
+    assert_eq!(fits.len(), 1);
+
+    let fit = &fits[0];
+    assert_eq!(fit.ephemeris.toe, truth.toe);
+    assert_eq!(fit.residuals.len(), 49);
+    assert!(fit.rms_m < 1e-3);
+    assert!(fit.clock_rms_ns.unwrap() < 1e-3);
+    assert!((fit.ephemeris.af0 - truth.af0).abs() < 1e-12);
+    assert!((fit.ephemeris.af1 - truth.af1).abs() < 1e-16);
+
+    for epoch in epochs {
+        assert!((fit.ephemeris.position_ecef_km(epoch) - truth.position_ecef_km(epoch)).norm() < 1e-6);
+    }
+}
+
+#[test]
+fn fits_propagated_orbit() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+
+    let pos = Vector3::new(0.0, 26_560.0, 0.0);
+    let inclination = 55f64.to_radians();
+    let inertial_vel = 3.874 * Vector3::new(-inclination.cos(), 0.0, inclination.sin());
+    let vel = inertial_vel - Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(&pos);
+
+    let force_model = ForceModel::new(&GravityModel::egm2008(), Ecom::a_priori());
+    let epochs:Vec<f64> = (0..=96).map(|i| start + i as f64 * STEP_S).collect();
+    let positions = force_model.propagate(&State { epoch: start, pos_ecef_km: pos, vel_ecef_km_s: vel }, &epochs, 60.0)
+        .iter()
+        .map(|state| state.pos_ecef_km)
+        .collect();
+
+    let fits = fit_ephemerides(&orbit("G12", "cddis_cod_ult", epochs, positions), &EphemerisConfig::default()).unwrap();
+    assert_eq!(fits.len(), 2);
+
+    for fit in &fits {
+        // decimeter level, the 15 parameters cannot follow the full force model over 4 hours
+        assert!(fit.rms_m < 0.5, "rms {}", fit.rms_m);
+        assert!(fit.clock_rms_ns.is_none());
+        assert!(fit.residuals.iter().all(|residual| residual.clock_ns.is_none()));
+    }
+    assert_ne!(fits[0].ephemeris.iode, fits[1].ephemeris.iode);
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use common::circular_orbit;
+use ground_control::{algo::{gravity::GravityModel, propagate::{extrapolate, PropagatorConfig}}, error::ApiError};
+
+#[test]
+fn status_and_kind() {
+
+    let errors = [
+        (ApiError::invalid_satellite("X99"), 400, "invalid_satellite"),
+        (ApiError::invalid_source("cod"), 400, "invalid_source"),
+        (ApiError::InvalidEpoch("Invalid epoch soon".to_string()), 400, "invalid_epoch"),
+        (ApiError::unsupported_format("xls"), 400, "invalid_request"),
+        (ApiError::UnknownSource("No orbits for source cddis_cod_fin".to_string()), 404, "unknown_source"),
+        (ApiError::NotFound("No orbit for cddis_cod_fin_G01".to_string()), 404, "not_found"),
+        (ApiError::OutsideCoverage("Epoch 1 outside the orbit".to_string()), 416, "outside_coverage"),
+        (ApiError::Unprocessable("Not enough samples".to_string()), 422, "unprocessable"),
+        (ApiError::BackendUnavailable("connection refused".to_string()), 503, "backend_unavailable"),
+        (ApiError::Internal("panicked".to_string()), 500, "internal"),
+    ];
+
+    for (error, status, kind) in errors {
+        assert_eq!(error.status(), status);
+        assert_eq!(error.kind(), kind);
+    }
+
+    let body = serde_json::to_value(ApiError::invalid_satellite("X99").body()).unwrap();
+    assert_eq!(body, serde_json::json!({"error": "invalid_satellite", "message": "Invalid satellite X99"}));
+}
+
+#[test]
+fn kinds_from_handler_status() {
+
+    // terminal errors of the handlers come back from the ingress with the status of their kind
+    for error in [ApiError::NotFound("a".to_string()), ApiError::OutsideCoverage("b".to_string()), ApiError::Unprocessable("c".to_string()), ApiError::Internal("d".to_string())] {
+        assert_eq!(ApiError::from_status(error.status(), error.message().to_string()), error);
+    }
+
+    assert_eq!(ApiError::from_status(400, "bad".to_string()).status(), 400);
+    assert!(matches!(ApiError::from_status(502, String::new()), ApiError::BackendUnavailable(_)));
+    assert!(matches!(ApiError::from_status(504, String::new()), ApiError::BackendUnavailable(_)));
+
+    // untyped errors take the fallback kind
+    let typed = anyhow::Error::from(ApiError::OutsideCoverage("late".to_string()));
+    assert_eq!(ApiError::from_anyhow(typed, ApiError::Unprocessable), ApiError::OutsideCoverage("late".to_string()));
+    assert_eq!(ApiError::from_anyhow(anyhow::anyhow!("singular"), ApiError::Unprocessable), ApiError::Unprocessable("singular".to_string()));
+    assert_eq!(ApiError::from(anyhow::anyhow!("failed")), ApiError::Internal("failed".to_string()));
+}
+
+#[test]
+fn epochs_outside_coverage() {
+
+    fn kind<T>(result:Result<T, anyhow::Error>) -> Result<(), &'static str> {
+        result.map(|_| ()).map_err(|err| ApiError::from(err).kind())
+    }
+
+    let orbit = circular_orbit("G01", "cddis_cod_fin", 0.0);
+
+    assert_eq!(kind(orbit.get_position_at(3600.0)), Ok(()));
+    assert_eq!(kind(orbit.get_position_at(-600.0)), Err("outside_coverage"));
+    assert_eq!(kind(orbit.get_position_at(7000.0)), Err("outside_coverage"));
+    assert_eq!(kind(orbit.position_at(7200.0)), Err("outside_coverage"));
+    assert_eq!(kind(orbit.state_at(8000.0)), Err("outside_coverage"));
+
+    let config = PropagatorConfig::default();
+    let late = orbit.valid_to + config.max_extrapolation_s + 1.0;
+    assert_eq!(kind(extrapolate(&orbit, late, &GravityModel::egm2008().truncate(2), &config)), Err("outside_coverage"));
+}
@@ -0,0 +1,146 @@
+use ground_control::{algo::pass::Pass, gpst::{day_of_year, from_calendar, from_day_of_year, from_system_seconds, from_week_tow, iso_in, parse_epoch, resolve_week_rollover, system_seconds, to_calendar, week_tow, InTimeSystem, TimeSystem}, product::satellite::Constellation};
+use hifitime::{Epoch, TimeScale};
+
+#[test]
+fn week_numbering_per_system() {
+
+    // Wednesday of GPS week 2312
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST);
+
+    assert_eq!(week_tow(&epoch, TimeSystem::Gpst).unwrap(), (2312, 3.0 * 86_400.0));
+    assert_eq!(week_tow(&epoch, TimeSystem::Qzsst).unwrap(), (2312, 3.0 * 86_400.0));
+    assert_eq!(week_tow(&epoch, TimeSystem::Gst).unwrap(), (1288, 3.0 * 86_400.0));
+    assert_eq!(week_tow(&epoch, TimeSystem::Bdt).unwrap(), (956, 3.0 * 86_400.0 - 14.0));
+    assert!(week_tow(&epoch, TimeSystem::Glonasst).is_err());
+
+    for system in [TimeSystem::Gpst, TimeSystem::Gst, TimeSystem::Bdt] {
+        let (week, tow_s) = week_tow(&epoch, system).unwrap();
+        assert_eq!(from_week_tow(system, week, tow_s).unwrap(), epoch);
+    }
+
+    // BDT week 0 starts 2006-01-01 00:00:00 BDT, 14 s after GPST midnight
+    let bdt_origin = from_week_tow(TimeSystem::Bdt, 0, 0.0).unwrap();
+    assert_eq!(bdt_origin.to_gpst_seconds(), Epoch::from_gregorian_at_midnight(2006, 1, 1, TimeScale::GPST).to_gpst_seconds() + 14.0);
+}
+
+#[test]
+fn offsets_between_systems() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC);
+
+    assert_eq!(system_seconds(&epoch, TimeSystem::Gpst) - system_seconds(&epoch, TimeSystem::Utc), 18.0);
+    assert_eq!(system_seconds(&epoch, TimeSystem::Tai) - system_seconds(&epoch, TimeSystem::Gpst), 19.0);
+    assert_eq!(system_seconds(&epoch, TimeSystem::Glonasst) - system_seconds(&epoch, TimeSystem::Utc), 10_800.0);
+    assert_eq!(to_calendar(&epoch, TimeSystem::Glonasst), (2024, 5, 1, 3, 0, 0.0));
+    assert_eq!(to_calendar(&epoch, TimeSystem::Gpst), (2024, 5, 1, 0, 0, 18.0));
+
+    // before the leap second at the end of 2016
+    let epoch = Epoch::from_gregorian_at_midnight(2016, 6, 1, TimeScale::UTC);
+    assert_eq!(system_seconds(&epoch, TimeSystem::Gpst) - system_seconds(&epoch, TimeSystem::Utc), 17.0);
+
+    for system in [TimeSystem::Gpst, TimeSystem::Gst, TimeSystem::Bdt, TimeSystem::Glonasst, TimeSystem::Qzsst, TimeSystem::Tai, TimeSystem::Utc] {
+        assert_eq!(from_system_seconds(system_seconds(&epoch, system), system).unwrap(), epoch);
+        assert_eq!(system.to_string().parse::<TimeSystem>().unwrap(), system);
+    }
+
+    // epoch lines of an SP3 file in GLONASS time
+    assert_eq!(from_calendar(TimeSystem::Glonasst, 2016, 6, 1, 3, 0, 0.0).unwrap(), epoch);
+    assert!(from_calendar(TimeSystem::Gpst, 2023, 2, 29, 0, 0, 0.0).is_err());
+    assert_eq!(TimeSystem::for_constellation(Constellation::BeiDou), TimeSystem::Bdt);
+    assert!("XYZ".parse::<TimeSystem>().is_err());
+}
+
+#[test]
+fn rollover_resolution() {
+
+    let reference = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST);
+
+    assert_eq!(resolve_week_rollover(TimeSystem::Gpst, 2312 % 1024, &reference).unwrap(), 2312);
+    assert_eq!(resolve_week_rollover(TimeSystem::Gst, 1288, &reference).unwrap(), 1288);
+    assert_eq!(resolve_week_rollover(TimeSystem::Bdt, 956, &reference).unwrap(), 956);
+
+    // a week broadcast just before the rollover, received just after it
+    let reference = from_week_tow(TimeSystem::Gpst, 2049, 0.0).unwrap();
+    assert_eq!(resolve_week_rollover(TimeSystem::Gpst, 1023, &reference).unwrap(), 2047);
+    assert_eq!(resolve_week_rollover(TimeSystem::Gpst, 1, &reference).unwrap(), 2049);
+}
+
+#[test]
+fn day_of_year_conversions() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST);
+    assert_eq!(day_of_year(&epoch, TimeSystem::Gpst), (2024, 122, 0.0));
+    assert_eq!(from_day_of_year(TimeSystem::Gpst, 2024, 122, 0.0).unwrap(), epoch);
+
+    // already the next year in GLONASS time
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 12, 31, TimeScale::UTC).to_gpst_seconds() + 22.0 * 3_600.0;
+    let epoch = Epoch::from_gpst_seconds(epoch);
+    assert_eq!(day_of_year(&epoch, TimeSystem::Utc), (2024, 366, 22.0 * 3_600.0));
+    assert_eq!(day_of_year(&epoch, TimeSystem::Glonasst), (2025, 1, 3_600.0));
+    assert_eq!(from_day_of_year(TimeSystem::Glonasst, 2025, 1, 3_600.0).unwrap(), epoch);
+}
+
+#[test]
+fn epoch_inputs() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC);
+
+    let inputs = [
+        epoch.to_gpst_seconds().to_string(),
+        "2024-05-01T00:00:00Z".to_string(),
+        "2024-05-01T00:00:00.000".to_string(),
+        "2024-05-01".to_string(),
+        "2024-05-01T00:00:18 GPS".to_string(),
+        "2024-05-01T03:00 GLO".to_string(),
+        "unix:1714521600".to_string(),
+        "mjd:60431".to_string(),
+        "week:2312:259218".to_string(),
+        iso_in(&epoch, TimeSystem::Bdt),
+    ];
+
+    for input in inputs {
+        assert_eq!(parse_epoch(&input).unwrap(), epoch, "{}", input);
+    }
+
+    assert_eq!(iso_in(&epoch, TimeSystem::Gpst), "2024-05-01T00:00:18.000000 GPS");
+    assert!(parse_epoch("now").is_ok());
+
+    for input in ["2024-13-01", "yesterday", "week:2312", "unix:abc", "2024-05-01T00:00:00 XYZ"] {
+        assert!(parse_epoch(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+fn response_epochs() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC).to_gpst_seconds();
+    let pass = Pass {
+        satellite: "G05".parse().unwrap(),
+        rise: None,
+        rise_azimuth_deg: None,
+        culmination: epoch,
+        peak_elevation_deg: 80.0,
+        peak_azimuth_deg: 120.0,
+        set: Some(epoch + 60.0),
+        set_azimuth_deg: Some(200.0),
+    };
+
+    // GPST seconds outside a response, so stored state round trips
+    let json = serde_json::to_value(&pass).unwrap();
+    assert_eq!(json["culmination"], epoch);
+    assert_eq!(serde_json::from_value::<Pass>(json).unwrap(), pass);
+
+    let json = serde_json::to_value(InTimeSystem(&pass, Some(TimeSystem::Utc))).unwrap();
+    assert_eq!(json["culmination"], "2024-05-01T00:00:00.000000 UTC");
+    assert_eq!(json["set"], "2024-05-01T00:01:00.000000 UTC");
+    assert!(json["rise"].is_null());
+    // other numbers are untouched
+    assert_eq!(json["peak_elevation_deg"], 80.0);
+
+    // nested in a response, or GPST seconds without a time system
+    let json = serde_json::to_value(InTimeSystem(&vec![Some(pass.clone())], Some(TimeSystem::Gpst))).unwrap();
+    assert_eq!(json[0]["culmination"], "2024-05-01T00:00:18.000000 GPS");
+    assert_eq!(serde_json::to_value(InTimeSystem(&pass, None)).unwrap()["culmination"], epoch);
+
+    assert_eq!(serde_json::to_value(&pass).unwrap()["culmination"], epoch);
+}
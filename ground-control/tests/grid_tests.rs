@@ -0,0 +1,122 @@
+mod common;
+
+use std::collections::HashMap;
+
+use anise::math::Vector3;
+use arrow_schema::DataType;
+use common::{orbit, GPS_RADIUS_KM};
+use ground_control::{algo::{grid::{visibility_grid, GridField, VisibilityGrid}, pass::ElevationMask, util::latlon_to_ecef}, product::sv::Orbit};
+
+// satellite held above a sub-satellite point for an hour
+fn fixed_orbit(sv:&str, lat_deg:f64, lon_deg:f64) -> Orbit {
+
+    let (x, y, z) = latlon_to_ecef(lat_deg.to_radians(), lon_deg.to_radians(), 0.0);
+    let position = Vector3::new(x, y, z).normalize() * GPS_RADIUS_KM;
+    let epochs:Vec<f64> = (0..=24).map(|i| i as f64 * 150.0).collect();
+    let positions = vec![position; epochs.len()];
+
+    orbit(sv, "cddis_cod_fin", epochs, positions)
+}
+
+fn value_at(grid:&VisibilityGrid, lat_deg:f64, lon_deg:f64) -> (u32, Option<f64>) {
+    let i = grid.lats_deg.iter().position(|lat| *lat == lat_deg).unwrap();
+    let j = grid.lons_deg.iter().position(|lon| *lon == lon_deg).unwrap();
+    let index = i * grid.lons_deg.len() + j;
+    (grid.visible[index], grid.pdop[index])
+}
+
+#[test]
+fn visibility_at_known_geometry() {
+
+    let orbits = vec![fixed_orbit("G01", 0.0, 0.0)];
+    let grid = visibility_grid(&orbits, 1800.0, 10.0, &ElevationMask::new(0.0), &[]);
+
+    assert_eq!(grid.lats_deg.len(), 19);
+    assert_eq!(grid.lons_deg.len(), 37);
+    assert_eq!(grid.visible.len(), 19 * 37);
+
+    // seen at zenith below the satellite, not from the antipode or the poles
+    assert_eq!(value_at(&grid, 0.0, 0.0), (1, None));
+    assert_eq!(value_at(&grid, 0.0, 180.0).0, 0);
+    assert_eq!(value_at(&grid, 90.0, 0.0).0, 0);
+    // the horizon of a GPS satellite is about 76 degrees from its sub-satellite point
+    assert_eq!(value_at(&grid, 0.0, 70.0).0, 1);
+    assert_eq!(value_at(&grid, 0.0, 80.0).0, 0);
+    // the 180 column repeats -180
+    assert_eq!(value_at(&grid, 30.0, 180.0), value_at(&grid, 30.0, -180.0));
+
+    // zenith plus three satellites low in the sky around the equator point
+    let orbits = vec![
+        fixed_orbit("G01", 0.0, 0.0),
+        fixed_orbit("G02", 50.0, 0.0),
+        fixed_orbit("G03", -25.0, 45.0),
+        fixed_orbit("G04", -25.0, -45.0),
+    ];
+
+    let grid = visibility_grid(&orbits, 1800.0, 10.0, &ElevationMask::new(0.0), &[]);
+    let (visible, pdop) = value_at(&grid, 0.0, 0.0);
+    assert_eq!(visible, 4);
+    assert!(pdop.is_some_and(|pdop| pdop > 1.0 && pdop < 10.0));
+
+    // a mask hides the low satellites
+    let grid = visibility_grid(&orbits, 1800.0, 10.0, &ElevationMask::new(40.0), &[]);
+    assert_eq!(value_at(&grid, 0.0, 0.0), (1, None));
+}
+
+#[test]
+fn contours_close() {
+
+    let orbits = vec![fixed_orbit("G01", 0.0, 0.0)];
+    let grid = visibility_grid(&orbits, 1800.0, 5.0, &ElevationMask::new(0.0), &[]);
+    let contours = grid.contours(GridField::Visible, &[0.5]);
+
+    let features = contours["features"].as_array().unwrap();
+    assert_eq!(features.len(), 1);
+    assert_eq!(features[0]["properties"]["level"], 0.5);
+
+    let segments = features[0]["geometry"]["coordinates"].as_array().unwrap();
+    assert!(!segments.is_empty());
+
+    // every segment end is shared with exactly one other segment, so the segments form closed rings
+    let mut ends:HashMap<(i64, i64), usize> = HashMap::new();
+    for segment in segments {
+        for point in segment.as_array().unwrap() {
+            let lon = point[0].as_f64().unwrap();
+            let lat = point[1].as_f64().unwrap();
+            // inside the visibility cap around the sub-satellite point
+            assert!(lon.abs() < 80.0 && lat.abs() < 80.0);
+            *ends.entry(((lon * 1e6).round() as i64, (lat * 1e6).round() as i64)).or_default() += 1;
+        }
+    }
+
+    assert!(ends.values().all(|count| *count == 2));
+
+    // PDOP is undefined everywhere with one satellite
+    let contours = grid.contours(GridField::Pdop, &[2.0]);
+    assert!(contours["features"][0]["geometry"]["coordinates"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn arrow_schema() {
+
+    let schema = VisibilityGrid::arrow_schema();
+    let fields:Vec<(&str, &DataType, bool)> = schema.fields().iter()
+        .map(|field| (field.name().as_str(), field.data_type(), field.is_nullable()))
+        .collect();
+
+    assert_eq!(fields, [
+        ("lat_deg", &DataType::Float64, false),
+        ("lon_deg", &DataType::Float64, false),
+        ("visible", &DataType::UInt32, false),
+        ("pdop", &DataType::Float64, true),
+    ]);
+
+    let orbits = vec![fixed_orbit("G01", 0.0, 0.0)];
+    let grid = visibility_grid(&orbits, 1800.0, 30.0, &ElevationMask::new(0.0), &[]);
+    let batch = grid.to_record_batch().unwrap();
+
+    assert_eq!(batch.num_rows(), 7 * 13);
+    assert_eq!(batch.schema(), schema);
+    // one satellite never gives a PDOP
+    assert_eq!(batch.column(3).null_count(), 7 * 13);
+}
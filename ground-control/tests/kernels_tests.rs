@@ -0,0 +1,80 @@
+use std::{fs, path::Path, sync::Arc, time::{Duration, SystemTime}};
+
+use anise::prelude::Almanac;
+use ground_control::product::kernels::KernelManager;
+
+#[test]
+fn embedded_pck_provides_earth_shape() {
+
+    let kernels = KernelManager::new(Vec::new());
+
+    let frame = kernels.earth_frame().unwrap();
+    assert!(frame.shape.is_some());
+
+    // shared between requests until the files change
+    let almanac = kernels.almanac().unwrap();
+    assert!(Arc::ptr_eq(&almanac, &kernels.almanac().unwrap()));
+
+    // unchanged files are not loaded again
+    assert!(!kernels.refresh().unwrap());
+    assert!(Arc::ptr_eq(&almanac, &kernels.almanac().unwrap()));
+
+    // no planetary ephemerides without a BSP
+    assert!(kernels.planetary_almanac().is_err());
+}
+
+#[test]
+fn missing_kernel_file() {
+
+    let kernels = KernelManager::new(vec!["/nonexistent/de440.bsp".to_string()]);
+
+    assert!(kernels.almanac().is_err());
+    assert!(kernels.planetary_almanac().is_err());
+    assert!(kernels.refresh().is_err());
+}
+
+// waits for the background check to swap the almanac, false after a few intervals without a swap
+async fn swapped(kernels:&KernelManager, almanac:&Arc<Almanac>) -> bool {
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        if !Arc::ptr_eq(almanac, &kernels.almanac().unwrap()) {
+            return true;
+        }
+    }
+    false
+}
+
+// rewrites a kernel file with a later modification time than the last load
+fn replace(path:&Path, contents:&[u8], minutes:u64) {
+    fs::write(path, contents).unwrap();
+    let modified = SystemTime::now() + Duration::from_secs(60 * minutes);
+    fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn background_refresh_swaps_changed_kernels() {
+
+    let pck = fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/../pck08.pca")).unwrap();
+    let dir = std::env::temp_dir().join(format!("kernels_tests_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("pck08.pca");
+    fs::write(&path, &pck).unwrap();
+
+    let kernels:&'static KernelManager = Box::leak(Box::new(KernelManager::new(vec![path.to_string_lossy().to_string()])));
+    let almanac = kernels.almanac().unwrap();
+    let task = kernels.spawn_refresh(Duration::from_millis(20));
+
+    // nothing changed
+    assert!(!swapped(kernels, &almanac).await);
+
+    replace(&path, &pck, 1);
+    assert!(swapped(kernels, &almanac).await);
+
+    // a kernel that fails to load keeps the current one
+    let almanac = kernels.almanac().unwrap();
+    replace(&path, b"not a kernel", 2);
+    assert!(!swapped(kernels, &almanac).await);
+
+    task.abort();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::{algo::look::{look_angles, Observer}, product::sv::Orbit};
+
+// satellite held fixed in ECEF, e.g. an idealised geostationary satellite
+fn fixed_orbit(pos_ecef_km:Vector3) -> Orbit {
+
+    let epochs:Vec<f64> = (0..288).map(|i| i as f64 * 300.0).collect();
+    let positions = vec![pos_ecef_km; epochs.len()];
+
+    orbit("G01", "cddis_cod_ult", epochs, positions)
+}
+
+#[test]
+fn look_angles_zenith() {
+
+    let orbit = fixed_orbit(Vector3::new(26_560.0, 0.0, 0.0));
+    let observer = Observer::Geodetic { lat_deg: 0.0, lon_deg: 0.0, alt_m: 0.0 };
+
+    let look = look_angles(&observer, &orbit, 43_200.0).unwrap();
+
+    assert!(look.elevation_deg > 89.9);
+    assert!((look.range_km - (26_560.0 - 6_378.137)).abs() < 0.1);
+    assert!((look.light_time_s - look.range_km / 299_792.458).abs() < 1e-9);
+}
+
+#[test]
+fn look_angles_azimuth() {
+
+    // satellite due east of an equatorial observer and well above the horizon
+    let orbit = fixed_orbit(Vector3::new(20_000.0, 15_000.0, 0.0));
+    let observer = Observer::Ecef { x_m: 6_378_137.0, y_m: 0.0, z_m: 0.0 };
+
+    let look = look_angles(&observer, &orbit, 43_200.0).unwrap();
+
+    assert!((look.azimuth_deg - 90.0).abs() < 0.01);
+    assert!(look.elevation_deg > 0.0 && look.elevation_deg < 90.0);
+    assert!(look.range_rate_km_s.abs() < 1e-6);
+}
+
+#[test]
+fn look_angles_outside_coverage() {
+
+    let orbit = fixed_orbit(Vector3::new(26_560.0, 0.0, 0.0));
+    let observer = Observer::Geodetic { lat_deg: 0.0, lon_deg: 0.0, alt_m: 0.0 };
+
+    assert!(look_angles(&observer, &orbit, orbit.valid_to + 3600.0).is_err());
+}
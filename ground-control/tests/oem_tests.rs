@@ -0,0 +1,94 @@
+mod common;
+
+use anise::math::Vector3;
+use common::{circular_orbit, GPS_RADIUS_KM, GPS_RATE_RAD_S};
+use ground_control::data::{czml::{availability, czml}, oem::{oem_kvn, oem_xml, EphemerisSegment, INTERPOLATION_DEGREE}};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+#[test]
+fn states_over_range() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let orbit = circular_orbit("G07", "cddis_cod_ult", start);
+
+    // product samples, clipped to the orbit
+    let states = orbit.states(start - 600.0, start + 3_600.0, None).unwrap();
+    assert_eq!(states.len(), 13);
+    assert_eq!(states[0].epoch, start);
+
+    // resampled, including both ends of the orbit
+    let states = orbit.states(start, start + 7_200.0, Some(60.0)).unwrap();
+    assert_eq!(states.len(), 121);
+    for state in [states[0], states[37], states[120]] {
+        let angle = GPS_RATE_RAD_S * (state.epoch - start);
+        let velocity = Vector3::new(-angle.sin(), angle.cos(), 0.0) * GPS_RADIUS_KM * GPS_RATE_RAD_S;
+        assert!((state.pos_ecef_km.norm() - GPS_RADIUS_KM).abs() < 1e-3);
+        assert!((state.vel_ecef_km_s - velocity).norm() < 1e-5);
+    }
+
+    assert!(orbit.state_at(start + 7_201.0).is_err());
+    assert!(orbit.states(start, start + 600.0, Some(0.0)).is_err());
+}
+
+#[test]
+fn oem_messages() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC).to_gpst_seconds();
+    let segment = EphemerisSegment::from_orbit(&circular_orbit("G07", "cddis_cod_ult", start), start, start + 600.0, None).unwrap();
+    let creation = Epoch::from_gpst_seconds(start);
+
+    // a segment without states would have no START_TIME or STOP_TIME
+    let empty = EphemerisSegment { satellite: "G08".parse().unwrap(), states: Vec::new() };
+
+    let kvn = oem_kvn(&[segment.clone(), empty.clone()], &creation);
+    let lines:Vec<&str> = kvn.lines().collect();
+    assert_eq!(lines[0], "CCSDS_OEM_VERS = 2.0");
+    assert_eq!(lines[1], "CREATION_DATE = 2024-05-01T00:00:00.000000");
+    assert!(kvn.contains("OBJECT_NAME = G07\n"));
+    assert!(kvn.contains("REF_FRAME = ITRF\n"));
+    assert!(kvn.contains("START_TIME = 2024-05-01T00:00:00.000000\n"));
+    assert!(kvn.contains("STOP_TIME = 2024-05-01T00:10:00.000000\n"));
+    assert!(kvn.contains(&format!("INTERPOLATION_DEGREE = {}\n", INTERPOLATION_DEGREE)));
+    assert_eq!(kvn.matches("META_START").count(), 1);
+    assert!(!kvn.contains("G08"));
+    assert!(kvn.contains("META_STOP\n\n2024-05-01T00:00:00.000000 26560.000000 0.000000 0.000000 "));
+    assert_eq!(kvn.lines().filter(|line| line.starts_with("2024-05-01T")).count(), 3);
+
+    let xml = oem_xml(&[empty, segment], &creation);
+    assert!(xml.contains("<oem id=\"CCSDS_OEM_VERS\" version=\"2.0\">"));
+    assert!(xml.contains("<TIME_SYSTEM>UTC</TIME_SYSTEM>"));
+    assert_eq!(xml.matches("<stateVector>").count(), 3);
+    assert!(xml.contains("<EPOCH>2024-05-01T00:05:00.000000</EPOCH>"));
+    assert_eq!(xml.matches("<segment>").count(), 1);
+    assert!(xml.trim_end().ends_with("</oem>"));
+}
+
+#[test]
+fn czml_availability_and_positions() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC).to_gpst_seconds();
+    let mut segment = EphemerisSegment::from_orbit(&circular_orbit("G07", "cddis_cod_ult", start), start, start + 7_200.0, None).unwrap();
+
+    // drop the samples between 30 and 60 minutes
+    segment.states.retain(|state| state.epoch <= start + 1_800.0 || state.epoch >= start + 3_600.0);
+    assert_eq!(availability(&segment), vec![(start, start + 1_800.0), (start + 3_600.0, start + 7_200.0)]);
+
+    let document = czml(&[segment]);
+    let packets = document.as_array().unwrap();
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0]["id"], "document");
+    assert_eq!(packets[0]["clock"]["interval"], "2024-05-01T00:00:00.000000Z/2024-05-01T02:00:00.000000Z");
+
+    let packet = &packets[1];
+    assert_eq!(packet["id"], "G07");
+    assert_eq!(packet["availability"][1], "2024-05-01T01:00:00.000000Z/2024-05-01T02:00:00.000000Z");
+    assert_eq!(packet["position"]["referenceFrame"], "FIXED");
+    assert_eq!(packet["position"]["interpolationDegree"], INTERPOLATION_DEGREE);
+
+    let cartesian = packet["position"]["cartesian"].as_array().unwrap();
+    assert_eq!(cartesian.len(), 4 * 20);
+    assert_eq!(cartesian[1].as_f64().unwrap(), GPS_RADIUS_KM * 1_000.0);
+    assert_eq!(cartesian[4].as_f64().unwrap(), STEP_S);
+}
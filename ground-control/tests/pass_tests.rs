@@ -0,0 +1,72 @@
+mod common;
+
+use std::f64::consts::PI;
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::{algo::{look::Observer, pass::{predict_passes, ElevationMask}}, product::sv::Orbit};
+
+const RADIUS_KM:f64 = 26_560.0;
+const RATE_RAD_S:f64 = 2.0 * PI / 43_200.0;
+
+// equatorial circular orbit sampled directly in ECEF, passing over lon 0 at t = 0
+fn equatorial_orbit() -> Orbit {
+
+    let epochs:Vec<f64> = (-144..144).map(|i| i as f64 * 300.0).collect();
+    let pos_ecef_km = epochs.iter()
+        .map(|t| Vector3::new(RADIUS_KM * (RATE_RAD_S * t).cos(), RADIUS_KM * (RATE_RAD_S * t).sin(), 0.0))
+        .collect();
+
+    orbit("G01", "cddis_cod_ult", epochs, pos_ecef_km)
+}
+
+#[test]
+fn pass_rise_set_culmination() {
+
+    let orbit = equatorial_orbit();
+    let observer = Observer::Geodetic { lat_deg: 0.0, lon_deg: 0.0, alt_m: 0.0 };
+    let mask = ElevationMask::new(0.0);
+
+    let passes = predict_passes(&observer, &orbit, &mask, -20_000.0, 20_000.0, 60.0);
+    assert_eq!(passes.len(), 1);
+
+    // geocentric angle at which the satellite crosses the observer horizon
+    let horizon_time = (6_378.137 / RADIUS_KM).acos() / RATE_RAD_S;
+
+    let pass = &passes[0];
+    assert!((pass.rise.unwrap() + horizon_time).abs() < 1.0);
+    assert!((pass.set.unwrap() - horizon_time).abs() < 1.0);
+    assert!(pass.culmination.abs() < 1.0);
+    assert!(pass.peak_elevation_deg > 89.9);
+    assert!((pass.rise_azimuth_deg.unwrap() - 270.0).abs() < 0.01);
+    assert!((pass.set_azimuth_deg.unwrap() - 90.0).abs() < 0.01);
+}
+
+#[test]
+fn pass_horizon_mask() {
+
+    let orbit = equatorial_orbit();
+    let observer = Observer::Geodetic { lat_deg: 0.0, lon_deg: 0.0, alt_m: 0.0 };
+
+    let horizon = ElevationMask::parse_horizon("0:0,80:0,90:30,100:0").unwrap();
+    let open = predict_passes(&observer, &orbit, &ElevationMask::new(0.0), -20_000.0, 20_000.0, 60.0);
+    let obstructed = predict_passes(&observer, &orbit, &ElevationMask::with_horizon(0.0, horizon), -20_000.0, 20_000.0, 60.0);
+
+    // the obstruction in the east makes the satellite set early
+    assert_eq!(obstructed.len(), 1);
+    assert!(obstructed[0].set.unwrap() < open[0].set.unwrap() - 60.0);
+    assert!((obstructed[0].rise.unwrap() - open[0].rise.unwrap()).abs() < 0.1);
+}
+
+#[test]
+fn pass_open_at_window_start() {
+
+    let orbit = equatorial_orbit();
+    let observer = Observer::Geodetic { lat_deg: 0.0, lon_deg: 0.0, alt_m: 0.0 };
+
+    let passes = predict_passes(&observer, &orbit, &ElevationMask::new(10.0), 0.0, 20_000.0, 60.0);
+
+    assert_eq!(passes.len(), 1);
+    assert!(passes[0].rise.is_none());
+    assert!(passes[0].set.is_some());
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::{algo::{gravity::GravityModel, look::EARTH_ROTATION_RAD_S, propagate::{check_extrapolation, extrapolate, fit_orbit, Ecom, ForceModel, PropagatorConfig, State, PROPAGATION_DEGREE}, sun::sun_moon_analytic_ecef_km}, data::icgem::parse_icgem};
+use hifitime::Epoch;
+
+const ICGEM:&str = "\
+product_type              gravity_field
+modelname                 TEST
+earth_gravity_constant    0.3986004415E+15
+radius                    0.6378136300E+07
+max_degree                3
+norm                      fully_normalized
+end_of_head ==================================================================
+gfc    0    0  1.000000000000D+00  0.000000000000D+00
+gfc    2    0 -0.484165143790815D-03  0.000000000000D+00
+gfc    2    2  0.243938357328313D-05 -0.140027370385934D-05
+gfc    3    0  0.957161207093473D-06  0.000000000000D+00
+";
+
+#[test]
+fn gravity_matches_closed_form_j2() {
+
+    let mut model = GravityModel::egm2008().truncate(2);
+    model.c[2][1] = 0.0;
+    model.s[2][1] = 0.0;
+    model.c[2][2] = 0.0;
+    model.s[2][2] = 0.0;
+
+    let j2 = -model.c[2][0] * 5f64.sqrt();
+    let pos = Vector3::new(15_000.0, -12_000.0, 18_000.0);
+    let r = pos.norm();
+    let k = 1.5 * j2 * model.gm_km3_s2 * model.radius_km.powi(2) / r.powi(5);
+    let z2 = 5.0 * pos.z * pos.z / (r * r);
+
+    let expected = -model.gm_km3_s2 * pos / r.powi(3)
+        - k * Vector3::new(pos.x * (1.0 - z2), pos.y * (1.0 - z2), pos.z * (3.0 - z2));
+
+    assert!((model.acceleration(&pos) - expected).norm() < 1e-12 * expected.norm());
+}
+
+#[test]
+fn icgem_model() {
+
+    let model = parse_icgem(ICGEM.as_bytes(), 2).unwrap();
+    assert_eq!(model.degree, 2);
+    assert_eq!(model.gm_km3_s2, 398600.4415);
+    assert_eq!(model.s[2][2], -0.140027370385934e-5);
+
+    // same low degree terms as the embedded field
+    let pos = Vector3::new(20_000.0, 10_000.0, 14_000.0);
+    let mut embedded = GravityModel::egm2008().truncate(2);
+    embedded.c[2][1] = 0.0;
+    embedded.s[2][1] = 0.0;
+    assert!((model.acceleration(&pos) - embedded.acceleration(&pos)).norm() < 1e-15);
+}
+
+#[test]
+fn embedded_field_to_degree_12() {
+
+    let model = GravityModel::egm2008();
+    assert_eq!(model.degree, PROPAGATION_DEGREE);
+
+    // every coefficient present and within a few times Kaula's rule 1e-5 / n^2
+    for n in 3..=model.degree {
+        for m in 0..=n {
+            let bound = 3e-5 / (n * n) as f64;
+            assert!(model.c[n][m] != 0.0 && model.c[n][m].abs() < bound, "C{} {}", n, m);
+            assert!((m == 0) == (model.s[n][m] == 0.0) && model.s[n][m].abs() < bound, "S{} {}", n, m);
+        }
+    }
+
+    // same coefficients as the published field when it is available
+    if let Ok(path) = std::env::var("GRAVITY_MODEL_PATH") {
+        let icgem = parse_icgem(std::fs::File::open(path).map(std::io::BufReader::new).unwrap(), PROPAGATION_DEGREE).unwrap();
+        for n in 0..=model.degree {
+            for m in 0..=n {
+                assert!((model.c[n][m] - icgem.c[n][m]).abs() < 1e-18, "C{} {}", n, m);
+                assert!((model.s[n][m] - icgem.s[n][m]).abs() < 1e-18, "S{} {}", n, m);
+            }
+        }
+    }
+}
+
+#[test]
+fn analytic_sun_near_solstice() {
+
+    let epoch = Epoch::from_gregorian_utc_at_midnight(2024, 6, 21).to_gpst_seconds();
+    let (sun, moon) = sun_moon_analytic_ecef_km(epoch);
+
+    let declination = (sun.z / sun.norm()).asin().to_degrees();
+    assert!((declination - 23.44).abs() < 0.1);
+    assert!((sun.norm() - 1.52e8).abs() < 1e6);
+    assert!(moon.norm() > 356_000.0 && moon.norm() < 407_000.0);
+}
+
+#[test]
+fn extrapolation_reproduces_force_model() {
+
+    let start = Epoch::from_gregorian_utc_at_midnight(2024, 3, 1).to_gpst_seconds();
+    let step = 900.0;
+
+    // GPS-like orbit, inertial velocity converted to the rotating frame
+    let pos = Vector3::new(26_560.0, 0.0, 0.0);
+    let inclination = 55f64.to_radians();
+    let inertial_vel = 3.874 * Vector3::new(0.0, inclination.cos(), inclination.sin());
+    let vel = inertial_vel - Vector3::new(0.0, 0.0, EARTH_ROTATION_RAD_S).cross(&pos);
+
+    let srp = Ecom { d0: -1.1e-10, y0: 6e-13, b0: -2e-12 };
+    let truth = ForceModel::new(&GravityModel::egm2008(), srp);
+    let epochs:Vec<f64> = (0..=96).map(|i| start + i as f64 * step).collect();
+    let states = truth.propagate(&State { epoch: start, pos_ecef_km: pos, vel_ecef_km_s: vel }, &epochs, 60.0);
+
+    // product ends after 18 hours
+    let span = 73;
+    let orbit = orbit("G07", "cddis_cod_ult", epochs[..span].to_vec(), states[..span].iter().map(|state| state.pos_ecef_km).collect());
+
+    let config = PropagatorConfig::default();
+    let fit = fit_orbit(&orbit, &GravityModel::egm2008(), &config).unwrap();
+    assert_eq!(fit.samples, 25);
+    assert!(fit.rms_km < 1e-5);
+
+    for i in [80, 88, 96] {
+        let extrapolated = extrapolate(&orbit, epochs[i], &GravityModel::egm2008(), &config).unwrap();
+        assert!(extrapolated.extrapolated);
+        assert_eq!(extrapolated.epochs, [epochs[i]]);
+        // within a centimeter of the truth up to six hours out
+        assert!((extrapolated.pos_ecef_km[0] - states[i].pos_ecef_km).norm() < 1e-5);
+    }
+
+    assert!(extrapolate(&orbit, orbit.valid_to + config.max_extrapolation_s + 1.0, &GravityModel::egm2008(), &config).is_err());
+
+    // rejected before fitting: the fit arc starts six hours before the product end
+    assert!(check_extrapolation(&orbit, epochs[span - 1], &config).is_ok());
+    assert!(check_extrapolation(&orbit, epochs[span - 26], &config).is_err());
+    assert!(check_extrapolation(&orbit, orbit.valid_to + config.max_extrapolation_s + 1.0, &config).is_err());
+}
@@ -0,0 +1,86 @@
+use ground_control::{algo::ephemeris::{GALILEO_GM_M3_S2, GPS_GM_M3_S2}, data::rinex::{parse_rinex_nav, NavFile}, product::satellite::Constellation};
+use hifitime::{Epoch, TimeScale};
+
+const NAV:&str = "     3.04           N: GNSS NAV DATA    M: MIXED            RINEX VERSION / TYPE
+BCEmerge           congo               20240502 001503 GMT  PGM / RUN BY / DATE
+    18                                                      LEAP SECONDS
+                                                            END OF HEADER
+G01 2024 05 01 00 00 00 2.500000000000D-04-3.400000000000D-12 0.000000000000D+00
+     4.200000000000D+01 2.150000000000D+01 4.500000000000D-09 1.200000000000D+00
+     1.100000000000D-06 9.500000000000D-03 8.200000000000D-06 5.153650000000D+03
+     2.592000000000D+05 1.300000000000D-07-2.100000000000D+00-5.200000000000D-08
+     9.620000000000D-01 2.100000000000D+02 7.100000000000D-01-8.100000000000D-09
+     1.200000000000D-10 1.000000000000D+00 2.312000000000D+03 0.000000000000D+00
+     2.000000000000D+00 0.000000000000D+00-1.100000000000D-08 4.200000000000D+01
+     2.520000000000D+05 4.000000000000D+00
+R05 2024 05 01 00 15 00 1.200000000000D-05 0.000000000000D+00 2.592000000000D+05
+    -1.200000000000D+04 1.100000000000D+00 0.000000000000D+00 0.000000000000D+00
+     1.600000000000D+04 2.100000000000D+00 0.000000000000D+00 1.000000000000D+00
+     1.500000000000D+04-2.400000000000D+00 0.000000000000D+00 0.000000000000D+00
+E05 2024 05 01 00 00 00-4.100000000000D-04-2.100000000000D-12 0.000000000000D+00
+     8.700000000000D+01-3.020000000000D+01 2.900000000000D-09-4.000000000000D-01
+    -1.400000000000D-06 2.200000000000D-04 7.100000000000D-06 5.440610000000D+03
+     2.592000000000D+05-2.200000000000D-08 1.300000000000D+00 4.100000000000D-08
+     9.750000000000D-01 2.301000000000D+02-6.000000000000D-01-5.600000000000D-09
+    -1.100000000000D-10 5.160000000000D+02 2.312000000000D+03 0.000000000000D+00
+     3.120000000000D+00 0.000000000000D+00-4.700000000000D-09-5.300000000000D-09
+     2.598000000000D+05
+";
+
+#[test]
+fn gps_and_galileo_records() {
+
+    let ephemerides = parse_rinex_nav(NAV.as_bytes()).unwrap();
+    // the GLONASS record is skipped
+    assert_eq!(ephemerides.len(), 2);
+
+    let wednesday = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+
+    let gps = &ephemerides[0];
+    assert_eq!(gps.satellite, "G01".parse().unwrap());
+    assert_eq!(gps.iode, 42);
+    assert_eq!(gps.toe, wednesday);
+    assert_eq!(gps.toc, wednesday);
+    assert_eq!(gps.sqrt_a, 5_153.65);
+    assert_eq!(gps.e, 0.0095);
+    assert_eq!(gps.omega0, -2.1);
+    assert_eq!(gps.omega_dot, -8.1e-9);
+    assert_eq!(gps.af0, 2.5e-4);
+    assert_eq!(gps.fit_interval_s, 14_400.0);
+    assert_eq!(gps.gm_m3_s2(), GPS_GM_M3_S2);
+
+    let radius_km = gps.position_ecef_km(wednesday).norm();
+    assert!((radius_km - 26_560.0).abs() < 300.0);
+
+    let galileo = &ephemerides[1];
+    assert_eq!(galileo.satellite.constellation, Constellation::Galileo);
+    assert_eq!(galileo.iode, 87);
+    assert_eq!(galileo.toe, wednesday);
+    assert_eq!(galileo.af0, -4.1e-4);
+    assert_eq!(galileo.fit_interval_s, 14_400.0);
+    assert_eq!(galileo.gm_m3_s2(), GALILEO_GM_M3_S2);
+}
+
+#[test]
+fn rejects_other_files() {
+
+    // the GPS record is cut short
+    let truncated:Vec<&str> = NAV.lines().take(11).collect();
+    assert!(parse_rinex_nav(truncated.join("\n").as_bytes()).is_err());
+
+    let rinex2 = NAV.replacen("     3.04", "     2.11", 1);
+    assert!(parse_rinex_nav(rinex2.as_bytes()).is_err());
+
+    let observations = NAV.replacen("N: GNSS NAV DATA", "O: OBSERVATION DATA", 1);
+    assert!(parse_rinex_nav(observations.as_bytes()).is_err());
+}
+
+#[test]
+fn broadcast_source_names() {
+
+    let nav_file = NavFile { source: "cddis".to_string(), archive_path: "/cddis/2312/BRDC00IGS_R_20240920000_01D_MN.rnx.gz".to_string() };
+    assert_eq!(nav_file.data_source().unwrap().get_key(), "cddis_igs_brdc");
+
+    let sp3_file = NavFile { source: "cddis".to_string(), archive_path: "/cddis/2312/COD0OPSFIN_20240920000_01D_05M_ORB.SP3.gz".to_string() };
+    assert!(sp3_file.data_source().is_err());
+}
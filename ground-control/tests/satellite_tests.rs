@@ -0,0 +1,55 @@
+use ground_control::product::{satellite::{Constellation, SatelliteId}, sv::{Orbit, SVSource}};
+use utoipa::{PartialSchema, ToSchema};
+
+#[test]
+fn satellite_id_parse_and_display() {
+
+    let satellite:SatelliteId = "e05".parse().unwrap();
+    assert_eq!(satellite.constellation, Constellation::Galileo);
+    assert_eq!(satellite.prn, 5);
+    assert_eq!(satellite.to_string(), "E05");
+
+    for invalid in ["", "G1", "G001", "X01", "G00", "GG1", "01G", "G-1"] {
+        assert!(invalid.parse::<SatelliteId>().is_err(), "{} should not parse", invalid);
+    }
+
+    assert_eq!("galileo".parse::<Constellation>().unwrap(), Constellation::Galileo);
+    assert_eq!("J".parse::<Constellation>().unwrap(), Constellation::Qzss);
+    assert!("Z".parse::<Constellation>().is_err());
+}
+
+#[test]
+fn satellite_id_serde_and_keys() {
+
+    let satellite:SatelliteId = serde_json::from_str("\"C19\"").unwrap();
+    assert_eq!(serde_json::to_string(&satellite).unwrap(), "\"C19\"");
+    assert!(serde_json::from_str::<SatelliteId>("\"C1\"").is_err());
+
+    let sv = SVSource::from_key("cddis_cod_fin_c19").unwrap();
+    assert_eq!(sv.satellite, satellite);
+    assert_eq!(sv.data_source.analysis_center, "cod");
+    assert_eq!(sv.get_key(), "cddis_cod_fin_c19");
+
+    assert_eq!(SVSource::from_key("g01").unwrap().data_source.get_key(), "cddis_cod_ult");
+    assert!(SVSource::from_key("cddis_cod_fin_x99").is_err());
+    assert!(SVSource::from_key("cddis_cod").is_err());
+}
+
+#[test]
+fn schemas_match_serde() {
+
+    // the OpenAPI schema of a satellite is the string it serializes to
+    let schema = serde_json::to_value(SatelliteId::schema()).unwrap();
+    assert_eq!(schema["type"], "string");
+    assert_eq!(schema["pattern"], "^[GRECJISLgrecjisl][0-9]{2}$");
+
+    let mut schemas = Vec::new();
+    Orbit::schemas(&mut schemas);
+    let names:Vec<String> = schemas.iter().map(|(name, _)| name.clone()).collect();
+    assert!(names.contains(&"SVSource".to_string()));
+    assert!(names.contains(&"SatelliteId".to_string()));
+
+    let orbit = serde_json::to_value(Orbit::schema()).unwrap();
+    assert_eq!(orbit["properties"]["sv"]["$ref"], "#/components/schemas/SVSource");
+    assert_eq!(orbit["properties"]["pos_ecef_km"]["items"]["type"], "array");
+}
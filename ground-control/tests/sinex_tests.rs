@@ -0,0 +1,93 @@
+use ground_control::{algo::attitude::BlockType, data::sinex::{parse_launch_date, parse_sinex_epoch, parse_satellite_metadata}};
+
+const METADATA:&str = "\
+%=SNX 2.02 IGS 24:001:00000 IGS 00:000:00000 00:000:00000 P 00000 0
++SATELLITE/IDENTIFIER
+*SVN_ COSPAR_ID SatCat Block__________ Comment__________________________________
+ G049 2009-014A  34661 GPS-IIR-M      Launched 2009-03-24; NAVSTAR 64
+ G074 2018-109A  43873 GPS-IIIA       Launched 2018-12-23; NAVSTAR 74
+ R856 2014-032A  40001 GLO-M          Launched 2014-06-14; COSMOS 2500
+-SATELLITE/IDENTIFIER
++SATELLITE/PRN
+*SVN_ Valid_From____ Valid_To______ PRN Comment_________________________________
+ G049 2009:083:00000 2019:197:00000 G01
+ G074 2019:197:00000 0000:000:00000 G04
+ G049 2019:180:00000 2020:001:00000 G04
+ R856 2014:165:00000 0000:000:00000 R04
+-SATELLITE/PRN
++SATELLITE/FREQUENCY_CHANNEL
+*SVN_ Valid_From____ Valid_To______ chn Comment_________________________________
+ R856 2014:165:00000 0000:000:00000   6
+-SATELLITE/FREQUENCY_CHANNEL
++SATELLITE/MASS
+*SVN_ Valid_From____ Valid_To______ Mass_[kg] Comment___________________________
+ G074 2018:357:00000 0000:000:00000  2161.000
+-SATELLITE/MASS
++SATELLITE/TX_POWER
+*SVN_ Valid_From____ Valid_To______ P[W] Comment________________________________
+ G074 2018:357:00000 0000:000:00000  300
+-SATELLITE/TX_POWER
+%ENDSNX
+";
+
+#[test]
+fn sinex_epochs() {
+
+    assert_eq!(parse_sinex_epoch("0000:000:00000").unwrap(), None);
+
+    let day1 = parse_sinex_epoch("2024:001:00000").unwrap().unwrap();
+    let day2 = parse_sinex_epoch("24:002:43200").unwrap().unwrap();
+    assert!((day2 - day1 - 129_600.0).abs() < 1e-6);
+
+    assert!(parse_sinex_epoch("2024:001").is_err());
+}
+
+#[test]
+fn launch_dates() {
+
+    let launched = parse_launch_date("Launched 2018-12-23; NAVSTAR 74").unwrap();
+    assert_eq!(Some(launched), parse_sinex_epoch("2018:357:00000").unwrap());
+
+    assert_eq!(parse_launch_date("NAVSTAR 74"), None);
+    assert_eq!(parse_launch_date("Launched 2018-13-23"), None);
+}
+
+#[test]
+fn prn_reassignment() {
+
+    let catalog = parse_satellite_metadata(METADATA.as_bytes()).unwrap();
+    assert_eq!(catalog.identifiers.len(), 3);
+
+    let before = parse_sinex_epoch("2019:100:00000").unwrap().unwrap();
+    let after = parse_sinex_epoch("2021:100:00000").unwrap().unwrap();
+
+    let (g01, g04) = ("G01".parse().unwrap(), "G04".parse().unwrap());
+    assert_eq!(catalog.svn_for_prn(&g01, before).as_deref(), Some("G049"));
+    assert_eq!(catalog.svn_for_prn(&g01, after), None);
+    assert_eq!(catalog.svn_for_prn(&g04, after).as_deref(), Some("G074"));
+
+    // G049 moved to G04 before G074 took the slot over, the latest assignment wins the overlap
+    let moved = parse_sinex_epoch("2019:185:00000").unwrap().unwrap();
+    let overlap = parse_sinex_epoch("2019:250:00000").unwrap().unwrap();
+    assert_eq!(catalog.svn_for_prn(&g04, moved).as_deref(), Some("G049"));
+    assert_eq!(catalog.svn_for_prn(&g04, overlap).as_deref(), Some("G074"));
+    assert_eq!(catalog.lookup("G049", overlap).unwrap().prn, Some(g04));
+
+    let info = catalog.lookup("g04", after).unwrap();
+    assert_eq!(info.svn, "G074");
+    assert_eq!(info.block_type, BlockType::GpsIII);
+    assert_eq!(info.mass_kg, Some(2161.0));
+    assert_eq!(info.tx_power_w, Some(300.0));
+    assert_eq!(info.launched, parse_sinex_epoch("2018:357:00000").unwrap());
+    assert_eq!(info.decommissioned, None);
+
+    // lookup by SVN, no longer holding a PRN
+    let retired = catalog.lookup("G049", after).unwrap();
+    assert_eq!(retired.prn, None);
+    assert_eq!(retired.block_type, BlockType::GpsIIRM);
+    assert_eq!(retired.launched, parse_sinex_epoch("2009:083:00000").unwrap());
+    assert_eq!(retired.decommissioned, parse_sinex_epoch("2020:001:00000").unwrap());
+
+    let glonass = catalog.lookup("R04", after).unwrap();
+    assert_eq!(glonass.frequency_channel, Some(6));
+}
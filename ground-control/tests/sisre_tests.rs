@@ -0,0 +1,116 @@
+mod common;
+
+use common::{broadcast, orbit};
+use ground_control::{algo::{attitude::{pco_ecef_km, BlockType}, ephemeris::{rac_basis, BroadcastEphemeris}, sisre::{sisre_series, SisreConfig, SisreMonitoring}, ssr::SPEED_OF_LIGHT_M_S, sun::sun_moon_analytic_ecef_km}, product::{satellite::Constellation, sv::Orbit}};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+// precise center of mass orbit offset from the ephemeris by radial/along/cross errors and a clock
+// error, as a function of the sample index, and by the antenna offset of the block
+fn precise(ephemeris:&BroadcastEphemeris, block:BlockType, start:f64, errors:impl Fn(usize) -> (f64, f64, f64, f64), clocks:bool) -> Orbit {
+
+    let epochs:Vec<f64> = (0..=48).map(|i| start + i as f64 * STEP_S).collect();
+    let positions = epochs.iter().enumerate().map(|(i, epoch)| {
+        let (dr, da, dc, _) = errors(i);
+        let pos = ephemeris.position_ecef_km(*epoch);
+        let vel = ephemeris.velocity_ecef_km_s(*epoch);
+        let (radial, along, cross) = rac_basis(&pos, &vel);
+        let phase_center = pos - (dr * radial + da * along + dc * cross) / 1_000.0;
+        phase_center - pco_ecef_km(block, &phase_center, &vel, &sun_moon_analytic_ecef_km(*epoch).0)
+    }).collect();
+    let clock_usec = epochs.iter().enumerate().map(|(i, epoch)| (ephemeris.clock_offset_s(*epoch) - errors(i).3 / SPEED_OF_LIGHT_M_S) * 1e6).collect();
+
+    Orbit { clock_usec: clocks.then_some(clock_usec), ..orbit(&ephemeris.satellite.to_string(), "cddis_cod_ult", epochs, positions) }
+}
+
+#[test]
+fn sisre_from_known_errors() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let ephemeris = broadcast("G12", start + 7_200.0);
+    let orbit = precise(&ephemeris, BlockType::GpsIIR, start, |_| (0.3, 1.4, -0.7, 0.2), true);
+
+    let config = SisreConfig { interval_s: 900.0, threshold_m: 3.0 };
+    let samples = sisre_series(&orbit, std::slice::from_ref(&ephemeris), BlockType::GpsIIR, start + 3_600.0, start + 10_800.0, &config).unwrap();
+    assert_eq!(samples.len(), 9);
+
+    let along_cross = (1.4f64.powi(2) + 0.7f64.powi(2)) / 49.0;
+    for sample in &samples {
+        assert_eq!(sample.iode, 42);
+        assert!((sample.clock_m.unwrap() - 0.2).abs() < 1e-6);
+        assert!((sample.orbit_sisre_m - ((0.98f64 * 0.3).powi(2) + along_cross).sqrt()).abs() < 1e-6);
+        assert!((sample.sisre_m.unwrap() - ((0.98f64 * 0.3 - 0.2).powi(2) + along_cross).sqrt()).abs() < 1e-6);
+    }
+
+    // without precise clocks only the orbit SISRE is available
+    let orbit = precise(&ephemeris, BlockType::GpsIIR, start, |_| (0.3, 1.4, -0.7, 0.2), false);
+    let samples = sisre_series(&orbit, &[ephemeris], BlockType::GpsIIR, start + 3_600.0, start + 10_800.0, &config).unwrap();
+    assert!(samples.iter().all(|sample| sample.clock_m.is_none() && sample.sisre_m.is_none()));
+}
+
+#[test]
+fn broadcast_minus_precise_center_of_mass() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let ephemeris = broadcast("G07", start + 7_200.0);
+    let config = SisreConfig { interval_s: 900.0, threshold_m: 3.0 };
+    let pco = BlockType::GpsIIF.pco_m();
+
+    // error free IIF orbit: the broadcast phase center is offset from the center of mass by the PCO
+    let orbit = precise(&ephemeris, BlockType::GpsIIF, start, |_| (0.0, 0.0, 0.0, 0.0), true);
+
+    let samples = sisre_series(&orbit, std::slice::from_ref(&ephemeris), BlockType::GpsIIF, start + 3_600.0, start + 10_800.0, &config).unwrap();
+    assert_eq!(samples.len(), 9);
+    assert!(samples.iter().all(|sample| sample.orbit_sisre_m < 1e-4));
+
+    // without the offset the phase center sits the z offset nearer the Earth and the x offset off the
+    // radial, within the few millimeters the flight path angle tilts the radial of the RAC frame
+    let samples = sisre_series(&orbit, &[ephemeris], BlockType::Unknown, start + 3_600.0, start + 10_800.0, &config).unwrap();
+    for sample in &samples {
+        assert!((sample.radial_m + pco.z).abs() < 0.01);
+        assert!((sample.along_m.hypot(sample.cross_m) - pco.x.abs()).abs() < 0.01);
+    }
+}
+
+#[test]
+fn statistics_and_alerts() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let config = SisreConfig { interval_s: STEP_S, threshold_m: 3.0 };
+    let (from, to) = (start + 3_600.0, start + 10_800.0);
+
+    let healthy = broadcast("G12", start + 7_200.0);
+    let mut samples = sisre_series(&precise(&healthy, BlockType::GpsIIR, start, |_| (0.1, 0.5, 0.3, 0.1), true), &[healthy], BlockType::GpsIIR, from, to, &config).unwrap();
+
+    // clock jump between 2 h and 2.5 h
+    let faulty = broadcast("G07", start + 7_200.0);
+    let jump = |i:usize| if (24..=30).contains(&i) { 10.0 } else { 0.0 };
+    samples.extend(sisre_series(&precise(&faulty, BlockType::GpsIIF, start, |i| (0.1, 0.5, 0.3, jump(i)), true), &[faulty], BlockType::GpsIIF, from, to, &config).unwrap());
+
+    let galileo = broadcast("E05", start + 7_200.0);
+    samples.extend(sisre_series(&precise(&galileo, BlockType::GalileoFoc, start, |_| (0.05, 0.2, 0.2, 0.0), false), &[galileo], BlockType::GalileoFoc, from, to, &config).unwrap());
+
+    let monitoring = SisreMonitoring::new(samples, &config);
+
+    assert_eq!(monitoring.statistics.len(), 2);
+    let gps = &monitoring.statistics[0];
+    assert_eq!(gps.constellation, Constellation::Gps);
+    assert_eq!((gps.satellites, gps.samples), (2, 50));
+    assert!(gps.orbit_rms_m < 0.2);
+    assert!(gps.p95_m.unwrap() > 9.0 && gps.max_m > 9.0);
+
+    let galileo = &monitoring.statistics[1];
+    assert_eq!(galileo.constellation, Constellation::Galileo);
+    assert!(galileo.rms_m.is_none());
+
+    assert_eq!(monitoring.alerts.len(), 1);
+    let alert = &monitoring.alerts[0];
+    assert_eq!(alert.satellite, "G07".parse().unwrap());
+    assert_eq!((alert.from, alert.to, alert.samples), (start + 7_200.0, start + 9_000.0, 7));
+
+    let batch = monitoring.samples_record_batch().unwrap();
+    assert_eq!(batch.num_rows(), 75);
+    assert_eq!(batch.column_by_name("sisre_m").unwrap().null_count(), 25);
+    assert_eq!(monitoring.statistics_record_batch().unwrap().num_rows(), 2);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::circular_orbit;
+use ground_control::product::sv::{best_positions, orbit_positions, Orbit, SourceChain, BEST_SOURCE};
+
+#[test]
+fn source_chain_parse_and_resolve() {
+
+    let chain = SourceChain::parse("cddis_cod_fin, cddis_cod_rap,cddis_igs_ult").unwrap();
+    let keys:Vec<String> = chain.sources.iter().map(|source| source.get_key()).collect();
+    assert_eq!(keys, ["cddis_cod_fin", "cddis_cod_rap", "cddis_igs_ult"]);
+
+    assert!(SourceChain::parse("").is_err());
+    assert!(SourceChain::parse("cddis_cod_fin,cod").is_err());
+
+    // a named source is a chain of one
+    let single = SourceChain::resolve("cddis_cod_ult", Some("cddis_cod_fin")).unwrap();
+    assert_eq!(single.sources.len(), 1);
+    assert_eq!(single.sources[0].product_type, "ult");
+
+    let best = SourceChain::resolve(BEST_SOURCE, Some("cddis_cod_fin,cddis_cod_ult")).unwrap();
+    assert_eq!(best.sources[1].get_key(), "cddis_cod_ult");
+}
+
+#[test]
+fn batched_positions() {
+
+    let mut orbits:Vec<Orbit> = (1..=40).map(|prn| circular_orbit(&format!("G{:02}", prn), "cddis_cod_fin", 10.0 * prn as f64)).collect();
+    // starts after the epoch
+    orbits.push(circular_orbit("G41", "cddis_cod_fin", 4_000.0));
+
+    let positions = orbit_positions(&orbits, 3_700.0);
+    assert_eq!(positions.len(), orbits.len());
+
+    for (orbit, position) in orbits[..40].iter().zip(&positions) {
+        assert_eq!(position.as_ref().unwrap(), &orbit.get_position_at(3_700.0).unwrap());
+    }
+
+    assert!(positions[40].is_err());
+    assert!(orbit_positions::<Orbit>(&[], 3_700.0).is_empty());
+}
+
+#[test]
+fn best_positions_in_chain_order() {
+
+    let position = |sv:&str, source:&str, valid_to:f64, extrapolated:bool| {
+        let mut orbit = circular_orbit(sv, source, 0.0);
+        orbit.valid_to = valid_to;
+        orbit.extrapolated = extrapolated;
+        orbit
+    };
+
+    let best = best_positions(vec![
+        vec![position("G01", "cddis_cod_fin", 100.0, true), position("G02", "cddis_cod_fin", 100.0, false)],
+        vec![position("G02", "cddis_cod_ult", 200.0, false), position("G03", "cddis_cod_ult", 200.0, true), position("G01", "cddis_cod_ult", 200.0, true)],
+        vec![position("G03", "cddis_igs_ult", 300.0, false)],
+    ]);
+
+    let sources:Vec<String> = best.iter().map(|orbit| orbit.sv.get_key()).collect();
+    // extrapolated from the orbit ending last, otherwise the first interpolated
+    assert_eq!(sources, ["cddis_cod_ult_g01", "cddis_cod_fin_g02", "cddis_igs_ult_g03"]);
+}
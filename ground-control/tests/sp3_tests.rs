@@ -1,9 +1,12 @@
 use std::time::Duration;
 
-use ground_control::{data::sp3::{Sp3Data, Sp3DataImpl}, product::sv::{Orbit, SVOrbits, SVOrbitsImpl}};
+use anise::math::Vector3;
+use arrow_array::{Array, FixedSizeListArray, Float64Array, StringArray};
+use ground_control::{algo::{gravity::GravityModel, propagate::{extrapolate, PropagatorConfig}}, data::sp3::{sp3_clocks, Sp3Data, Sp3DataImpl, Sp3Table}, product::sv::{DataSource, Orbit, SVOrbits, SVOrbitsImpl, SVSource}};
 use restate_sdk::prelude::*;
 use restate_sdk_test_env::TestContainer;
 use ground_control::data::sp3::Sp3File;
+use hifitime::{Epoch, TimeScale};
 use tracing::info;
 
 #[tokio::test]
@@ -101,3 +104,132 @@ async fn sp3_loader_workflow_test() {
     info!("orbit pos3 response: {:#?}", result);
 
 }
+
+// Fits the first 18 hours of the observed day of an ultra rapid product and compares the
+// extrapolated arc with the 6 hours held out, with the embedded gravity field
+#[tokio::test]
+async fn extrapolation_matches_held_out_sp3() {
+
+    let _ = dotenvy::dotenv();
+
+    let sp3_file = Sp3File {source: "cddis".to_string(), archive_path:"/cddis/2361/COD0OPSULT_20250960000_02D_05M_ORB.SP3.gz".to_string()};
+    let batch = Sp3Table::new(sp3_file.get_table_name().unwrap()).load_sp3_file(&sp3_file).await.unwrap();
+
+    let epochs = batch.column(0).as_any().downcast_ref::<Float64Array>().unwrap();
+    let satellites = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+    let positions = batch.column(4).as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+    let positions = positions.values().as_any().downcast_ref::<Float64Array>().unwrap();
+
+    let start = epochs.values().iter().cloned().fold(f64::MAX, f64::min);
+    let held_out_from = start + 18.0 * 3600.0;
+    let held_out_to = start + 24.0 * 3600.0;
+
+    let data_source = DataSource {
+        source: sp3_file.source.clone(),
+        analysis_center: sp3_file.get_analysis_center().unwrap(),
+        product_type: sp3_file.get_product_type().unwrap(),
+    };
+
+    let gravity = GravityModel::egm2008();
+    let config = PropagatorConfig::default();
+
+    for sv in ["G01", "G12", "G24"] {
+
+        // SP3 marks missing positions with zeros
+        let samples:Vec<(f64, Vector3)> = (0..batch.num_rows())
+            .filter(|i| satellites.value(*i) == sv)
+            .map(|i| (epochs.value(i), Vector3::new(positions.value(3 * i), positions.value(3 * i + 1), positions.value(3 * i + 2))))
+            .filter(|(epoch, pos)| *epoch < held_out_to && pos.norm() > 0.0)
+            .collect();
+
+        let (fitted, held_out):(Vec<(f64, Vector3)>, Vec<(f64, Vector3)>) = samples.into_iter()
+            .partition(|(epoch, _)| *epoch < held_out_from);
+
+        let orbit = Orbit {
+            sv: SVSource { satellite: sv.parse().unwrap(), data_source: data_source.clone() },
+            product_run_id: sp3_file.get_product_run_id().unwrap(),
+            sampling_resolution: sp3_file.get_sampling_resolution().unwrap(),
+            valid_from: fitted[0].0,
+            valid_to: fitted[fitted.len() - 1].0,
+            epochs: fitted.iter().map(|(epoch, _)| *epoch).collect(),
+            pos_ecef_km: fitted.iter().map(|(_, pos)| *pos).collect(),
+            pos_latlonalt: None,
+            clock_usec: None,
+            metadata: None,
+            extrapolated: false,
+        };
+
+        assert_eq!(held_out.len(), 72);
+
+        for (epoch, truth) in held_out.iter().step_by(12) {
+            let extrapolated = extrapolate(&orbit, *epoch, &gravity, &config).unwrap();
+            let error_km = (extrapolated.pos_ecef_km[0] - truth).norm();
+            info!("{} extrapolated {:.0} s: {:.3} m", sv, epoch - orbit.valid_to, error_km * 1000.0);
+
+            // tens of meters in the first hour, within a kilometer after six hours
+            let limit_km = if epoch - orbit.valid_to <= 3600.0 { 0.1 } else { 1.0 };
+            assert!(error_km < limit_km, "{} off by {:.3} km at {}", sv, error_km, epoch);
+        }
+    }
+}
+
+// a data source without stored orbits answers 404 rather than panicking, the API turns it into
+// an unknown_source error
+#[tokio::test]
+async fn unknown_source_not_found() {
+
+    let endpoint = Endpoint::builder()
+        .bind(SVOrbitsImpl.serve())
+        .build();
+
+    let test_container = TestContainer::builder()
+        .with_container(
+            "docker.io/restatedev/restate".to_string(),
+            "latest".to_string(),
+        )
+        .build()
+        .start(endpoint)
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/SVOrbits/cddis_xyz_fin/getSatellites", test_container.ingress_url()))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 404);
+
+    let body:serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["message"], "No orbits for cddis_xyz_fin");
+}
+
+const SP3_RECORDS:&str = "\
+#dP2024  5  1  0  0  0.00000000       2 ORBIT IGS20 FIT  COD
+%c M  cc GPS ccc cccc cccc cccc cccc ccccc ccccc ccccc ccccc
+%c cc cc ccc ccc cccc cccc cccc cccc ccccc ccccc ccccc ccccc
+*  2024  5  1  0  0  0.00000000
+PG01  13349.306212 -22429.262394   3936.307476    -62.119466
+PG02 -12345.678901  12345.678901  12345.678901 999999.999999
+*  2024  5  1  0  5  0.00000000
+PG01  13360.112233 -22420.445566   3950.778899    -62.119512
+PG02 -12340.000000  12350.000000  12340.000000
+EOF
+";
+
+#[test]
+fn sp3_clock_column() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let g01 = "G01".parse().unwrap();
+
+    // the bad and the blank G02 clocks are left out
+    assert_eq!(sp3_clocks(SP3_RECORDS).unwrap(), vec![(start, g01, -62.119466), (start + 300.0, g01, -62.119512)]);
+
+    // epochs in the time system of the file
+    let utc = sp3_clocks(&SP3_RECORDS.replace("cc GPS", "cc UTC")).unwrap();
+    assert_eq!(utc[0].0, Epoch::from_gregorian_utc_at_midnight(2024, 5, 1).to_gpst_seconds());
+
+    assert!(sp3_clocks("PG01  13349.306212 -22429.262394   3936.307476    -62.119466").is_err());
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::{broadcast, orbit};
+use ground_control::{algo::{attitude::{pco_ecef_km, BlockType}, ephemeris::{rac_basis, BroadcastEphemeris}, ssr::{ssr_corrections, ClockCorrection, OrbitCorrection, SsrConfig, SPEED_OF_LIGHT_M_S}, sun::sun_moon_analytic_ecef_km}, data::rtcm::{crc24q, encode_clock_corrections, encode_orbit_corrections, SsrStream}, error::ApiError, product::sv::Orbit};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+// reads big endian bit fields
+fn bits(bytes:&[u8], start:usize, width:usize) -> u64 {
+    (start..start + width).fold(0, |value, bit| (value << 1) | ((bytes[bit / 8] >> (7 - bit % 8)) & 1) as u64)
+}
+
+#[test]
+fn crc24q_reference_frame() {
+
+    // RTCM 10403 example message 1005
+    let frame = [0xD3, 0x00, 0x13, 0x3E, 0xD7, 0xD3, 0x02, 0x02, 0x98, 0x0E, 0xDE, 0xEF, 0x34, 0xB4, 0xBD, 0x62, 0xAC, 0x09, 0x41, 0x98, 0x6F, 0x33, 0x36, 0x0B, 0x98];
+    assert_eq!(crc24q(&frame[..22]), 0x36_0B98);
+    assert_eq!(crc24q(&frame), 0);
+}
+
+#[test]
+fn corrections_recover_offsets() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let ephemeris = broadcast("G12", start + 7_200.0);
+    let (dr, da, dc, dclock) = (0.12, -0.8, 0.35, 0.5);
+
+    let epochs:Vec<f64> = (0..=48).map(|i| start + i as f64 * STEP_S).collect();
+    // center of mass, the broadcast orbit being for the antenna phase center
+    let positions = epochs.iter().map(|epoch| {
+        let pos = ephemeris.position_ecef_km(*epoch);
+        let vel = ephemeris.velocity_ecef_km_s(*epoch);
+        let (radial, along, cross) = rac_basis(&pos, &vel);
+        let phase_center = pos - (dr * radial + da * along + dc * cross) / 1_000.0;
+        phase_center - pco_ecef_km(BlockType::GpsIIR, &phase_center, &vel, &sun_moon_analytic_ecef_km(*epoch).0)
+    }).collect();
+    let clocks = epochs.iter().map(|epoch| (ephemeris.clock_offset_s(*epoch) - dclock / SPEED_OF_LIGHT_M_S) * 1e6).collect();
+
+    let orbit = Orbit { clock_usec: Some(clocks), ..orbit("G12", "cddis_cod_ult", epochs.clone(), positions) };
+
+    let config = SsrConfig { orbit_interval_s: 600.0, clock_interval_s: 300.0 };
+    let corrections = ssr_corrections(&orbit, std::slice::from_ref(&ephemeris), BlockType::GpsIIR, epochs[18], epochs[30], &config).unwrap();
+
+    assert_eq!(corrections.orbit.len(), 7);
+    for correction in &corrections.orbit {
+        assert_eq!(correction.iode, 42);
+        assert!((correction.radial_m - dr).abs() < 1e-6);
+        assert!((correction.along_m - da).abs() < 1e-6);
+        assert!((correction.cross_m - dc).abs() < 1e-6);
+        assert!(correction.along_rate_m_s.abs() < 1e-5);
+    }
+
+    assert_eq!(corrections.clock.len(), 13);
+    for correction in &corrections.clock {
+        assert!((correction.c0_m - dclock).abs() < 1e-6);
+        assert!(correction.c1_m_s.abs() < 1e-9);
+    }
+
+    // outside the fit interval of the only ephemeris
+    let uncovered = ssr_corrections(&orbit, &[broadcast("G12", start - 86_400.0)], BlockType::GpsIIR, epochs[18], epochs[30], &config).unwrap();
+    assert!(uncovered.orbit.is_empty() && uncovered.clock.is_empty());
+
+    // the broadcast clock drift rate is left for C2, the precise clock being linear between samples
+    let drifting = BroadcastEphemeris { af2: 1e-15, ..ephemeris.clone() };
+    let corrections = ssr_corrections(&orbit, std::slice::from_ref(&drifting), BlockType::GpsIIR, epochs[18], epochs[30], &config).unwrap();
+    for correction in &corrections.clock {
+        let dt = correction.epoch - drifting.toc;
+        assert!((correction.c0_m - dclock - 1e-15 * dt * dt * SPEED_OF_LIGHT_M_S).abs() < 1e-6);
+        assert!((correction.c2_m_s2 - 1e-15 * SPEED_OF_LIGHT_M_S).abs() < 1e-10);
+    }
+
+    // no clock corrections to go with the orbit corrections
+    let err = ssr_corrections(&Orbit { clock_usec: None, ..orbit }, &[ephemeris], BlockType::GpsIIR, epochs[18], epochs[30], &config).unwrap_err();
+    assert!(matches!(err.downcast_ref::<ApiError>(), Some(ApiError::Unprocessable(_))));
+}
+
+#[test]
+fn rtcm_orbit_messages() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds() + 3_600.0;
+    let correction = |satellite:&str, radial_m:f64| OrbitCorrection {
+        satellite: satellite.parse().unwrap(),
+        epoch,
+        iode: 42,
+        radial_m,
+        along_m: -0.8,
+        cross_m: 0.35,
+        radial_rate_m_s: 1e-4,
+        along_rate_m_s: 0.0,
+        cross_rate_m_s: 0.0,
+    };
+
+    let stream = SsrStream { provider_id: 1234, solution_id: 1, iod_ssr: 3 };
+    let frames = encode_orbit_corrections(&[correction("G12", 0.1234), correction("E05", -0.05), correction("G07", 1.0)], 60.0, &stream).unwrap();
+    assert_eq!(frames.len(), 2);
+
+    let gps = &frames[0];
+    assert_eq!(gps[0], 0xD3);
+    let length = bits(gps, 14, 10) as usize;
+    assert_eq!(gps.len(), length + 6);
+    assert_eq!(crc24q(gps), 0);
+
+    let payload = &gps[3..3 + length];
+    assert_eq!(bits(payload, 0, 12), 1057);
+    // Wednesday 01:00
+    assert_eq!(bits(payload, 12, 20), 3 * 86_400 + 3_600);
+    assert_eq!(bits(payload, 32, 4), 6);
+    // more messages for this epoch follow
+    assert_eq!(bits(payload, 36, 1), 1);
+    assert_eq!(bits(payload, 42, 16), 1234);
+    assert_eq!(bits(payload, 62, 6), 2);
+    assert_eq!(bits(payload, 68, 6), 12);
+    assert_eq!(bits(payload, 74, 8), 42);
+    assert_eq!(bits(payload, 82, 22), 1234);
+    // along track, two's complement in 0.4 mm
+    assert_eq!(bits(payload, 104, 20), (1 << 20) - 2_000);
+
+    let galileo = &frames[1][3..];
+    assert_eq!(bits(galileo, 0, 12), 1240);
+    assert_eq!(bits(galileo, 36, 1), 0);
+
+    assert!(encode_orbit_corrections(&[correction("G12", 500.0)], 60.0, &stream).is_err());
+}
+
+#[test]
+fn rtcm_satellite_count_fits_df387() {
+
+    let epoch = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let corrections:Vec<ClockCorrection> = (0..70).map(|i| ClockCorrection {
+        satellite: format!("G{:02}", i % 32 + 1).parse().unwrap(),
+        epoch,
+        iode: 42,
+        c0_m: 0.5,
+        c1_m_s: 0.0,
+        c2_m_s2: 0.0,
+    }).collect();
+
+    let frames = encode_clock_corrections(&corrections, 5.0, &SsrStream::default()).unwrap();
+    assert_eq!(frames.len(), 2);
+
+    // six bit DF387 after the header without the reference datum flag
+    assert_eq!(bits(&frames[0][3..], 61, 6), 63);
+    assert_eq!(bits(&frames[1][3..], 61, 6), 7);
+    assert_eq!(bits(&frames[0][3..], 36, 1), 1);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use anise::math::Vector3;
+use common::orbit;
+use ground_control::product::{satellite::Constellation, stream::{PositionStream, SatelliteFilter, UpdateKind}, sv::Orbit};
+
+fn position(sv:&str, product_run_id:u64, epoch:f64) -> Orbit {
+    let epochs = vec![epoch - 300.0, epoch, epoch + 300.0];
+    let positions = vec![Vector3::new(26_560.0, -100.0, 0.0), Vector3::new(26_560.0, 0.0, 0.0), Vector3::new(26_560.0, 100.0, 0.0)];
+    Orbit { product_run_id, ..orbit(sv, "cddis_cod_fin", epochs, positions) }
+}
+
+#[test]
+fn filter_and_clock() {
+
+    let filter = SatelliteFilter { satellites: vec!["E12".parse().unwrap()], constellations: vec![Constellation::Gps] };
+    assert!(filter.matches(&"G01".parse().unwrap()));
+    assert!(filter.matches(&"E12".parse().unwrap()));
+    assert!(!filter.matches(&"E11".parse().unwrap()));
+    assert!(SatelliteFilter::default().matches(&"R03".parse().unwrap()));
+
+    // replay at 60 times real time
+    let stream = PositionStream::new(SatelliteFilter::default(), 1_000.0, 60.0, 1.0).unwrap();
+    assert_eq!(stream.epoch_at(0.0), 1_000.0);
+    assert_eq!(stream.epoch_at(2.5), 1_150.0);
+
+    assert!(PositionStream::new(SatelliteFilter::default(), 0.0, 1.0, 0.01).is_err());
+    assert!(PositionStream::new(SatelliteFilter::default(), 0.0, 1.0, 0.5).is_err());
+    assert!(PositionStream::new(SatelliteFilter::default(), 0.0, -1.0, 1.0).is_err());
+}
+
+#[test]
+fn product_run_updates() {
+
+    let filter = SatelliteFilter { satellites: Vec::new(), constellations: vec![Constellation::Gps] };
+    let mut stream = PositionStream::new(filter, 0.0, 1.0, 1.0).unwrap();
+
+    let update = stream.update(600.0, &[position("G01", 1, 600.0), position("E12", 1, 600.0)]);
+    assert_eq!(update.kind, UpdateKind::Positions);
+    assert_eq!(update.epoch, 600.0);
+    assert_eq!(update.positions.len(), 1);
+    assert_eq!(update.positions[0].satellite, "G01".parse().unwrap());
+    assert_eq!(update.positions[0].point.epoch, 600.0);
+    assert!(update.positions[0].point.lat_deg.abs() < 1e-6);
+
+    // same run, and a run change of a filtered out satellite
+    assert_eq!(stream.update(601.0, &[position("G01", 1, 601.0), position("E12", 2, 601.0)]).kind, UpdateKind::Positions);
+
+    assert_eq!(stream.update(602.0, &[position("G01", 2, 602.0)]).kind, UpdateKind::ProductRun);
+    assert_eq!(stream.update(603.0, &[position("G01", 2, 603.0), position("G02", 2, 603.0)]).kind, UpdateKind::Positions);
+
+    let json = serde_json::to_value(stream.update(604.0, &[position("G01", 3, 604.0)])).unwrap();
+    assert_eq!(json["kind"], "product_run");
+    assert_eq!(json["positions"][0]["satellite"], "G01");
+}
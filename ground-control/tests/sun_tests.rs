@@ -0,0 +1,70 @@
+mod common;
+
+use anise::math::Vector3;
+use common::circular_orbit;
+use ground_control::{algo::sun::{beta_and_orbit_angle, eclipse_status, EclipseStatus}, error::ApiError, product::sv::satellite_states};
+
+const AU_KM:f64 = 149_597_870.7;
+const RADIUS_KM:f64 = 26_560.0;
+
+// Moon placed far from the Sun line so only the Earth can occult
+fn moon() -> Vector3 {
+    Vector3::new(0.0, 0.0, 384_400.0)
+}
+
+#[test]
+fn eclipse_umbra_sunlit_penumbra() {
+
+    let sun = Vector3::new(AU_KM, 0.0, 0.0);
+
+    let (status, fraction) = eclipse_status(&Vector3::new(-RADIUS_KM, 0.0, 0.0), &sun, &moon());
+    assert_eq!(status, EclipseStatus::Umbra);
+    assert_eq!(fraction, 0.0);
+
+    let (status, fraction) = eclipse_status(&Vector3::new(0.0, RADIUS_KM, 0.0), &sun, &moon());
+    assert_eq!(status, EclipseStatus::Sunlit);
+    assert_eq!(fraction, 1.0);
+
+    // on the shadow boundary, where the Earth limb bisects the solar disk
+    let limb = (6_378.137 / RADIUS_KM).asin();
+    let sv = Vector3::new(-RADIUS_KM * limb.cos(), RADIUS_KM * limb.sin(), 0.0);
+    let (status, fraction) = eclipse_status(&sv, &sun, &moon());
+    assert_eq!(status, EclipseStatus::Penumbra);
+    assert!(fraction > 0.3 && fraction < 0.7);
+}
+
+#[test]
+fn beta_and_orbit_angle_equatorial() {
+
+    // equatorial prograde orbit, ECEF velocity is the inertial velocity less earth rotation
+    let pos = Vector3::new(RADIUS_KM, 0.0, 0.0);
+    let vel = Vector3::new(0.0, 3.874 - 7.2921151467e-5 * RADIUS_KM, 0.0);
+
+    let (beta, orbit_angle) = beta_and_orbit_angle(&pos, &vel, &Vector3::new(AU_KM, 0.0, 0.0));
+    assert!(beta.abs() < 1e-9);
+    assert!((orbit_angle - 180.0).abs() < 1e-9);
+
+    let (beta, orbit_angle) = beta_and_orbit_angle(&pos, &vel, &Vector3::new(0.0, -AU_KM, AU_KM));
+    assert!((beta - 45.0).abs() < 1e-9);
+    assert!((orbit_angle - 270.0).abs() < 1e-9);
+}
+
+#[test]
+fn states_of_a_source() {
+
+    let orbits = vec![circular_orbit("G01", "cddis_cod_fin", 0.0), circular_orbit("G02", "cddis_cod_fin", 3_600.0)];
+    let sun = Vector3::new(AU_KM, 0.0, 0.0);
+
+    let states = satellite_states(&orbits, 3_000.0, &sun, &moon());
+    assert_eq!(states.len(), 2);
+
+    // a quarter turn past the Sun line, in the sunlight
+    let state = states[0].as_ref().unwrap();
+    assert_eq!(state.satellite, "G01".parse().unwrap());
+    assert_eq!(state.sun_ecef_km, sun);
+    assert_eq!(state.eclipse, EclipseStatus::Sunlit);
+
+    // before the second orbit starts
+    let err = states[1].as_ref().unwrap_err();
+    assert!(matches!(err.downcast_ref::<ApiError>(), Some(ApiError::OutsideCoverage(_))));
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use common::orbit;
+use ground_control::{algo::tle::{alpha5, compare_tle, fit_mean_elements, tle_checksum, MeanElements, TleConfig, TleIdentity, TwoLineElements}, product::sv::Orbit};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+fn identity() -> TleIdentity {
+    TleIdentity { name: "GPS BIIR-M (G07)".to_string(), norad_id: 32260, cospar_id: "2008-012A".to_string() }
+}
+
+// one day of positions generated by SGP4 from known mean elements at its end
+fn sgp4_orbit() -> (MeanElements, Orbit) {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::GPST).to_gpst_seconds();
+    let truth = MeanElements {
+        satellite: "G07".parse().unwrap(),
+        epoch: start + 86_400.0,
+        inclination_deg: 55.0,
+        raan_deg: 120.0,
+        eccentricity: 0.005,
+        arg_perigee_deg: 40.0,
+        mean_anomaly_deg: 10.0,
+        mean_motion_rev_day: 2.00563,
+        bstar: 0.0,
+    };
+
+    let epochs:Vec<f64> = (0..=288).map(|i| start + i as f64 * STEP_S).collect();
+    let positions = epochs.iter().map(|epoch| truth.position_ecef_km(*epoch).unwrap()).collect();
+
+    let orbit = orbit("G07", "cddis_cod_ult", epochs, positions);
+
+    (truth, orbit)
+}
+
+#[test]
+fn checksum_of_reference_tle() {
+    assert_eq!(tle_checksum("1 25544U 98067A   08264.51782528 -.00002182  00000-0 -11606-4 0  2927"), 7);
+    assert_eq!(tle_checksum("2 25544  51.6416 247.4627 0006703 130.5360 325.0288 15.72125391563537"), 7);
+}
+
+#[test]
+fn fit_recovers_mean_elements() {
+
+    let (truth, orbit) = sgp4_orbit();
+    let fit = fit_mean_elements(&orbit, identity(), &TleConfig::default()).unwrap();
+
+    assert_eq!(fit.elements.epoch, truth.epoch);
+    assert_eq!(fit.statistics.samples, 97);
+    assert!(fit.statistics.rms_km < 1e-3);
+    assert!((fit.elements.mean_motion_rev_day - truth.mean_motion_rev_day).abs() < 1e-8);
+    assert!((fit.elements.inclination_deg - truth.inclination_deg).abs() < 1e-6);
+    assert!((fit.elements.raan_deg - truth.raan_deg).abs() < 1e-6);
+    assert!((fit.elements.eccentricity - truth.eccentricity).abs() < 1e-8);
+}
+
+#[test]
+fn tle_and_omm_output() {
+
+    let (_, orbit) = sgp4_orbit();
+    let fit = fit_mean_elements(&orbit, identity(), &TleConfig::default()).unwrap();
+    let [line1, line2] = fit.elements.tle(&fit.identity).unwrap();
+
+    assert_eq!((line1.len(), line2.len()), (69, 69));
+    assert_eq!(line1.chars().last().unwrap().to_digit(10).unwrap(), tle_checksum(&line1));
+    assert_eq!(line2.chars().last().unwrap().to_digit(10).unwrap(), tle_checksum(&line2));
+    // 2024-05-02 00:00 GPST is 23:59:42 UTC on day 122
+    assert!(line1.starts_with("1 32260U 08012A   24122.99979167 "));
+    assert!(line2.starts_with("2 32260  55.0000 120.0000 0050000  40.0000  10.0000  2.00563000"));
+
+    let omm = fit.elements.omm(&fit.identity, &Epoch::from_gpst_seconds(orbit.valid_to));
+    assert!(omm.starts_with("CCSDS_OMM_VERS = 3.0\n"));
+    assert!(omm.contains("OBJECT_ID = 2008-012A\n"));
+    assert!(omm.contains("EPOCH = 2024-05-01T23:59:42.000000\n"));
+    assert!(omm.contains("NORAD_CAT_ID = 32260\n"));
+
+    // the rounded TLE reproduces the precise orbit to TLE precision
+    let tle = TwoLineElements { name: Some(fit.identity.name.clone()), line1, line2 };
+    let comparison = compare_tle(&tle, &orbit, orbit.valid_from, orbit.valid_to).unwrap();
+    assert_eq!(comparison.norad_id, 32260);
+    assert!((comparison.tle_epoch - fit.elements.epoch).abs() < 1e-3);
+    assert_eq!(comparison.statistics.samples, 289);
+    assert!(comparison.statistics.rms_km < 0.1);
+
+    let corrupted = TwoLineElements { line2: tle.line2.replace("55.0000", "55.0001"), ..tle };
+    assert!(compare_tle(&corrupted, &orbit, orbit.valid_from, orbit.valid_to).is_err());
+}
+
+#[test]
+fn alpha5_catalog_numbers() {
+
+    assert_eq!(alpha5(32260).unwrap(), "32260");
+    assert_eq!(alpha5(99_999).unwrap(), "99999");
+    assert_eq!(alpha5(100_000).unwrap(), "A0000");
+    // I and O are skipped
+    assert_eq!(alpha5(180_001).unwrap(), "J0001");
+    assert_eq!(alpha5(230_000).unwrap(), "P0000");
+    assert_eq!(alpha5(339_999).unwrap(), "Z9999");
+    assert!(alpha5(340_000).is_err());
+
+    let (_, orbit) = sgp4_orbit();
+    let fit = fit_mean_elements(&orbit, TleIdentity { norad_id: 340_000, ..identity() }, &TleConfig::default()).unwrap();
+    assert!(fit.elements.tle(&fit.identity).is_err());
+}
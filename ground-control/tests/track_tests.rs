@@ -0,0 +1,93 @@
+use anise::math::Vector3;
+use ground_control::{algo::{propagate::State, track::{ground_track, SatellitePosition}, util::latlon_to_ecef}, data::{geojson::{ground_tracks_geojson, positions_geojson}, kml::{ground_tracks_kml, positions_kml}, oem::EphemerisSegment}};
+use hifitime::{Epoch, TimeScale};
+
+const STEP_S:f64 = 300.0;
+
+// states at the given latitudes and longitudes at GNSS altitude, one step apart
+fn track_states(start:f64, points:&[(f64, f64)]) -> Vec<State> {
+    points.iter().enumerate()
+        .map(|(i, (lat, lon))| {
+            let (x, y, z) = latlon_to_ecef(lat.to_radians(), lon.to_radians(), 20_200_000.0);
+            State { epoch: start + i as f64 * STEP_S, pos_ecef_km: Vector3::new(x, y, z) / 1_000.0, vel_ecef_km_s: Vector3::zeros() }
+        })
+        .collect()
+}
+
+#[test]
+fn ground_track_splits_at_antimeridian() {
+
+    let states = track_states(0.0, &[(10.0, 170.0), (12.0, 175.0), (19.0, -178.0), (20.0, -173.0)]);
+    let parts = ground_track(&states);
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].len(), 3);
+    assert_eq!(parts[1].len(), 3);
+
+    let (end, start) = (parts[0][2], parts[1][0]);
+    assert!((end.lon_deg - 180.0).abs() < 1e-9);
+    assert!((start.lon_deg + 180.0).abs() < 1e-9);
+    // 5 of the 7 degrees to the crossing
+    assert!((end.lat_deg - 17.0).abs() < 1e-6);
+    assert!((end.epoch - STEP_S * (1.0 + 5.0 / 7.0)).abs() < 1e-6);
+    assert_eq!(end.epoch, start.epoch);
+
+    // westward crossing
+    let states = track_states(0.0, &[(0.0, -175.0), (0.0, 179.0)]);
+    let parts = ground_track(&states);
+    assert_eq!(parts.len(), 2);
+    assert!((parts[0][1].lon_deg + 180.0).abs() < 1e-9);
+    assert!((parts[1][0].lon_deg - 180.0).abs() < 1e-9);
+}
+
+#[test]
+fn ground_track_splits_at_gaps() {
+
+    let mut states = track_states(0.0, &[(0.0, 0.0), (1.0, 5.0), (2.0, 10.0), (3.0, 15.0), (4.0, 20.0)]);
+    states.remove(2);
+
+    let parts = ground_track(&states);
+    assert_eq!(parts.iter().map(|part| part.len()).collect::<Vec<usize>>(), vec![2, 2]);
+}
+
+#[test]
+fn geojson_and_kml_output() {
+
+    let start = Epoch::from_gregorian_at_midnight(2024, 5, 1, TimeScale::UTC).to_gpst_seconds();
+    let segment = EphemerisSegment {
+        satellite: "E12".parse().unwrap(),
+        states: track_states(start, &[(10.0, 170.0), (12.0, 175.0), (19.0, -178.0), (20.0, -173.0)]),
+    };
+
+    let tracks = ground_tracks_geojson(std::slice::from_ref(&segment));
+    let feature = &tracks["features"][0];
+    assert_eq!(feature["geometry"]["type"], "MultiLineString");
+    assert_eq!(feature["geometry"]["coordinates"].as_array().unwrap().len(), 2);
+    assert_eq!(feature["properties"]["satellite"], "E12");
+    assert_eq!(feature["properties"]["start"], "2024-05-01T00:00:00.000000Z");
+    assert_eq!(feature["properties"]["stroke"], "#2ca02c");
+
+    let kml = ground_tracks_kml(std::slice::from_ref(&segment));
+    assert!(kml.contains("<kml xmlns=\"http://www.opengis.net/kml/2.2\">"));
+    assert!(kml.contains("<Style id=\"E\">"));
+    assert!(kml.contains("<color>ff2ca02c</color>"));
+    assert_eq!(kml.matches("<LineString>").count(), 2);
+    assert!(kml.contains("<coordinates>170.000000,10.000000,0 175.000000,12.000000,0 180.000000,17.000000,0</coordinates>"));
+
+    let position = SatellitePosition {
+        satellite: segment.satellite,
+        source: "cddis_cod_fin".to_string(),
+        extrapolated: false,
+        point: ground_track(&segment.states)[0][1],
+    };
+
+    let points = positions_geojson(std::slice::from_ref(&position));
+    let coordinates = points["features"][0]["geometry"]["coordinates"].as_array().unwrap();
+    assert!((coordinates[0].as_f64().unwrap() - 175.0).abs() < 1e-9);
+    assert!((coordinates[1].as_f64().unwrap() - 12.0).abs() < 1e-9);
+    assert!((points["features"][0]["properties"]["alt_km"].as_f64().unwrap() - 20_200.0).abs() < 1e-6);
+
+    let kml = positions_kml(&[position]);
+    assert!(kml.contains("<Point><coordinates>175.000000,12.000000,0</coordinates></Point>"));
+    assert!(kml.contains("<TimeStamp><when>2024-05-01T00:05:00.000000Z</when></TimeStamp>"));
+}